@@ -0,0 +1,44 @@
+//! The Weegames engine, without the window loop.
+//!
+//! Games are described by [`wee::GameData`], usually loaded from JSON or put together
+//! with [`wee::GameData::builder`], and run one frame at a time with [`wee::Game`].
+//!
+//! ```
+//! use webgames::wee::{
+//!     Action, ButtonState, Game, GameData, Input, Instruction, Length, Mouse, MouseInteraction,
+//!     MouseOver, SerialiseObject, Trigger, Vec2, WinStatus,
+//! };
+//!
+//! # fn main() -> webgames::wee::WeeResult<()> {
+//! let button = SerialiseObject::builder()
+//!     .name("Button")
+//!     .position(Vec2::new(800.0, 450.0))
+//!     .instruction(Instruction {
+//!         triggers: vec![Trigger::Input(Input::Mouse {
+//!             over: MouseOver::Anywhere,
+//!             interaction: MouseInteraction::Button {
+//!                 state: ButtonState::Press,
+//!             },
+//!         })],
+//!         actions: vec![Action::Win],
+//!         cooldown: 0,
+//!     })
+//!     .build();
+//! let game_data = GameData::builder()
+//!     .object(button)
+//!     .length(Length::Seconds(4.0))
+//!     .intro_text("Click!")
+//!     .build();
+//!
+//! let mut game = Game::from_data(game_data);
+//! let clicks = std::iter::once(Mouse {
+//!     position: Vec2::new(800.0, 450.0),
+//!     state: ButtonState::Press,
+//! });
+//! let (status, _sounds) = game.run_headless(clicks, 240)?;
+//! assert!(matches!(status, WinStatus::Won | WinStatus::HasBeenWon));
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod wee;
@@ -1,6 +1,5 @@
 use macroquad::logging as log;
 use macroquad::prelude::*;
-use macroquad::texture;
 use macroquad::{
     audio::{self, PlaySoundParams, Sound},
     experimental::coroutines::{start_coroutine, Coroutine},
@@ -8,32 +7,133 @@ use macroquad::{
 
 use futures::future::join_all;
 use std::{
-    collections::{HashMap, HashSet},
+    cell::RefCell,
+    collections::{HashMap, HashSet, VecDeque},
     default::Default,
-    path::Path,
+    io::{Cursor, Read},
+    path::{Path, PathBuf},
     str,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
 };
 
-mod wee;
-
-use wee::*;
-
-const PROJECTION_WIDTH: f32 = 1600.0;
-const PROJECTION_HEIGHT: f32 = 900.0;
+use webgames::wee::{self, *};
 
 const DEFAULT_DIFFICULTY: u32 = 1;
 const DEFAULT_PLAYBACK_RATE: f32 = 1.0;
-const MAX_LIVES: i32 = 4;
+const DEFAULT_LIVES: i32 = 4;
 const INITIAL_PLAYBACK_RATE: f32 = 1.0;
 const PLAYBACK_RATE_INCREASE: f32 = 0.1;
 const PLAYBACK_RATE_MAX: f32 = 2.0;
-const UP_TO_DIFFICULTY_TWO: i32 = 20;
-const UP_TO_DIFFICULTY_THREE: i32 = 40;
 const BOSS_GAME_INTERVAL: i32 = 15;
-const INCREASE_SPEED_AFTER_GAMES: i32 = 5;
-const VOLUME: f32 = 0.5;
+const DEFAULT_VOLUME: f32 = 0.5;
+const VOLUME_STEP: f32 = 0.1;
+const DEFAULT_GAME_COOLDOWN: usize = 3;
+const MAX_LOAD_ATTEMPTS: u32 = 3;
+const MAX_DIFFICULTY: u32 = 3;
+const MAX_CONTINUES: u32 = 1;
+const PRACTICE_RESULT_FRAMES: u32 = 90;
+
+static MIRRORED: AtomicBool = AtomicBool::new(false);
+static IS_FULLSCREEN: AtomicBool = AtomicBool::new(true);
+static SHOW_DEBUG_OVERLAY: AtomicBool = AtomicBool::new(false);
+static SHOW_COLLISION_AREAS: AtomicBool = AtomicBool::new(false);
+
+// Somewhere other than the filesystem that game files can be read from
+trait ResourceSource: Send + Sync {
+    fn contains(&self, path: &Path) -> bool;
+
+    fn read(&self, path: &Path) -> WeeResult<Vec<u8>>;
+}
+
+// A zip archive standing in for the folder with the same name
+struct ZipSource {
+    folder: PathBuf,
+    archive: Mutex<zip::ZipArchive<Cursor<Vec<u8>>>>,
+}
+
+impl ZipSource {
+    fn new(folder: impl Into<PathBuf>, bytes: Vec<u8>) -> WeeResult<ZipSource> {
+        Ok(ZipSource {
+            folder: folder.into(),
+            archive: Mutex::new(zip::ZipArchive::new(Cursor::new(bytes))?),
+        })
+    }
+
+    fn game_files(&self) -> WeeResult<Vec<PathBuf>> {
+        let mut archive = self
+            .archive
+            .lock()
+            .map_err(|_| "Zip archive lock was poisoned")?;
+        let mut games = Vec::new();
+        for index in 0..archive.len() {
+            let name = archive.by_index(index)?.name().to_string();
+            if name.ends_with(".json") {
+                games.push(self.folder.join(name));
+            }
+        }
+        Ok(games)
+    }
+}
+
+impl ResourceSource for ZipSource {
+    fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.folder)
+    }
+
+    fn read(&self, path: &Path) -> WeeResult<Vec<u8>> {
+        let entry = path.strip_prefix(&self.folder)?;
+        let entry = entry.to_string_lossy().replace('\\', "/");
+        let mut archive = self
+            .archive
+            .lock()
+            .map_err(|_| "Zip archive lock was poisoned")?;
+        let mut file = archive.by_name(&entry)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+#[derive(Clone, Default)]
+struct Resources {
+    sources: Vec<Arc<dyn ResourceSource>>,
+}
+
+impl Resources {
+    async fn mount_zip(&mut self, filename: &str) -> WeeResult<()> {
+        let bytes = macroquad::file::load_file(filename).await?;
+        let folder = Path::new(filename).with_extension("");
+        let source = ZipSource::new(folder, bytes)?;
+        log::info!(
+            "Mounted {} with {} games",
+            filename,
+            source.game_files()?.len()
+        );
+        self.sources.push(Arc::new(source));
+        Ok(())
+    }
+
+    async fn load_bytes(&self, path: impl AsRef<Path>) -> WeeResult<Vec<u8>> {
+        let path = path.as_ref();
+        for source in &self.sources {
+            if source.contains(path) {
+                return source.read(path);
+            }
+        }
+        Ok(macroquad::file::load_file(&path.to_string_lossy()).await?)
+    }
+
+    async fn load_game_data(&self, filename: impl AsRef<Path>) -> WeeResult<GameData> {
+        let bytes = self.load_bytes(filename).await?;
+        GameData::from_json(str::from_utf8(&bytes)?)
+    }
+}
 
 async fn load_images<P: AsRef<Path>>(
+    resources: &Resources,
     image_files: &HashMap<String, String>,
     base_path: P,
 ) -> WeeResult<Images> {
@@ -52,13 +152,13 @@ async fn load_images<P: AsRef<Path>>(
     }
 
     for path in &paths {
-        loading_images.push(texture::load_texture(path));
+        loading_images.push(resources.load_bytes(path));
     }
 
     let textures: Vec<_> = join_all(loading_images).await;
 
-    for (key, texture) in image_files.keys().zip(textures) {
-        let texture = texture?;
+    for (key, bytes) in image_files.keys().zip(textures) {
+        let texture = Texture2D::from_file_with_format(&bytes?, None);
         texture.set_filter(macroquad::texture::FilterMode::Nearest);
         images.insert(key.to_string(), texture);
     }
@@ -67,82 +167,253 @@ async fn load_images<P: AsRef<Path>>(
 }
 
 async fn load_sounds(
-    sound_files: &HashMap<String, String>,
+    resources: &Resources,
+    sound_files: &HashMap<String, SoundLoadInfo>,
     base_path: impl AsRef<Path>,
 ) -> WeeResult<Sounds> {
     let base_path = base_path.as_ref().join("audio");
     let mut sounds = Sounds::new();
 
-    for (key, filename) in sound_files {
-        let path = base_path.join(&filename);
+    for (key, sound_info) in sound_files {
+        let path = base_path.join(&sound_info.filename);
 
-        let sound = macroquad::audio::load_sound(&path.to_str().unwrap()).await?;
+        let bytes = resources.load_bytes(&path).await?;
+        let sound = macroquad::audio::load_sound_from_bytes(&bytes).await?;
+        let length = sound_length(&bytes);
+        if length.is_none() {
+            log::warn!("Couldn't work out the length of {:?}", path);
+        }
 
-        sounds.insert(key.to_string(), sound);
+        sounds.insert(
+            key.to_string(),
+            LoadedSound {
+                sound,
+                volume: sound_info.volume,
+                length,
+            },
+        );
     }
     Ok(sounds)
 }
 
+// macroquad can't say how long a sound is so it's read from the file instead.
+// Only wav and ogg vorbis files are understood.
+fn sound_length(bytes: &[u8]) -> Option<f32> {
+    if bytes.starts_with(b"RIFF") {
+        wav_length(bytes)
+    } else if bytes.starts_with(b"OggS") {
+        ogg_length(bytes)
+    } else {
+        None
+    }
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    let b = bytes.get(at..at + 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn wav_length(bytes: &[u8]) -> Option<f32> {
+    let mut position = 12;
+    let mut byte_rate = None;
+    while position + 8 <= bytes.len() {
+        let id = &bytes[position..position + 4];
+        let size = read_u32(bytes, position + 4)? as usize;
+        if id == b"fmt " {
+            byte_rate = read_u32(bytes, position + 16).filter(|rate| *rate > 0);
+        } else if id == b"data" {
+            return Some(size as f32 / byte_rate? as f32);
+        }
+        position = position
+            .checked_add(8)?
+            .checked_add(size)?
+            .checked_add(size % 2)?;
+    }
+    None
+}
+
+// The length is the granule position (the sample count) of the last page divided by
+// the sample rate from the identification header
+fn ogg_length(bytes: &[u8]) -> Option<f32> {
+    let segments = *bytes.get(26)? as usize;
+    let packet = 27 + segments;
+    if bytes.get(packet..packet + 7)? != b"\x01vorbis" {
+        return None;
+    }
+    let sample_rate = read_u32(bytes, packet + 12).filter(|rate| *rate > 0)?;
+    let last_page = bytes.windows(4).rposition(|window| window == b"OggS")?;
+    let granule = bytes.get(last_page + 6..last_page + 14)?;
+    let mut granule_bytes = [0; 8];
+    granule_bytes.copy_from_slice(granule);
+    let samples = i64::from_le_bytes(granule_bytes);
+    if samples < 0 {
+        return None;
+    }
+    Some(samples as f32 / sample_rate as f32)
+}
+
+// The parts of a sound music needs, so tracks can be tested without an audio device
+trait SoundHandle: Copy + PartialEq {
+    fn play(self, params: PlaySoundParams);
+
+    fn stop(self);
+
+    fn set_volume(self, volume: f32);
+}
+
+impl SoundHandle for Sound {
+    fn play(self, params: PlaySoundParams) {
+        macroquad::audio::play_sound(self, params);
+    }
+
+    fn stop(self) {
+        macroquad::audio::stop_sound(self);
+    }
+
+    fn set_volume(self, volume: f32) {
+        macroquad::audio::set_sound_volume(self, volume);
+    }
+}
+
 #[derive(Clone)]
-struct Music {
-    data: Sound,
+struct Music<S: SoundHandle = Sound> {
+    data: S,
     looped: bool,
+    volume: f32,
 }
 
 async fn load_music(
-    music_file: &Option<SerialiseMusic>,
+    resources: &Resources,
+    music_files: &[SerialiseMusic],
     base_path: impl AsRef<Path>,
-) -> WeeResult<Option<Music>> {
+) -> WeeResult<Vec<Music>> {
     let base_path = base_path.as_ref().join("audio");
+    let mut tracks = Vec::new();
 
-    if let Some(music_info) = music_file {
+    for music_info in music_files {
         let path = base_path.join(&music_info.filename);
 
-        let sound = macroquad::audio::load_sound(&path.to_str().unwrap()).await?;
+        let bytes = resources.load_bytes(&path).await?;
+        let sound = macroquad::audio::load_sound_from_bytes(&bytes).await?;
 
-        Ok(Some(Music {
+        tracks.push(Music {
             data: sound,
             looped: music_info.looped,
-        }))
-    } else {
-        Ok(None)
+            volume: music_info.volume,
+        });
     }
+    Ok(tracks)
 }
 
 pub trait MusicPlayer {
     fn play(&self, playback_rate: f32, volume: f32);
 
     fn stop(&self);
+
+    fn set_volume(&self, volume: f32);
+
+    fn set_track_volume(&self, track: usize, volume: f32);
 }
 
-impl MusicPlayer for Option<Music> {
+impl<S: SoundHandle> MusicPlayer for Vec<Music<S>> {
     fn play(&self, playback_rate: f32, volume: f32) {
-        if let Some(music) = self {
+        for music in self {
+            music.data.play(PlaySoundParams {
+                looped: music.looped,
+                volume: volume * music.volume,
+                speed: playback_rate,
+            });
+        }
+    }
+
+    fn stop(&self) {
+        for music in self {
+            music.data.stop();
+        }
+    }
+
+    fn set_volume(&self, volume: f32) {
+        for track in 0..self.len() {
+            self.set_track_volume(track, volume);
+        }
+    }
+
+    fn set_track_volume(&self, track: usize, volume: f32) {
+        if let Some(music) = self.get(track) {
+            music.data.set_volume(volume * music.volume);
+        }
+    }
+}
+
+impl<S: SoundHandle> Drop for Music<S> {
+    fn drop(&mut self) {
+        self.data.stop();
+    }
+}
+
+const MUSIC_FADE_FRAMES: u32 = 15;
+
+struct MusicFade {
+    fading_out: Vec<Music>,
+    fading_in: Vec<(Sound, f32)>,
+    volume: f32,
+    frame: u32,
+}
+
+impl MusicFade {
+    fn new() -> MusicFade {
+        MusicFade {
+            fading_out: Vec::new(),
+            fading_in: Vec::new(),
+            volume: DEFAULT_VOLUME,
+            frame: MUSIC_FADE_FRAMES,
+        }
+    }
+
+    fn fade_in(&mut self, music: &[Music], playback_rate: f32, volume: f32) {
+        for music in music {
             macroquad::audio::play_sound(
                 music.data,
                 PlaySoundParams {
                     looped: music.looped,
-                    volume: volume,
+                    volume: 0.0,
                     speed: playback_rate,
                 },
             );
         }
+        // Hold on to the sound handles without taking ownership of the music
+        self.fading_in = music
+            .iter()
+            .map(|music| (music.data, music.volume))
+            .collect();
+        self.volume = volume;
+        self.frame = 0;
     }
 
-    fn stop(&self) {
-        if let Some(music) = self {
-            macroquad::audio::stop_sound(music.data);
-        }
+    fn fade_out(&mut self, music: Vec<Music>, volume: f32) {
+        self.fading_out = music;
+        self.fading_in = Vec::new();
+        self.volume = volume;
+        self.frame = 0;
     }
-}
 
-impl Drop for Music {
-    fn drop(&mut self) {
-        macroquad::audio::stop_sound(self.data);
+    fn update(&mut self) {
+        if self.frame >= MUSIC_FADE_FRAMES {
+            self.fading_out = Vec::new();
+            self.fading_in = Vec::new();
+            return;
+        }
+        self.frame += 1;
+        let progress = self.frame as f32 / MUSIC_FADE_FRAMES as f32;
+        self.fading_out.set_volume(self.volume * (1.0 - progress));
+        for (sound, track_volume) in &self.fading_in {
+            macroquad::audio::set_sound_volume(*sound, self.volume * track_volume * progress);
+        }
     }
 }
 
 async fn load_fonts(
+    resources: &Resources,
     font_files: &HashMap<String, FontLoadInfo>,
     base_path: impl AsRef<Path>,
 ) -> WeeResult<Fonts> {
@@ -152,7 +423,8 @@ async fn load_fonts(
     for (key, font_info) in font_files {
         let path = base_path.join(&font_info.filename);
 
-        let font = macroquad::text::load_ttf_font(path.to_str().unwrap()).await?;
+        let bytes = resources.load_bytes(&path).await?;
+        let font = macroquad::text::load_ttf_font_from_bytes(&bytes)?;
         fonts.insert(key.to_string(), (font, font_info.size as u16));
     }
     Ok(fonts)
@@ -160,26 +432,36 @@ async fn load_fonts(
 
 type Images = HashMap<String, Texture2D>;
 type Fonts = HashMap<String, (Font, u16)>;
-type Sounds = HashMap<String, Sound>;
+type Sounds = HashMap<String, LoadedSound>;
+
+#[derive(Copy, Clone)]
+struct LoadedSound {
+    sound: Sound,
+    volume: f32,
+    // In seconds
+    length: Option<f32>,
+}
 
 #[derive(Clone)]
 struct LoadedGameData {
     data: GameData,
     images: Images,
-    music: Option<Music>,
+    music: Vec<Music>,
     sounds: Sounds,
     fonts: Fonts,
 }
 
 impl LoadedGameData {
-    async fn load(filename: impl AsRef<Path>) -> WeeResult<LoadedGameData> {
-        let game_data = GameData::load(&filename).await.unwrap();
+    async fn load(resources: &Resources, filename: impl AsRef<Path>) -> WeeResult<LoadedGameData> {
+        let game_data = resources.load_game_data(&filename).await?;
+        game_data.validate()?;
         let base_path = filename.as_ref().parent().unwrap();
+        let asset_files = &game_data.asset_files;
         let data = LoadedGameData {
-            images: load_images(&game_data.asset_files.images, base_path).await?,
-            music: load_music(&game_data.asset_files.music, base_path).await?,
-            sounds: load_sounds(&game_data.asset_files.audio, base_path).await?,
-            fonts: load_fonts(&game_data.asset_files.fonts, base_path).await?,
+            images: load_images(resources, &asset_files.image_files(), base_path).await?,
+            music: load_music(resources, &asset_files.music, base_path).await?,
+            sounds: load_sounds(resources, &asset_files.audio, base_path).await?,
+            fonts: load_fonts(resources, &asset_files.fonts, base_path).await?,
             data: game_data,
         };
         Ok(data)
@@ -189,18 +471,22 @@ impl LoadedGameData {
 #[derive(Clone)]
 struct Assets {
     images: Images,
-    music: Option<Music>,
+    music: Vec<Music>,
     sounds: Sounds,
     fonts: Fonts,
 }
 
 impl Assets {
-    async fn load(asset_files: &AssetFiles, base_path: impl AsRef<Path>) -> WeeResult<Assets> {
+    async fn load(
+        resources: &Resources,
+        asset_files: &AssetFiles,
+        base_path: impl AsRef<Path>,
+    ) -> WeeResult<Assets> {
         let assets = Assets {
-            images: load_images(&asset_files.images, &base_path).await?,
-            music: load_music(&asset_files.music, &base_path).await?,
-            sounds: load_sounds(&asset_files.audio, &base_path).await?,
-            fonts: load_fonts(&asset_files.fonts, &base_path).await?,
+            images: load_images(resources, &asset_files.image_files(), &base_path).await?,
+            music: load_music(resources, &asset_files.music, &base_path).await?,
+            sounds: load_sounds(resources, &asset_files.audio, &base_path).await?,
+            fonts: load_fonts(resources, &asset_files.fonts, &base_path).await?,
         };
         Ok(assets)
     }
@@ -208,8 +494,171 @@ impl Assets {
     fn stop_sounds(&self) {
         self.music.stop();
 
-        for sound in self.sounds.values() {
-            audio::stop_sound(*sound);
+        self.stop_sound_effects();
+    }
+
+    fn stop_sound_effects(&self) {
+        for loaded in self.sounds.values() {
+            audio::stop_sound(loaded.sound);
+        }
+    }
+
+    fn unload(self) {
+        self.stop_sounds();
+        for texture in self.images.values() {
+            texture.delete();
+        }
+        for loaded in self.sounds.values() {
+            audio::delete_sound(loaded.sound);
+        }
+        for music in &self.music {
+            audio::delete_sound(music.data);
+        }
+        for (font, _) in self.fonts.values() {
+            macroquad::text::delete_font(*font);
+        }
+    }
+
+    // Unloads what isn't also used by the assets replacing these
+    fn unload_replaced(self, replacement: &Assets) {
+        let replaced = Assets {
+            images: self
+                .images
+                .into_iter()
+                .filter(|(_, texture)| !replacement.images.values().any(|other| other == texture))
+                .collect(),
+            music: self
+                .music
+                .into_iter()
+                .filter(|music| {
+                    !replacement
+                        .music
+                        .iter()
+                        .any(|other| other.data == music.data)
+                })
+                .collect(),
+            sounds: self
+                .sounds
+                .into_iter()
+                .filter(|(_, loaded)| {
+                    !replacement
+                        .sounds
+                        .values()
+                        .any(|other| other.sound == loaded.sound)
+                })
+                .collect(),
+            fonts: self
+                .fonts
+                .into_iter()
+                .filter(|(_, (font, _))| {
+                    !replacement.fonts.values().any(|(other, _)| other == font)
+                })
+                .collect(),
+        };
+        replaced.unload();
+    }
+}
+
+const MAX_CACHED_GAMES: usize = 4;
+
+// System games stay loaded, other games are kept until they are the least recently played
+struct AssetCache {
+    pinned: HashMap<&'static str, Assets>,
+    recent: VecDeque<(&'static str, Assets)>,
+    capacity: usize,
+}
+
+impl AssetCache {
+    fn new(pinned: HashMap<&'static str, Assets>, capacity: usize) -> AssetCache {
+        AssetCache {
+            pinned,
+            recent: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    // Looking up a recent entry marks it as the most recently used
+    fn get(&mut self, filename: &str) -> Option<&Assets> {
+        if self.pinned.contains_key(filename) {
+            return self.pinned.get(filename);
+        }
+        let index = self.recent.iter().position(|(name, _)| *name == filename)?;
+        let entry = self.recent.remove(index)?;
+        self.recent.push_back(entry);
+        self.recent.back().map(|(_, assets)| assets)
+    }
+
+    fn insert(&mut self, filename: &'static str, assets: Assets) {
+        if self.pinned.contains_key(filename) {
+            return;
+        }
+        // Cached assets are cloned out to be played, so a replaced entry can share its handles
+        if let Some(index) = self.recent.iter().position(|(name, _)| *name == filename) {
+            if let Some((_, replaced)) = self.recent.remove(index) {
+                replaced.unload_replaced(&assets);
+            }
+        }
+        self.recent.push_back((filename, assets));
+        while self.recent.len() > self.capacity {
+            if let Some((name, assets)) = self.recent.pop_front() {
+                log::debug!("Unloading {}", name);
+                assets.unload();
+            }
+        }
+    }
+}
+
+const MAX_NAME_LENGTH: usize = 8;
+
+#[derive(Debug, Clone, Default)]
+struct HighScore {
+    name: String,
+    score: i32,
+}
+
+impl HighScore {
+    fn text(&self) -> String {
+        if self.name.is_empty() {
+            self.score.to_string()
+        } else {
+            format!("{} {}", self.name, self.score)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct HighScores {
+    entries: [HighScore; 3],
+}
+
+impl HighScores {
+    // Returns the index of the new entry if the score made it onto the table.
+    // Ties stay below the older score and runs that scored nothing aren't entered.
+    fn insert(&mut self, score: i32) -> Option<usize> {
+        if score <= 0 {
+            return None;
+        }
+        let position = self.entries.iter().position(|entry| score > entry.score)?;
+        for i in (position + 1..self.entries.len()).rev() {
+            self.entries[i] = self.entries[i - 1].clone();
+        }
+        self.entries[position] = HighScore {
+            name: String::new(),
+            score,
+        };
+        Some(position)
+    }
+
+    fn set_name(&mut self, index: usize, name: &str) {
+        self.entries[index].name = name.trim().to_string();
+    }
+}
+
+// Adds typed characters to a high score name, ignoring any that won't fit
+fn type_name(name: &mut String, typed: impl Iterator<Item = char>) {
+    for c in typed {
+        if (c.is_alphanumeric() || c == ' ') && name.chars().count() < MAX_NAME_LENGTH {
+            name.push(c.to_ascii_uppercase());
         }
     }
 }
@@ -228,28 +677,60 @@ struct Progress {
     difficulty: u32,
     last_game: Option<LastGame>,
     boss_playback_rate: f32,
+    continues_used: u32,
+    max_lives: i32,
 }
 
 impl Progress {
-    fn new() -> Progress {
+    fn new(max_lives: i32) -> Progress {
         Progress {
             playback_rate: INITIAL_PLAYBACK_RATE,
             score: 0,
-            lives: MAX_LIVES,
+            lives: max_lives,
             difficulty: DEFAULT_DIFFICULTY,
             last_game: None,
             boss_playback_rate: INITIAL_PLAYBACK_RATE,
+            continues_used: 0,
+            max_lives,
         }
     }
 
-    fn update(&mut self, has_won: bool, is_boss_game: bool) {
-        self.score += 1;
-        if self.score % INCREASE_SPEED_AFTER_GAMES == 0 {
+    fn from_settings(settings: &Settings) -> Progress {
+        Progress::new(settings.lives.unwrap_or(DEFAULT_LIVES).max(1))
+    }
+
+    fn can_continue(&self) -> bool {
+        self.continues_used < MAX_CONTINUES
+    }
+
+    // Keeps the score and difficulty but starts again from full lives and speed
+    fn continue_run(&mut self) {
+        self.lives = self.max_lives;
+        self.playback_rate = INITIAL_PLAYBACK_RATE;
+        self.boss_playback_rate = INITIAL_PLAYBACK_RATE;
+        self.last_game = None;
+        self.continues_used += 1;
+    }
+
+    fn update(
+        &mut self,
+        has_won: bool,
+        is_boss_game: bool,
+        lives_adjustment: i32,
+        score_adjustment: i32,
+        curve: &DifficultyCurve,
+    ) {
+        let lives_before = self.lives;
+
+        self.score += 1 + score_adjustment;
+        if curve.increase_speed_after_games > 0
+            && self.score % curve.increase_speed_after_games == 0
+        {
             self.playback_rate += PLAYBACK_RATE_INCREASE;
         }
-        if self.score >= UP_TO_DIFFICULTY_THREE {
+        if self.score >= curve.up_to_difficulty_three {
             self.difficulty = 3;
-        } else if self.score >= UP_TO_DIFFICULTY_TWO {
+        } else if self.score >= curve.up_to_difficulty_two {
             self.difficulty = 2;
         }
         self.playback_rate = self.playback_rate.min(PLAYBACK_RATE_MAX);
@@ -262,10 +743,12 @@ impl Progress {
             self.lives -= 1;
         }
 
-        let was_life_gained = is_boss_game && has_won && self.lives < MAX_LIVES;
-        if was_life_gained {
+        if is_boss_game && has_won {
             self.lives += 1;
         }
+        self.lives = (self.lives + lives_adjustment).clamp(0, self.max_lives);
+
+        let was_life_gained = self.lives > lives_before;
         self.last_game = Some(LastGame {
             has_won,
             was_life_gained,
@@ -328,118 +811,238 @@ fn draw_rectangle_ex(
     }
 }
 
-fn draw_game(game: &Game, images: &Images, fonts: &Fonts, intro_font: &Font) {
+fn set_shaking_camera(shake: ScreenShake) {
+    // Zoom in by the shake intensity so the jittered view never goes past the edges
+    let margin = shake.current_intensity().min(PROJECTION_HEIGHT / 4.0);
+    let width = PROJECTION_WIDTH - margin * 2.0;
+    let height = PROJECTION_HEIGHT - margin * 2.0 * PROJECTION_HEIGHT / PROJECTION_WIDTH;
+    let jitter = |max_offset: f32| {
+        if max_offset > 0.0 {
+            rand::gen_range(-max_offset, max_offset)
+        } else {
+            0.0
+        }
+    };
+    let x = ((PROJECTION_WIDTH - width) / 2.0 + jitter(margin))
+        .max(0.0)
+        .min(PROJECTION_WIDTH - width);
+    let y = ((PROJECTION_HEIGHT - height) / 2.0 + jitter(margin))
+        .max(0.0)
+        .min(PROJECTION_HEIGHT - height);
+    set_projection_camera(macroquad::math::Rect::new(x, y, width, height));
+}
+
+// The largest area with the projection's aspect ratio that fits centred in the window
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Letterbox {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+impl Letterbox {
+    fn fit(screen_width: f32, screen_height: f32) -> Letterbox {
+        let scale = (screen_width / PROJECTION_WIDTH).min(screen_height / PROJECTION_HEIGHT);
+        let width = PROJECTION_WIDTH * scale;
+        let height = PROJECTION_HEIGHT * scale;
+        Letterbox {
+            x: (screen_width - width) / 2.0,
+            y: (screen_height - height) / 2.0,
+            width,
+            height,
+        }
+    }
+
+    fn current() -> Letterbox {
+        Letterbox::fit(
+            macroquad::window::screen_width(),
+            macroquad::window::screen_height(),
+        )
+    }
+
+    fn to_projection(self, x: f32, y: f32) -> wee::Vec2 {
+        wee::Vec2::new(
+            (x - self.x) / self.width * PROJECTION_WIDTH,
+            (y - self.y) / self.height * PROJECTION_HEIGHT,
+        )
+    }
+
+    fn viewport(self) -> (i32, i32, i32, i32) {
+        (
+            self.x.round() as i32,
+            self.y.round() as i32,
+            self.width.round() as i32,
+            self.height.round() as i32,
+        )
+    }
+}
+
+fn set_projection_camera(rect: macroquad::math::Rect) {
+    let mut camera = macroquad::camera::Camera2D::from_display_rect(rect);
+    camera.viewport = Some(Letterbox::current().viewport());
+    macroquad::camera::set_camera(&camera);
+}
+
+fn sprite_source(sprite: &Sprite) -> Option<macroquad::math::Rect> {
+    match sprite {
+        Sprite::ImageRegion { source, .. } => Some(macroquad::math::Rect::new(
+            source.min.x,
+            source.min.y,
+            source.width(),
+            source.height(),
+        )),
+        _ => None,
+    }
+}
+
+fn is_mirrored() -> bool {
+    MIRRORED.load(Ordering::Relaxed)
+}
+
+// Swaps between game and screen x positions, the mirroring is its own inverse
+fn mirror_x(x: f32) -> f32 {
+    if is_mirrored() {
+        PROJECTION_WIDTH - x
+    } else {
+        x
+    }
+}
+
+fn mirror_point(point: wee::Vec2) -> wee::Vec2 {
+    wee::Vec2::new(mirror_x(point.x), point.y)
+}
+
+fn mirror_angle(angle: f32) -> f32 {
+    if is_mirrored() {
+        -angle
+    } else {
+        angle
+    }
+}
+
+fn background_rect(area: wee::AABB) -> macroquad::math::Rect {
+    macroquad::math::Rect::new(
+        mirror_x(area.min.x).min(mirror_x(area.max.x)),
+        area.min.y,
+        area.width(),
+        area.height(),
+    )
+}
+
+fn draw_game(game: &Game, images: &Images, fonts: &Fonts, intro_font: &Font, playback_rate: f32) {
+    clear_background(BLACK);
+    set_shaking_camera(game.shake);
+
     // Draw background
     for part in &game.background {
+        let rect = background_rect(part.area);
         match &part.sprite {
-            Sprite::Image { name } => {
+            Sprite::Image { name } | Sprite::ImageRegion { name, .. } => {
                 let params = macroquad::texture::DrawTextureParams {
-                    dest_size: Some(macroquad::math::Vec2::new(
-                        part.area.width(),
-                        part.area.height(),
-                    )),
-                    source: None,
+                    dest_size: Some(macroquad::math::Vec2::new(rect.w, rect.h)),
+                    source: sprite_source(&part.sprite),
                     rotation: 0.0,
                     pivot: None,
-                    flip_x: false,
+                    flip_x: is_mirrored(),
                     flip_y: false,
                 };
                 draw_texture_ex(
                     images[name],
-                    part.area.min.x,
-                    part.area.min.y,
+                    rect.x,
+                    rect.y,
                     macroquad::color::WHITE,
                     params,
                 );
             }
             Sprite::Colour(colour) => macroquad::shapes::draw_rectangle(
-                part.area.min.x,
-                part.area.min.y,
-                part.area.max.x,
-                part.area.max.y,
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h,
                 macroquad::color::Color::new(colour.r, colour.g, colour.b, colour.a),
             ),
         }
     }
 
     // Draw Objects
-    let mut layers: Vec<u8> = game.objects.values().map(|o| o.layer).collect();
-    layers.sort();
-    layers.dedup();
-    layers.reverse();
-    for layer in layers.into_iter() {
-        for (key, object) in game.objects.iter() {
-            if object.layer == layer {
-                match &object.sprite {
-                    Sprite::Image { name } => {
-                        let origin = object.origin_in_world();
-                        let origin = macroquad::math::Vec2::new(origin.x, origin.y);
-                        let params = macroquad::texture::DrawTextureParams {
-                            dest_size: Some(macroquad::math::Vec2::new(
-                                object.size.width,
-                                object.size.height,
-                            )),
-                            source: None,
-                            rotation: object.angle.to_radians(),
-                            pivot: Some(origin),
-                            flip_x: object.flip.horizontal,
-                            flip_y: object.flip.vertical,
-                        };
-                        draw_texture_ex(
-                            images[name],
-                            object.position.x - object.size.width / 2.0,
-                            object.position.y - object.size.height / 2.0,
-                            macroquad::color::WHITE,
-                            params,
-                        );
-                    }
-                    Sprite::Colour(colour) => {
-                        let origin = object.origin_in_world();
-                        let origin = macroquad::math::Vec2::new(origin.x, origin.y);
-                        draw_rectangle_ex(
-                            Color::new(colour.r, colour.g, colour.b, colour.a),
-                            object.position.x - object.size.width / 2.0,
-                            object.position.y - object.size.height / 2.0,
+    for (key, object) in game.draw_order() {
+        if object.is_visible() {
+            let position = mirror_point(object.position);
+            let angle = mirror_angle(object.angle);
+            match &object.sprite {
+                Sprite::Image { name } | Sprite::ImageRegion { name, .. } => {
+                    let origin = mirror_point(object.origin_in_world());
+                    let origin = macroquad::math::Vec2::new(origin.x, origin.y);
+                    let params = macroquad::texture::DrawTextureParams {
+                        dest_size: Some(macroquad::math::Vec2::new(
                             object.size.width,
                             object.size.height,
-                            object.angle.to_radians(),
-                            Some(origin),
-                        );
-                    }
+                        )),
+                        source: sprite_source(&object.sprite),
+                        rotation: angle.to_radians(),
+                        pivot: Some(origin),
+                        flip_x: object.flip.horizontal != is_mirrored(),
+                        flip_y: object.flip.vertical,
+                    };
+                    draw_texture_ex(
+                        images[name],
+                        position.x - object.size.width / 2.0,
+                        position.y - object.size.height / 2.0,
+                        Color::new(
+                            object.tint.r,
+                            object.tint.g,
+                            object.tint.b,
+                            object.tint.a * object.alpha,
+                        ),
+                        params,
+                    );
                 }
-
-                if game.drawn_text.contains_key(key) {
-                    let colour = game.drawn_text[key].colour;
-                    let colour = Color::new(colour.r, colour.g, colour.b, colour.a);
-                    let size = macroquad::text::measure_text(
-                        &game.drawn_text[key].text,
-                        Some(fonts[&game.drawn_text[key].font].0),
-                        fonts[&game.drawn_text[key].font].1,
-                        1.0,
+                Sprite::Colour(colour) => {
+                    let origin = mirror_point(object.origin_in_world());
+                    let origin = macroquad::math::Vec2::new(origin.x, origin.y);
+                    draw_rectangle_ex(
+                        Color::new(colour.r, colour.g, colour.b, colour.a * object.alpha),
+                        position.x - object.size.width / 2.0,
+                        position.y - object.size.height / 2.0,
+                        object.size.width,
+                        object.size.height,
+                        angle.to_radians(),
+                        Some(origin),
                     );
-                    let position = match game.drawn_text[key].justify {
-                        JustifyText::Left => wee::Vec2::new(
-                            object.position.x - object.half_width(),
-                            object.position.y, // - size.height / 1.25,
-                        ),
-                        JustifyText::Centre => wee::Vec2::new(
-                            object.position.x - size.width / 2.0,
-                            object.position.y, // - size.height / 2.0,
-                        ),
+                }
+            }
+
+            if let Some(drawn_text) = game.drawn_text.get(key) {
+                let colour = drawn_text.colour;
+                let colour = Color::new(colour.r, colour.g, colour.b, colour.a);
+                let (font, font_size) = font_or_fallback(fonts, &drawn_text.font, *intro_font);
+                let measure = |line: &str| measure_with_font(line, font, font_size);
+                let lines = text_lines(drawn_text, object, |line| measure(line).width);
+                let font_scale = match drawn_text.resize {
+                    TextResize::MatchObject => {
+                        scale_to_fit(measure_lines(&lines, font_size, measure), object.size)
+                    }
+                    TextResize::MatchText => 1.0,
+                };
+                let line_height = font_size as f32 * font_scale;
+                let first_line_y = position.y - line_height * (lines.len() - 1) as f32 / 2.0;
+                for (i, line) in lines.iter().enumerate() {
+                    let size =
+                        macroquad::text::measure_text(line, Some(font), font_size, font_scale);
+                    let y = first_line_y + line_height * i as f32;
+                    let position = match drawn_text.justify {
+                        JustifyText::Left => wee::Vec2::new(position.x - object.half_width(), y),
+                        JustifyText::Centre => wee::Vec2::new(position.x - size.width / 2.0, y),
                     };
                     let params = macroquad::text::TextParams {
-                        font: fonts[&game.drawn_text[key].font].0,
-                        font_size: fonts[&game.drawn_text[key].font].1,
-                        font_scale: 1.0,
+                        font,
+                        font_size,
+                        font_scale,
                         font_scale_aspect: 1.0,
                         color: colour,
                     };
-                    macroquad::text::draw_text_ex(
-                        &game.drawn_text[key].text,
-                        position.x,
-                        position.y,
-                        params,
-                    );
+                    macroquad::text::draw_text_ex(line, position.x, position.y, params);
                 }
             }
         }
@@ -480,66 +1083,550 @@ fn draw_game(game: &Game, images: &Images, fonts: &Fonts, intro_font: &Font) {
             params,
         );
     }
-}
-
-fn update_frame(game: &mut Game, assets: &Assets, playback_rate: f32) -> WeeResult<()> {
-    let position = macroquad::input::mouse_position();
-    let position = wee::Vec2::new(position.0 as f32, position.1 as f32);
-    let position = wee::Vec2::new(
-        position.x / macroquad::window::screen_width() as f32 * PROJECTION_WIDTH,
-        position.y / macroquad::window::screen_height() as f32 * PROJECTION_HEIGHT,
-    );
-    let mouse = Mouse {
-        position,
-        state: if macroquad::input::is_mouse_button_pressed(MouseButton::Left) {
-            ButtonState::Press
-        } else if macroquad::input::is_mouse_button_released(MouseButton::Left) {
-            ButtonState::Release
-        } else if macroquad::input::is_mouse_button_down(MouseButton::Left) {
-            ButtonState::Down
-        } else {
-            ButtonState::Up
-        },
-    };
 
-    let played_sounds = game.update(&mouse)?;
+    draw_cursor(game);
 
-    for played_sound in played_sounds {
-        audio::play_sound(
-            assets.sounds[&played_sound],
-            PlaySoundParams {
-                looped: false,
-                volume: VOLUME,
-                speed: playback_rate,
-            },
-        );
+    if SHOW_COLLISION_AREAS.load(Ordering::Relaxed) {
+        draw_collision_areas(game);
     }
+    if SHOW_DEBUG_OVERLAY.load(Ordering::Relaxed) {
+        draw_debug_overlay(game, intro_font, playback_rate);
+    }
+}
 
-    if game.has_music_finished {
-        assets.music.stop();
+// Called once per rendered frame, before drawing
+fn handle_hotkeys() {
+    if macroquad::input::is_key_pressed(KeyCode::F12) {
+        save_screenshot();
     }
+    if macroquad::input::is_key_pressed(KeyCode::F3) {
+        SHOW_DEBUG_OVERLAY.fetch_xor(true, Ordering::Relaxed);
+    }
+    if macroquad::input::is_key_pressed(KeyCode::F4) {
+        SHOW_COLLISION_AREAS.fetch_xor(true, Ordering::Relaxed);
+    }
+    let is_alt_down = macroquad::input::is_key_down(KeyCode::LeftAlt)
+        || macroquad::input::is_key_down(KeyCode::RightAlt);
+    if is_alt_down && macroquad::input::is_key_pressed(KeyCode::Enter) {
+        toggle_fullscreen();
+    }
+}
 
-    game.status.current = game.status.next_frame;
-    game.status.next_frame = match game.status.next_frame {
-        WinStatus::HasBeenWon => WinStatus::Won,
-        WinStatus::HasBeenLost => WinStatus::Lost,
-        _ => game.status.next_frame,
+fn pointer_position() -> wee::Vec2 {
+    if let Some(position) = gamepad::cursor() {
+        return position;
+    }
+    let position = match macroquad::input::touches().first() {
+        Some(touch) => (touch.position.x, touch.position.y),
+        None => macroquad::input::mouse_position(),
     };
-    game.frames.ran += 1;
+    Letterbox::current().to_projection(position.0, position.1)
+}
 
-    Ok(())
+// macroquad can't change the system cursor so a pointer is drawn in its place
+fn draw_cursor(game: &Game) {
+    let position = pointer_position();
+    let cursor = game.cursor_at(mirror_point(position));
+    let is_virtual = gamepad::cursor().is_some();
+    macroquad::input::show_mouse(cursor == CursorStyle::Default && !is_virtual);
+    if cursor == CursorStyle::Pointer {
+        macroquad::shapes::draw_circle(position.x, position.y, 12.0, BLACK);
+        macroquad::shapes::draw_circle(position.x, position.y, 9.0, WHITE);
+    } else if is_virtual {
+        macroquad::shapes::draw_circle(position.x, position.y, 8.0, BLACK);
+        macroquad::shapes::draw_circle(position.x, position.y, 5.0, WHITE);
+    }
 }
 
-#[derive(Debug)]
-struct GamesList {
-    games: Vec<&'static str>,
-    bosses: Vec<&'static str>,
-    next: Vec<&'static str>,
-    directory: String,
+fn draw_collision_areas(game: &Game) {
+    const THICKNESS: f32 = 2.0;
+    let outline_colour = Color::new(0.0, 1.0, 0.0, 0.8);
+    let origin_colour = Color::new(1.0, 0.0, 1.0, 0.8);
+    for (_, object) in game.draw_order() {
+        match object.collision_outline() {
+            CollisionOutline::Polygon(points) => {
+                let points: Vec<wee::Vec2> = points.into_iter().map(mirror_point).collect();
+                for (i, start) in points.iter().enumerate() {
+                    let end = points[(i + 1) % points.len()];
+                    macroquad::shapes::draw_line(
+                        start.x,
+                        start.y,
+                        end.x,
+                        end.y,
+                        THICKNESS,
+                        outline_colour,
+                    );
+                }
+            }
+            CollisionOutline::Circle { centre, radius } => {
+                let centre = mirror_point(centre);
+                macroquad::shapes::draw_circle_lines(
+                    centre.x,
+                    centre.y,
+                    radius,
+                    THICKNESS,
+                    outline_colour,
+                );
+            }
+        }
+        let origin = mirror_point(object.origin_in_world());
+        macroquad::shapes::draw_circle(origin.x, origin.y, 4.0, origin_colour);
+    }
+
+    let mouse = pointer_position();
+    macroquad::shapes::draw_circle_lines(
+        mouse.x,
+        mouse.y,
+        MOUSE_HIT_RADIUS.max(THICKNESS),
+        THICKNESS,
+        Color::new(1.0, 1.0, 0.0, 0.8),
+    );
 }
 
-impl GamesList {
-    fn from_directory(all_games: &HashMap<&'static str, GameData>, directory: String) -> GamesList {
+// Crops the screen to the letterboxed projection so the bars aren't saved
+#[cfg(not(target_arch = "wasm32"))]
+fn crop_to_letterbox(screen: &Image, letterbox: Letterbox) -> Image {
+    let (x, y, width, height) = letterbox.viewport();
+    let x = x.clamp(0, screen.width as i32) as usize;
+    let width = width.clamp(0, screen.width as i32 - x as i32) as usize;
+    // Screen data rows go from the bottom of the window up
+    let bottom = (screen.height as i32 - y - height).clamp(0, screen.height as i32) as usize;
+    let height = height.clamp(0, screen.height as i32 - bottom as i32) as usize;
+    let mut bytes = Vec::with_capacity(width * height * 4);
+    for row in bottom..bottom + height {
+        let start = (row * screen.width as usize + x) * 4;
+        bytes.extend_from_slice(&screen.bytes[start..start + width * 4]);
+    }
+    Image {
+        bytes,
+        width: width as u16,
+        height: height as u16,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_screenshot_in(directory: &Path, screenshot: &Image) -> PathBuf {
+    let timestamp = (macroquad::miniquad::date::now() * 1000.0) as u64;
+    let path = directory.join(format!("screenshot-{}.png", timestamp));
+    screenshot.export_png(&path.to_string_lossy());
+    path
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_screenshot() {
+    let screenshot =
+        crop_to_letterbox(&macroquad::texture::get_screen_data(), Letterbox::current());
+    let path = save_screenshot_in(Path::new("."), &screenshot);
+    log::info!("Saved screenshot to {}", path.display());
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_screenshot() {
+    log::warn!("Screenshots aren't supported in the browser yet");
+}
+
+fn toggle_fullscreen() {
+    let fullscreen = !IS_FULLSCREEN.fetch_xor(true, Ordering::Relaxed);
+    log::info!("Fullscreen: {}", fullscreen);
+    unsafe {
+        macroquad::window::get_internal_gl()
+            .quad_context
+            .set_fullscreen(fullscreen);
+    }
+}
+
+fn draw_debug_overlay(game: &Game, intro_font: &Font, playback_rate: f32) {
+    macroquad::camera::set_default_camera();
+
+    let lines = [
+        format!("FPS: {}", macroquad::time::get_fps()),
+        format!("Playback rate: {:.2}", playback_rate),
+        format!("Frames ran: {}", game.frames.ran),
+        format!("Steps taken: {}", game.frames.steps_taken),
+        format!("Objects: {}", game.objects.len()),
+    ];
+    const FONT_SIZE: u16 = 24;
+    macroquad::shapes::draw_rectangle(
+        0.0,
+        0.0,
+        260.0,
+        FONT_SIZE as f32 * (lines.len() as f32 + 0.5),
+        Color::new(0.0, 0.0, 0.0, 0.6),
+    );
+    for (i, line) in lines.iter().enumerate() {
+        let params = macroquad::text::TextParams {
+            font: *intro_font,
+            font_size: FONT_SIZE,
+            font_scale: 1.0,
+            font_scale_aspect: 1.0,
+            color: WHITE,
+        };
+        macroquad::text::draw_text_ex(line, 8.0, FONT_SIZE as f32 * (i as f32 + 1.0), params);
+    }
+
+    set_projection_camera(macroquad::math::Rect::new(
+        0.0,
+        0.0,
+        PROJECTION_WIDTH,
+        PROJECTION_HEIGHT,
+    ));
+}
+
+// Lines are broken at max_width before any MatchObject scaling is applied
+fn wrap_text(text: &str, max_width: f32, width: impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let joined = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if width(&joined) <= max_width {
+            line = joined;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        for c in word.chars() {
+            line.push(c);
+            if width(&line) > max_width && line.chars().count() > 1 {
+                line.pop();
+                lines.push(std::mem::take(&mut line));
+                line.push(c);
+            }
+        }
+    }
+    lines.push(line);
+    lines
+}
+
+fn text_lines(drawn_text: &DrawnText, object: &Object, width: impl Fn(&str) -> f32) -> Vec<String> {
+    let text = object.format_text(&drawn_text.text);
+    match drawn_text.max_width {
+        Some(max_width) => wrap_text(&text, max_width, width),
+        None => vec![text],
+    }
+}
+
+fn measure_with_font(text: &str, font: Font, font_size: u16) -> wee::Size {
+    let size = macroquad::text::measure_text(text, Some(font), font_size, 1.0);
+    wee::Size::new(size.width, size.height)
+}
+
+fn measure_lines(
+    lines: &[String],
+    font_size: u16,
+    measure: impl Fn(&str) -> wee::Size,
+) -> wee::Size {
+    let mut size = wee::Size {
+        width: 0.0,
+        height: 0.0,
+    };
+    for (i, line) in lines.iter().enumerate() {
+        let line_size = measure(line);
+        size.width = size.width.max(line_size.width);
+        if i == 0 {
+            size.height = line_size.height;
+        } else {
+            size.height += font_size as f32;
+        }
+    }
+    size
+}
+
+// The font scale for MatchObject, text is shrunk or grown until it touches the object's edges
+fn scale_to_fit(text_size: wee::Size, object_size: wee::Size) -> f32 {
+    if text_size.width > 0.0 && text_size.height > 0.0 {
+        (object_size.width / text_size.width).min(object_size.height / text_size.height)
+    } else {
+        1.0
+    }
+}
+
+const FALLBACK_FONT_SIZE: u16 = 48;
+
+thread_local! {
+    static MISSING_FONTS: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
+}
+
+// Text with an unknown font is drawn with the intro font, the warning is only logged once
+fn warn_missing_font(name: &str) {
+    MISSING_FONTS.with(|missing| {
+        if missing.borrow_mut().insert(name.to_string()) {
+            log::warn!("Font {} isn't loaded, using the default font", name);
+        }
+    });
+}
+
+fn font_or_fallback<F: Copy>(
+    fonts: &HashMap<String, (F, u16)>,
+    name: &str,
+    fallback: F,
+) -> (F, u16) {
+    fonts.get(name).copied().unwrap_or_else(|| {
+        warn_missing_font(name);
+        (fallback, FALLBACK_FONT_SIZE)
+    })
+}
+
+fn resize_objects_to_text<F: Copy>(
+    game: &mut Game,
+    fonts: &HashMap<String, (F, u16)>,
+    measure: impl Fn(&str, F, u16) -> wee::Size,
+) {
+    for (key, drawn_text) in game.drawn_text.iter() {
+        if drawn_text.resize == TextResize::MatchText {
+            let (font, font_size) = match fonts.get(&drawn_text.font) {
+                Some(font) => *font,
+                None => {
+                    warn_missing_font(&drawn_text.font);
+                    continue;
+                }
+            };
+            if let Some(object) = game.objects.get_mut(key) {
+                let measure = |line: &str| measure(line, font, font_size);
+                let lines = text_lines(drawn_text, object, |line| measure(line).width);
+                let size = measure_lines(&lines, font_size, measure);
+                if size.width > 0.0 && size.height > 0.0 {
+                    if drawn_text.justify == JustifyText::Left {
+                        let left = object.position.x - object.half_width();
+                        object.position.x = left + size.width / 2.0;
+                    }
+                    object.size = size;
+                }
+            }
+        }
+    }
+}
+
+const EDGE_ATTENUATION: f32 = 0.5;
+const DEFAULT_MAX_CONCURRENT_SOUNDS: usize = 4;
+const SOUND_LIMIT_FRAMES: u32 = 5;
+
+// Stops lots of copies of the same sound starting together and clipping
+struct SoundLimiter {
+    frame: u32,
+    started: HashMap<String, Vec<u32>>,
+    max_concurrent: usize,
+}
+
+impl SoundLimiter {
+    fn new(max_concurrent: usize) -> SoundLimiter {
+        SoundLimiter {
+            frame: 0,
+            started: HashMap::new(),
+            max_concurrent,
+        }
+    }
+
+    fn advance(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+        let frame = self.frame;
+        for starts in self.started.values_mut() {
+            starts.retain(|start| frame.wrapping_sub(*start) < SOUND_LIMIT_FRAMES);
+        }
+        self.started.retain(|_, starts| !starts.is_empty());
+    }
+
+    fn try_start(&mut self, name: &str) -> bool {
+        let starts = self.started.entry(name.to_string()).or_default();
+        if starts.len() >= self.max_concurrent {
+            false
+        } else {
+            starts.push(self.frame);
+            true
+        }
+    }
+}
+
+thread_local! {
+    static SOUND_LIMITER: RefCell<SoundLimiter> = RefCell::new(SoundLimiter::new(DEFAULT_MAX_CONCURRENT_SOUNDS));
+}
+
+// The audio backend can't set per-channel volume, so panned sounds get quieter
+// towards either edge of the screen instead of moving between the speakers
+fn pan_volume(pan: Option<f32>) -> f32 {
+    match pan {
+        Some(pan) => 1.0 - pan.abs().min(1.0) * EDGE_ATTENUATION,
+        None => 1.0,
+    }
+}
+
+fn play_sound_panned(sound: Sound, volume: f32, playback_rate: f32, pan: Option<f32>) {
+    audio::play_sound(
+        sound,
+        PlaySoundParams {
+            looped: false,
+            volume: volume * pan_volume(pan),
+            speed: playback_rate,
+        },
+    );
+}
+
+fn update_frame(
+    game: &mut Game,
+    assets: &Assets,
+    playback_rate: f32,
+    volume: f32,
+) -> WeeResult<()> {
+    gamepad::update();
+
+    let is_mouse_used = !macroquad::input::touches().is_empty()
+        || macroquad::input::is_mouse_button_pressed(MouseButton::Left);
+    if is_mouse_used {
+        gamepad::hide_cursor();
+    }
+
+    let letterbox = Letterbox::current();
+    let to_projection = |x: f32, y: f32| mirror_point(letterbox.to_projection(x, y));
+    let mouse = if let Some(touch) = macroquad::input::touches().first() {
+        Mouse {
+            position: to_projection(touch.position.x, touch.position.y),
+            state: match touch.phase {
+                TouchPhase::Started => ButtonState::Press,
+                TouchPhase::Stationary | TouchPhase::Moved => ButtonState::Down,
+                TouchPhase::Ended | TouchPhase::Cancelled => ButtonState::Release,
+            },
+        }
+    } else if let Some(position) = gamepad::cursor() {
+        Mouse {
+            position: mirror_point(position),
+            state: gamepad::state().state(gamepad::CLICK_BUTTON),
+        }
+    } else {
+        let position = macroquad::input::mouse_position();
+        Mouse {
+            position: to_projection(position.0 as f32, position.1 as f32),
+            state: if macroquad::input::is_mouse_button_pressed(MouseButton::Left) {
+                ButtonState::Press
+            } else if macroquad::input::is_mouse_button_released(MouseButton::Left) {
+                ButtonState::Release
+            } else if macroquad::input::is_mouse_button_down(MouseButton::Left) {
+                ButtonState::Down
+            } else {
+                ButtonState::Up
+            },
+        }
+    };
+    let mut keyboard = Keyboard::default();
+    for key in Key::all() {
+        let key_code = key.to_key_code();
+        let state = if macroquad::input::is_key_pressed(key_code) {
+            ButtonState::Press
+        } else if macroquad::input::is_key_released(key_code) {
+            ButtonState::Release
+        } else if macroquad::input::is_key_down(key_code) {
+            ButtonState::Down
+        } else {
+            ButtonState::Up
+        };
+        if state != ButtonState::Up {
+            keyboard.keys.insert(key, state);
+        }
+    }
+
+    let played_sounds = game.update(&mouse, &keyboard, &gamepad::state())?;
+
+    resize_objects_to_text(game, &assets.fonts, measure_with_font);
+
+    SOUND_LIMITER.with(|limiter| {
+        let mut limiter = limiter.borrow_mut();
+        limiter.advance();
+        for played_sound in played_sounds {
+            if !limiter.try_start(&played_sound.name) {
+                continue;
+            }
+            let loaded = assets.sounds[&played_sound.name];
+            if let Some(length) = loaded.length {
+                game.track_sound(&played_sound.name, length);
+            }
+            play_sound_panned(
+                loaded.sound,
+                volume * loaded.volume,
+                playback_rate,
+                played_sound.pan,
+            );
+        }
+    });
+
+    if game.has_music_finished {
+        assets.music.stop();
+    }
+
+    game.advance_frame();
+
+    Ok(())
+}
+
+async fn pause(game: &Game, assets: &Assets, intro_font: &Font, playback_rate: f32, volume: f32) {
+    assets.stop_sounds();
+
+    loop {
+        handle_hotkeys();
+        draw_game(
+            game,
+            &assets.images,
+            &assets.fonts,
+            intro_font,
+            playback_rate,
+        );
+
+        macroquad::shapes::draw_rectangle(
+            0.0,
+            0.0,
+            PROJECTION_WIDTH,
+            PROJECTION_HEIGHT,
+            Color::new(0.0, 0.0, 0.0, 0.5),
+        );
+        let size = macroquad::text::measure_text("Paused", Some(*intro_font), 174, 1.0);
+        let params = macroquad::text::TextParams {
+            font: *intro_font,
+            font_size: 174,
+            font_scale: 1.0,
+            font_scale_aspect: 1.0,
+            color: WHITE,
+        };
+        macroquad::text::draw_text_ex(
+            "Paused",
+            PROJECTION_WIDTH / 2.0 - size.width / 2.0,
+            PROJECTION_HEIGHT / 2.0,
+            params,
+        );
+
+        next_frame().await;
+
+        if macroquad::input::is_key_pressed(KeyCode::Escape) {
+            break;
+        }
+    }
+
+    assets.music.play(playback_rate, volume);
+}
+
+fn game_cooldown(settings: &Settings) -> usize {
+    settings.game_cooldown.unwrap_or(DEFAULT_GAME_COOLDOWN)
+}
+
+#[derive(Debug)]
+struct GamesList {
+    games: Vec<&'static str>,
+    bosses: Vec<&'static str>,
+    next: Vec<&'static str>,
+    cooldown: VecDeque<&'static str>,
+    cooldown_length: usize,
+    directory: String,
+    rng: SelectionRng,
+}
+
+impl GamesList {
+    fn from_directory(
+        all_games: &HashMap<&'static str, GameData>,
+        directory: String,
+        seed: u64,
+        cooldown_length: usize,
+    ) -> GamesList {
         let mut games = Vec::new();
         let mut bosses = Vec::new();
         for (filename, game) in all_games {
@@ -554,109 +1641,367 @@ impl GamesList {
             }
         }
 
+        // HashMap order varies between runs, so sort to keep selection reproducible
+        games.sort_unstable();
+        bosses.sort_unstable();
+
         GamesList {
             games,
             bosses,
             directory,
             next: Vec::new(),
+            cooldown: VecDeque::new(),
+            cooldown_length,
+            rng: SelectionRng::new(seed),
         }
     }
 
-    fn choose_game(&mut self) -> &'static str {
+    fn choose_game(&mut self) -> WeeResult<&'static str> {
+        if self.games.is_empty() && self.next.is_empty() && self.cooldown.is_empty() {
+            return Err(format!("No published games found in {}", self.directory).into());
+        }
+
+        // Small folders can't fill the whole cooldown so allow repeats sooner
+        let total = self.games.len() + self.next.len() + self.cooldown.len();
+        let cooldown_length = self.cooldown_length.min(total.saturating_sub(1));
+        while self.cooldown.len() > cooldown_length {
+            if let Some(game) = self.cooldown.pop_front() {
+                self.games.push(game);
+            }
+        }
+
         while !self.games.is_empty() && self.next.len() < 5 {
-            let game = self.games.remove(rand::gen_range(0, self.games.len()));
+            let game = self.games.remove(self.rng.gen_index(self.games.len()));
             self.next.push(game);
         }
 
         let next = self.next.remove(0);
-        self.games.push(next);
-        next
+        self.cooldown.push_back(next);
+        Ok(next)
+    }
+
+    // Folders without a boss game play a regular game in the boss slot instead
+    fn choose_boss(&mut self) -> WeeResult<&'static str> {
+        if self.bosses.is_empty() {
+            log::warn!(
+                "No boss games in {}, choosing a regular game",
+                self.directory
+            );
+            return self.choose_game();
+        }
+        Ok(self.bosses[self.rng.gen_index(self.bosses.len())])
     }
+}
+
+// Kept separate from the global generator so the games played don't depend on
+// how many random numbers the microgames themselves used
+#[derive(Debug)]
+struct SelectionRng {
+    state: u64,
+}
+
+impl SelectionRng {
+    fn new(seed: u64) -> SelectionRng {
+        SelectionRng { state: seed }
+    }
+
+    fn gen_index(&mut self, len: usize) -> usize {
+        // splitmix64
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^= z >> 31;
+        (z % len as u64) as usize
+    }
+}
 
-    fn choose_boss(&self) -> &'static str {
-        self.bosses[rand::gen_range(0, self.bosses.len())]
+fn random_seed() -> u64 {
+    let from_args = std::env::args().skip_while(|arg| arg != "--seed").nth(1);
+    let seed = from_args.or_else(|| std::env::var("WEE_SEED").ok());
+    match seed.map(|seed| seed.parse::<u64>()) {
+        Some(Ok(seed)) => seed,
+        Some(Err(error)) => {
+            log::warn!("Invalid seed, using the current time: {}", error);
+            macroquad::miniquad::date::now() as _
+        }
+        None => macroquad::miniquad::date::now() as _,
     }
 }
 
 // Adapted from macroquad::storage
 mod dispenser {
     use std::any::Any;
+    use std::cell::RefCell;
 
-    static mut STORAGE: Option<Box<dyn Any>> = None;
+    thread_local! {
+        static STORAGE: RefCell<Option<Box<dyn Any>>> = const { RefCell::new(None) };
+    }
 
     pub fn store<T: Any>(data: T) {
-        unsafe {
-            STORAGE = Some(Box::new(data));
-        };
+        STORAGE.with(|storage| *storage.borrow_mut() = Some(Box::new(data)));
     }
 
     pub fn take<T: Any>() -> T {
-        unsafe { *STORAGE.take().unwrap().downcast::<T>().unwrap() }
+        STORAGE.with(|storage| {
+            *storage
+                .borrow_mut()
+                .take()
+                .unwrap()
+                .downcast::<T>()
+                .unwrap()
+        })
+    }
+}
+
+mod gamepad {
+    use quad_gamepad::{ControllerContext, ControllerStatus};
+    use std::cell::RefCell;
+    use webgames::wee::{
+        ButtonState, Gamepad, GamepadButton, Vec2, PROJECTION_HEIGHT, PROJECTION_WIDTH,
+    };
+
+    // The left stick moves a virtual cursor for games that are played with the mouse
+    const LEFT_STICK_X: usize = 0;
+    const LEFT_STICK_Y: usize = 1;
+    const STICK_DEAD_ZONE: f32 = 0.25;
+    // Projection units moved each frame with the stick fully tilted
+    const CURSOR_SPEED: f32 = 16.0;
+    pub const CLICK_BUTTON: GamepadButton = GamepadButton::A;
+
+    struct Controllers {
+        context: Option<ControllerContext>,
+        state: Gamepad,
+        cursor: Option<Vec2>,
+    }
+
+    thread_local! {
+        static CONTROLLERS: RefCell<Option<Controllers>> = const { RefCell::new(None) };
+    }
+
+    fn to_quad_button(button: GamepadButton) -> quad_gamepad::GamepadButton {
+        match button {
+            GamepadButton::A => quad_gamepad::GamepadButton::A,
+            GamepadButton::B => quad_gamepad::GamepadButton::B,
+            GamepadButton::X => quad_gamepad::GamepadButton::X,
+            GamepadButton::Y => quad_gamepad::GamepadButton::Y,
+            GamepadButton::Start => quad_gamepad::GamepadButton::Start,
+            GamepadButton::Select => quad_gamepad::GamepadButton::Select,
+            GamepadButton::Up => quad_gamepad::GamepadButton::DpadUp,
+            GamepadButton::Down => quad_gamepad::GamepadButton::DpadDown,
+            GamepadButton::Left => quad_gamepad::GamepadButton::DpadLeft,
+            GamepadButton::Right => quad_gamepad::GamepadButton::DpadRight,
+        }
+    }
+
+    // Buttons held on any connected controller count
+    pub fn update() {
+        CONTROLLERS.with(|controllers| {
+            let mut controllers = controllers.borrow_mut();
+            let controllers = controllers.get_or_insert_with(|| Controllers {
+                context: ControllerContext::new(),
+                state: Gamepad::default(),
+                cursor: None,
+            });
+            update_controllers(controllers);
+        });
+    }
+
+    fn update_controllers(controllers: &mut Controllers) {
+        let context = match &mut controllers.context {
+            Some(context) => context,
+            None => return,
+        };
+        context.update();
+
+        let mut state = Gamepad::default();
+        for button in GamepadButton::all() {
+            let index = to_quad_button(button) as usize;
+            let is_down = (0..quad_gamepad::MAX_DEVICES).any(|device| {
+                let device = context.state(device);
+                device.status == ControllerStatus::Connected && device.digital_state[index]
+            });
+            let button_state = next_button_state(controllers.state.state(button), is_down);
+            if button_state != ButtonState::Up {
+                state.buttons.insert(button, button_state);
+            }
+        }
+        controllers.state = state;
+
+        let stick = (0..quad_gamepad::MAX_DEVICES)
+            .map(|device| context.state(device))
+            .filter(|device| device.status == ControllerStatus::Connected)
+            .map(|device| {
+                Vec2::new(
+                    device.analog_state[LEFT_STICK_X],
+                    device.analog_state[LEFT_STICK_Y],
+                )
+            })
+            .find(|stick| stick.magnitude() > STICK_DEAD_ZONE);
+        if let Some(stick) = stick {
+            let position = controllers
+                .cursor
+                .unwrap_or_else(|| Vec2::new(PROJECTION_WIDTH / 2.0, PROJECTION_HEIGHT / 2.0));
+            controllers.cursor = Some(move_cursor(position, stick));
+        }
+    }
+
+    pub fn next_button_state(previous: ButtonState, is_down: bool) -> ButtonState {
+        let was_down = match previous {
+            ButtonState::Press | ButtonState::Down => true,
+            ButtonState::Release | ButtonState::Up => false,
+        };
+        match (was_down, is_down) {
+            (false, true) => ButtonState::Press,
+            (true, true) => ButtonState::Down,
+            (true, false) => ButtonState::Release,
+            (false, false) => ButtonState::Up,
+        }
+    }
+
+    pub fn move_cursor(position: Vec2, stick: Vec2) -> Vec2 {
+        let position = position + stick * CURSOR_SPEED;
+        Vec2::new(
+            position.x.clamp(0.0, PROJECTION_WIDTH),
+            position.y.clamp(0.0, PROJECTION_HEIGHT),
+        )
+    }
+
+    // Where the virtual cursor is drawn, if the stick has been used since the mouse last was
+    pub fn cursor() -> Option<Vec2> {
+        CONTROLLERS.with(|controllers| {
+            controllers
+                .borrow()
+                .as_ref()
+                .and_then(|controllers| controllers.cursor)
+        })
+    }
+
+    pub fn hide_cursor() {
+        CONTROLLERS.with(|controllers| {
+            if let Some(controllers) = controllers.borrow_mut().as_mut() {
+                controllers.cursor = None;
+            }
+        });
+    }
+
+    pub fn state() -> Gamepad {
+        CONTROLLERS.with(|controllers| {
+            controllers
+                .borrow()
+                .as_ref()
+                .map(|controllers| controllers.state.clone())
+                .unwrap_or_default()
+        })
     }
 }
 
-fn frames_to_run(frames: FrameInfo, playback_rate: f32) -> u32 {
-    let mut num_frames = playback_rate.floor();
-    let remainder = playback_rate - num_frames;
-    if remainder != 0.0 {
-        let how_often_extra = 1.0 / remainder;
-        if (frames.steps_taken as f32 % how_often_extra).floor() == 0.0 {
-            num_frames += 1.0;
+// Long pauses (like the window losing focus) shouldn't be caught up all at once
+const MAX_FRAME_TIME: f32 = 0.25;
+
+// Runs games at FPS logic steps a second however often the display refreshes.
+// Real time is saved up and spent in whole steps. A step is borrowed early when
+// there's new input so presses aren't missed between steps on fast displays.
+#[derive(Debug, Default)]
+struct FixedTimestep {
+    accumulated: f32,
+}
+
+impl FixedTimestep {
+    fn steps(&mut self, frame_time: f32, playback_rate: f32, has_new_input: bool) -> u32 {
+        self.accumulated += frame_time.min(MAX_FRAME_TIME) * FPS * playback_rate;
+        let mut steps = self.accumulated.floor().max(0.0);
+        if steps == 0.0 && has_new_input {
+            steps = 1.0;
         }
+        self.accumulated -= steps;
+        steps as u32
     }
+}
+
+fn has_new_input() -> bool {
+    let is_touch_edge = macroquad::input::touches()
+        .iter()
+        .any(|touch| match touch.phase {
+            TouchPhase::Started | TouchPhase::Ended | TouchPhase::Cancelled => true,
+            TouchPhase::Stationary | TouchPhase::Moved => false,
+        });
+    is_touch_edge
+        || macroquad::input::is_mouse_button_pressed(MouseButton::Left)
+        || macroquad::input::is_mouse_button_released(MouseButton::Left)
+        || Key::all().into_iter().any(|key| {
+            let key_code = key.to_key_code();
+            macroquad::input::is_key_pressed(key_code)
+                || macroquad::input::is_key_released(key_code)
+        })
+}
+
+fn frames_to_run(frames: FrameInfo, timestep: &mut FixedTimestep, playback_rate: f32) -> u32 {
+    let num_frames = timestep.steps(
+        macroquad::time::get_frame_time(),
+        playback_rate,
+        has_new_input(),
+    );
     match frames.remaining() {
-        FrameCount::Frames(remaining) => (num_frames as u32).min(remaining),
-        FrameCount::Infinite => num_frames as u32,
+        FrameCount::Frames(remaining) => num_frames.min(remaining),
+        FrameCount::Infinite => num_frames,
     }
 }
 
-fn preloaded_game<'a>(
+fn preloaded_game(
     games: &HashMap<&'static str, GameData>,
-    preloaded_assets: &'a HashMap<&'static str, Assets>,
+    preloaded_assets: &mut AssetCache,
     directory: &str,
     filename: &str,
-) -> (GameData, &'a Assets) {
+) -> WeeResult<(GameData, Assets)> {
     let mode_path = |directory: &str, filename| {
         let mut path = directory.to_string();
         if !path.ends_with('/') {
-            path.push_str("/");
+            path.push('/');
         };
         path.push_str(filename);
         path
     };
     let file_path = mode_path(directory, filename);
 
-    let (game, assets) = (
-        games.get(file_path.as_str()),
-        preloaded_assets.get(file_path.as_str()),
-    );
-
-    if let (Some(game), Some(assets)) = (game, assets) {
-        (game.clone(), assets)
-    } else {
-        let file_path = format!("games/system/{}", filename);
-        let game = games[file_path.as_str()].clone();
-        let assets = &preloaded_assets[file_path.as_str()];
-        (game, assets)
+    if let Some(game) = games.get(file_path.as_str()) {
+        if let Some(assets) = preloaded_assets.get(file_path.as_str()) {
+            return Ok((game.clone(), assets.clone()));
+        }
     }
+
+    let file_path = format!("games/system/{}", filename);
+    let game = games[file_path.as_str()].clone();
+    let assets = preloaded_assets
+        .get(file_path.as_str())
+        .cloned()
+        .ok_or_else(|| format!("Assets not loaded for {}", file_path))?;
+    Ok((game, assets))
 }
 
 struct MainGame<S> {
     state: S,
     intro_font: Font,
     games: HashMap<&'static str, GameData>,
-    preloaded_assets: HashMap<&'static str, Assets>,
-    high_scores: HashMap<String, (i32, i32, i32)>,
+    preloaded_assets: AssetCache,
+    high_scores: HashMap<String, HighScores>,
     played_games: HashSet<&'static str>,
+    volume: f32,
+    music_fade: MusicFade,
+    seed: u64,
+    settings: Settings,
+    resources: Resources,
 }
 
 struct LoadingScreen {}
 
 impl MainGame<LoadingScreen> {
-    async fn load() -> WeeResult<MainGame<Menu>> {
-        let game = LoadedGameData::load("games/system/loading-screen.json").await?;
+    async fn load(seed: u64) -> WeeResult<MainGame<Menu>> {
+        let mut resources = Resources::default();
 
-        let assets = Assets {
+        let game = LoadedGameData::load(&resources, "games/system/loading-screen.json").await?;
+
+        let mut assets = Assets {
             images: game.images,
             fonts: game.fonts,
             sounds: game.sounds,
@@ -667,73 +2012,108 @@ impl MainGame<LoadingScreen> {
 
         let intro_font = macroquad::text::load_ttf_font("fonts/Roboto-Medium.ttf").await?;
 
-        let game_filenames = vec![
-            "games/second/bike.json",
-            "games/second/break.json",
-            "games/second/dawn.json",
-            "games/second/disc.json",
-            "games/second/explosion.json",
-            "games/second/gravity.json",
-            "games/second/jump.json",
-            "games/second/knock.json",
-            "games/second/mine.json",
-            "games/second/racer.json",
-            "games/second/slide.json",
-            "games/second/stealth.json",
-            "games/second/swim.json",
-            "games/second/tanks.json",
-            "games/second/time.json",
-            "games/yeah/baby.json",
-            "games/yeah/balloon.json",
-            "games/yeah/bird.json",
-            "games/yeah/boss.json",
-            "games/yeah/boxer.json",
-            "games/yeah/cannon.json",
-            "games/yeah/cat.json",
-            "games/yeah/disgrace.json",
-            "games/yeah/hiding.json",
-            "games/yeah/mask.json",
-            "games/yeah/monkey.json",
-            "games/yeah/orange.json",
-            "games/yeah/parachute.json",
-            "games/yeah/piano.json",
-            "games/yeah/planes.json",
-            "games/yeah/pumpkin.json",
-            "games/yeah/puzzle.json",
-            "games/yeah/quake.json",
-            "games/yeah/rhinos.json",
-            "games/yeah/shake.json",
-            "games/yeah/shed.json",
-            "games/yeah/titanic.json",
-            "games/yeah/wasp.json",
-            "games/second/prelude.json",
-            "games/system/prelude.json",
-            "games/second/interlude.json",
-            "games/system/interlude.json",
-            "games/second/boss.json",
-            "games/second/game-over.json",
-            "games/system/game-over.json",
-            "games/system/choose-mode.json",
-            "games/mine/bong.json",
-            "games/bops/cloud.json",
-        ];
-
-        let games_to_preload = vec![
-            "games/second/prelude.json",
-            "games/system/prelude.json",
-            "games/second/interlude.json",
-            "games/system/interlude.json",
-            "games/second/boss.json",
-            "games/second/game-over.json",
-            "games/system/game-over.json",
-            "games/system/choose-mode.json",
-        ];
+        let settings = match Settings::load("settings.json").await {
+            Ok(settings) => settings,
+            Err(error) => {
+                log::warn!("Failed to load settings, using defaults: {}", error);
+                Settings::default()
+            }
+        };
+        MIRRORED.store(settings.mirrored, Ordering::Relaxed);
+        SOUND_LIMITER.with(|limiter| {
+            limiter.borrow_mut().max_concurrent = settings
+                .max_concurrent_sounds
+                .unwrap_or(DEFAULT_MAX_CONCURRENT_SOUNDS);
+        });
+        let volume = adjust_volume(settings.volume.unwrap_or(DEFAULT_VOLUME), 0.0);
+
+        let (game_filenames, games_to_preload) =
+            match GameManifest::load("games/manifest.json").await {
+                Ok(manifest) => {
+                    for archive in &manifest.archives {
+                        if let Err(error) = resources.mount_zip(archive).await {
+                            log::warn!("Failed to load archive {}: {}", archive, error);
+                        }
+                    }
+                    manifest.into_static()
+                }
+                Err(error) => {
+                    log::warn!(
+                        "Failed to load game manifest, using default games: {}",
+                        error
+                    );
+                    (
+                        vec![
+                            "games/second/bike.json",
+                            "games/second/break.json",
+                            "games/second/dawn.json",
+                            "games/second/disc.json",
+                            "games/second/explosion.json",
+                            "games/second/gravity.json",
+                            "games/second/jump.json",
+                            "games/second/knock.json",
+                            "games/second/mine.json",
+                            "games/second/racer.json",
+                            "games/second/slide.json",
+                            "games/second/stealth.json",
+                            "games/second/swim.json",
+                            "games/second/tanks.json",
+                            "games/second/time.json",
+                            "games/yeah/baby.json",
+                            "games/yeah/balloon.json",
+                            "games/yeah/bird.json",
+                            "games/yeah/boss.json",
+                            "games/yeah/boxer.json",
+                            "games/yeah/cannon.json",
+                            "games/yeah/cat.json",
+                            "games/yeah/disgrace.json",
+                            "games/yeah/hiding.json",
+                            "games/yeah/mask.json",
+                            "games/yeah/monkey.json",
+                            "games/yeah/orange.json",
+                            "games/yeah/parachute.json",
+                            "games/yeah/piano.json",
+                            "games/yeah/planes.json",
+                            "games/yeah/pumpkin.json",
+                            "games/yeah/puzzle.json",
+                            "games/yeah/quake.json",
+                            "games/yeah/rhinos.json",
+                            "games/yeah/shake.json",
+                            "games/yeah/shed.json",
+                            "games/yeah/titanic.json",
+                            "games/yeah/wasp.json",
+                            "games/second/prelude.json",
+                            "games/system/prelude.json",
+                            "games/second/interlude.json",
+                            "games/system/interlude.json",
+                            "games/second/boss.json",
+                            "games/second/game-over.json",
+                            "games/system/game-over.json",
+                            "games/system/choose-mode.json",
+                            "games/mine/bong.json",
+                            "games/bops/cloud.json",
+                        ],
+                        vec![
+                            "games/second/prelude.json",
+                            "games/system/prelude.json",
+                            "games/second/interlude.json",
+                            "games/system/interlude.json",
+                            "games/second/boss.json",
+                            "games/second/game-over.json",
+                            "games/system/game-over.json",
+                            "games/system/choose-mode.json",
+                        ],
+                    )
+                }
+            };
 
         log::debug!("Declaring coroutine");
+        let coroutine_resources = resources.clone();
         let resources_loading: Coroutine = start_coroutine(async move {
             log::debug!("Starting coroutine");
 
             async fn preload_games(
+                resources: &Resources,
                 game_filenames: Vec<&'static str>,
                 games_to_preload: Vec<&'static str>,
             ) -> WeeResult<(
@@ -744,7 +2124,7 @@ impl MainGame<LoadingScreen> {
                     let mut loaded_data = HashMap::new();
                     let mut waiting_data = Vec::new();
                     for filename in &game_filenames {
-                        waiting_data.push(GameData::load(filename));
+                        waiting_data.push(resources.load_game_data(filename));
                     }
 
                     let mut data = join_all(waiting_data).await;
@@ -760,7 +2140,7 @@ impl MainGame<LoadingScreen> {
                 let mut preloaded_assets = HashMap::new();
                 let mut waiting_data = Vec::new();
                 for filename in &games_to_preload {
-                    waiting_data.push(LoadedGameData::load(filename));
+                    waiting_data.push(LoadedGameData::load(resources, filename));
                 }
 
                 let mut data = join_all(waiting_data).await;
@@ -781,24 +2161,37 @@ impl MainGame<LoadingScreen> {
                 Ok((games, preloaded_assets))
             }
 
-            dispenser::store(preload_games(game_filenames, games_to_preload).await);
+            dispenser::store(
+                preload_games(&coroutine_resources, game_filenames, games_to_preload).await,
+            );
         });
 
         clear_background(WHITE);
 
         log::debug!("Started intro");
 
-        assets.music.play(DEFAULT_PLAYBACK_RATE, VOLUME);
+        let mut music_fade = MusicFade::new();
+        music_fade.fade_in(&assets.music, DEFAULT_PLAYBACK_RATE, volume);
 
         while !resources_loading.is_done() {
-            update_frame(&mut game, &assets, DEFAULT_PLAYBACK_RATE)?;
+            update_frame(&mut game, &assets, DEFAULT_PLAYBACK_RATE, volume)?;
+
+            handle_hotkeys();
+            draw_game(
+                &game,
+                &assets.images,
+                &assets.fonts,
+                &intro_font,
+                DEFAULT_PLAYBACK_RATE,
+            );
 
-            draw_game(&game, &assets.images, &assets.fonts, &intro_font);
+            music_fade.update();
 
             next_frame().await;
         }
 
-        assets.stop_sounds();
+        assets.stop_sound_effects();
+        music_fade.fade_out(std::mem::take(&mut assets.music), volume);
 
         let (games, preloaded_assets) = dispenser::take::<
             WeeResult<(
@@ -811,35 +2204,67 @@ impl MainGame<LoadingScreen> {
             state: Menu {},
             intro_font,
             games,
-            preloaded_assets,
+            preloaded_assets: AssetCache::new(preloaded_assets, MAX_CACHED_GAMES),
             high_scores: HashMap::new(),
             played_games: HashSet::new(),
+            volume,
+            music_fade,
+            seed,
+            settings,
+            resources,
         })
     }
 }
 
 struct Menu {}
 
+fn adjust_volume(volume: f32, change: f32) -> f32 {
+    (volume + change).clamp(0.0, 1.0)
+}
+
+// Text for the objects on the choose mode screen that show a setting
+fn menu_status(volume: f32, settings: &Settings) -> Vec<(&'static str, String)> {
+    let on_off = |enabled| if enabled { "On" } else { "Off" }.to_string();
+    vec![
+        ("Volume", format!("{}%", (volume * 100.0).round())),
+        ("ExtraTime Status", on_off(settings.extra_time.enabled)),
+        ("Mirror Status", on_off(settings.mirrored)),
+    ]
+}
+
+enum MenuChoice {
+    Play(MainGame<Prelude>),
+    Practice(MainGame<Practice>),
+}
+
 impl MainGame<Menu> {
     async fn run_game_loop(self) -> WeeResult<MainGame<Menu>> {
-        let main_game = self
-            .pick_games()
-            .await?
-            .start()
-            .await?
-            .play_games()
-            .await?
-            .return_to_menu()
-            .await?;
+        let main_game = match self.pick_games().await? {
+            MenuChoice::Play(main_game) => {
+                let mut game_over = main_game.start().await?.play_games().await?;
+                loop {
+                    match game_over.return_to_menu().await? {
+                        AfterGameOver::Menu(main_game) => break main_game,
+                        AfterGameOver::Continue(main_game) => {
+                            game_over = main_game.play_games().await?;
+                        }
+                    }
+                }
+            }
+            MenuChoice::Practice(main_game) => main_game.practice().await?,
+        };
         Ok(main_game)
     }
 
-    async fn pick_games(self) -> WeeResult<MainGame<Prelude>> {
+    async fn pick_games(mut self) -> WeeResult<MenuChoice> {
         log::debug!("pick_games");
         let filename = "games/system/choose-mode.json";
 
         let mut game_data = self.games[filename].clone();
-        let assets = &self.preloaded_assets[filename];
+        let assets = self
+            .preloaded_assets
+            .get(filename)
+            .ok_or_else(|| format!("Assets not loaded for {}", filename))?;
 
         {
             let text_replacements =
@@ -851,43 +2276,342 @@ impl MainGame<Menu> {
 
         let mut game = Game::from_data(game_data);
 
-        assets.music.play(DEFAULT_PLAYBACK_RATE, VOLUME);
+        self.music_fade
+            .fade_in(&assets.music, DEFAULT_PLAYBACK_RATE, self.volume);
 
         let directory;
+        let mut practice_filename = None;
+
+        let is_menu_option = |name: &str| {
+            name.starts_with("OpenFolder:")
+                || name.starts_with("Practice:")
+                || name == "Shuffle"
+                || name == "VolumeUp"
+                || name == "VolumeDown"
+                || name == "ExtraTime"
+                || name == "Mirror"
+        };
+        let menu_options: Vec<String> = game
+            .objects
+            .keys()
+            .filter(|name| is_menu_option(name))
+            .cloned()
+            .collect();
+        let mut focused: Option<usize> = None;
 
         'choose_mode_running: loop {
-            update_frame(&mut game, assets, DEFAULT_PLAYBACK_RATE)?;
+            update_frame(&mut game, assets, DEFAULT_PLAYBACK_RATE, self.volume)?;
 
-            draw_game(&game, &assets.images, &assets.fonts, &self.intro_font);
+            for (name, text) in menu_status(self.volume, &self.settings) {
+                if let Some(drawn_text) = game.drawn_text.get_mut(name) {
+                    drawn_text.text = text;
+                }
+            }
+
+            let gamepad = gamepad::state();
+            if !menu_options.is_empty() {
+                let is_pressed = |button| gamepad.state(button) == ButtonState::Press;
+                if is_pressed(GamepadButton::Down) || is_pressed(GamepadButton::Right) {
+                    focused = Some(focused.map_or(0, |index| (index + 1) % menu_options.len()));
+                } else if is_pressed(GamepadButton::Up) || is_pressed(GamepadButton::Left) {
+                    focused = Some(focused.map_or(menu_options.len() - 1, |index| {
+                        (index + menu_options.len() - 1) % menu_options.len()
+                    }));
+                }
+                if let Some(index) = focused {
+                    if is_pressed(GamepadButton::A) {
+                        if let Some(object) = game.objects.get_mut(&menu_options[index]) {
+                            object.switch = SwitchState::SwitchedOn;
+                        }
+                    }
+                }
+            }
+
+            handle_hotkeys();
+            draw_game(
+                &game,
+                &assets.images,
+                &assets.fonts,
+                &self.intro_font,
+                DEFAULT_PLAYBACK_RATE,
+            );
+
+            if let Some(object) = focused.and_then(|index| game.objects.get(&menu_options[index])) {
+                macroquad::shapes::draw_rectangle_lines(
+                    object.position.x - object.size.width / 2.0,
+                    object.position.y - object.size.height / 2.0,
+                    object.size.width,
+                    object.size.height,
+                    8.0,
+                    macroquad::color::YELLOW,
+                );
+            }
+
+            self.music_fade.update();
 
             next_frame().await;
 
             for (key, object) in game.objects.iter() {
                 if object.switch == SwitchState::SwitchedOn {
-                    let pattern = "OpenFolder:";
-                    if key.starts_with(pattern) {
-                        directory = key[pattern.len()..].to_string();
+                    if let Some(folder) = key.strip_prefix("OpenFolder:") {
+                        directory = folder.to_string();
                         break 'choose_mode_running;
                     }
                     if key == "Shuffle" {
                         directory = "games".to_string();
                         break 'choose_mode_running;
                     }
+                    if let Some(filename) = key.strip_prefix("Practice:") {
+                        match self.games.keys().find(|game| **game == filename) {
+                            Some(filename) => {
+                                directory = String::new();
+                                practice_filename = Some(*filename);
+                                break 'choose_mode_running;
+                            }
+                            None => log::error!("Can't practice unknown game {}", filename),
+                        }
+                    }
+                    if key == "VolumeUp" || key == "VolumeDown" {
+                        let change = if key == "VolumeUp" {
+                            VOLUME_STEP
+                        } else {
+                            -VOLUME_STEP
+                        };
+                        self.volume = adjust_volume(self.volume, change);
+                        assets.music.set_volume(self.volume);
+                        self.settings.volume = Some(self.volume);
+                        if let Err(error) = self.settings.save("settings.json") {
+                            log::warn!("Failed to save settings: {}", error);
+                        }
+                    }
+                    if key == "ExtraTime" {
+                        self.settings.extra_time.enabled = !self.settings.extra_time.enabled;
+                        log::info!("Extra time: {}", self.settings.extra_time.enabled);
+                        if let Err(error) = self.settings.save("settings.json") {
+                            log::warn!("Failed to save settings: {}", error);
+                        }
+                    }
+                    if key == "Mirror" {
+                        self.settings.mirrored = !self.settings.mirrored;
+                        MIRRORED.store(self.settings.mirrored, Ordering::Relaxed);
+                        log::info!("Mirrored: {}", self.settings.mirrored);
+                        if let Err(error) = self.settings.save("settings.json") {
+                            log::warn!("Failed to save settings: {}", error);
+                        }
+                    }
                 }
             }
         }
 
-        assets.stop_sounds();
+        assets.stop_sound_effects();
+        self.music_fade.fade_out(assets.music.clone(), self.volume);
 
-        Ok(MainGame {
+        if let Some(filename) = practice_filename {
+            return Ok(MenuChoice::Practice(MainGame {
+                state: Practice {
+                    filename,
+                    difficulty: MAX_DIFFICULTY,
+                    playback_rate: INITIAL_PLAYBACK_RATE,
+                },
+                intro_font: self.intro_font,
+                games: self.games,
+                preloaded_assets: self.preloaded_assets,
+                high_scores: self.high_scores,
+                played_games: self.played_games,
+                volume: self.volume,
+                music_fade: self.music_fade,
+                seed: self.seed,
+                settings: self.settings,
+                resources: self.resources,
+            }));
+        }
+
+        Ok(MenuChoice::Play(MainGame {
             state: Prelude { directory },
             intro_font: self.intro_font,
             games: self.games,
             preloaded_assets: self.preloaded_assets,
             high_scores: self.high_scores,
             played_games: self.played_games,
+            volume: self.volume,
+            music_fade: self.music_fade,
+            seed: self.seed,
+            settings: self.settings,
+            resources: self.resources,
+        }))
+    }
+}
+
+// Loops a single game so it can be tested without playing through a session
+struct Practice {
+    filename: &'static str,
+    difficulty: u32,
+    playback_rate: f32,
+}
+
+impl Practice {
+    // Every round starts with full lives so practising never ends the run
+    fn progress(&self, settings: &Settings) -> Progress {
+        Progress {
+            difficulty: self.difficulty,
+            playback_rate: self.playback_rate,
+            boss_playback_rate: self.playback_rate,
+            ..Progress::from_settings(settings)
+        }
+    }
+
+    fn change_speed(&mut self, change: f32) {
+        self.playback_rate =
+            (self.playback_rate + change).clamp(INITIAL_PLAYBACK_RATE, PLAYBACK_RATE_MAX);
+    }
+}
+
+impl MainGame<Practice> {
+    async fn practice(mut self) -> WeeResult<MainGame<Menu>> {
+        log::debug!("practice {}", self.state.filename);
+
+        let filename = self.state.filename;
+        let game_data = self.games[filename].clone();
+        let directory = Path::new(filename).parent().unwrap();
+        let assets = match self.preloaded_assets.get(filename).cloned() {
+            Some(assets) => assets,
+            None => Assets::load(&self.resources, &game_data.asset_files, directory).await?,
+        };
+        let is_boss_game = game_data.game_type == GameType::BossGame;
+
+        loop {
+            let practice = self.state;
+            let progress = practice.progress(&self.settings);
+            let games_list = GamesList::from_directory(
+                &self.games,
+                directory.to_string_lossy().to_string(),
+                self.seed,
+                game_cooldown(&self.settings),
+            );
+
+            let interlude = MainGame {
+                state: Play {
+                    filename,
+                    game_data: game_data.clone(),
+                    assets: assets.clone(),
+                    progress,
+                    games_list,
+                    is_boss_game,
+                },
+                intro_font: self.intro_font,
+                games: self.games,
+                preloaded_assets: self.preloaded_assets,
+                high_scores: self.high_scores,
+                played_games: self.played_games,
+                volume: self.volume,
+                music_fade: self.music_fade,
+                seed: self.seed,
+                settings: self.settings,
+                resources: self.resources,
+            }
+            .play()
+            .await?;
+
+            let has_won = interlude
+                .state
+                .progress
+                .last_game
+                .map(|last_game| last_game.has_won)
+                .unwrap_or(false);
+
+            self = MainGame {
+                state: practice,
+                intro_font: interlude.intro_font,
+                games: interlude.games,
+                preloaded_assets: interlude.preloaded_assets,
+                high_scores: interlude.high_scores,
+                played_games: interlude.played_games,
+                volume: interlude.volume,
+                music_fade: interlude.music_fade,
+                seed: interlude.seed,
+                settings: interlude.settings,
+                resources: interlude.resources,
+            };
+
+            if !self.show_practice_result(has_won).await {
+                break;
+            }
+        }
+
+        Ok(MainGame {
+            state: Menu {},
+            intro_font: self.intro_font,
+            games: self.games,
+            preloaded_assets: self.preloaded_assets,
+            high_scores: self.high_scores,
+            played_games: self.played_games,
+            volume: self.volume,
+            music_fade: self.music_fade,
+            seed: self.seed,
+            settings: self.settings,
+            resources: self.resources,
         })
     }
+
+    // Returns false if the player wants to go back to the menu
+    async fn show_practice_result(&mut self, has_won: bool) -> bool {
+        set_projection_camera(macroquad::math::Rect::new(
+            0.0,
+            0.0,
+            PROJECTION_WIDTH,
+            PROJECTION_HEIGHT,
+        ));
+
+        for _ in 0..PRACTICE_RESULT_FRAMES {
+            if macroquad::input::is_key_pressed(KeyCode::Escape) {
+                return false;
+            }
+            let difficulty_keys = [(KeyCode::Key1, 1), (KeyCode::Key2, 2), (KeyCode::Key3, 3)];
+            for (key, difficulty) in difficulty_keys.iter() {
+                if macroquad::input::is_key_pressed(*key) {
+                    self.state.difficulty = *difficulty;
+                }
+            }
+            if macroquad::input::is_key_pressed(KeyCode::Up) {
+                self.state.change_speed(PLAYBACK_RATE_INCREASE);
+            }
+            if macroquad::input::is_key_pressed(KeyCode::Down) {
+                self.state.change_speed(-PLAYBACK_RATE_INCREASE);
+            }
+
+            clear_background(BLACK);
+            let lines = [
+                if has_won { "Won!" } else { "Lost!" }.to_string(),
+                format!("Difficulty: {} (1/2/3)", self.state.difficulty),
+                format!("Speed: {:.1} (Up/Down)", self.state.playback_rate),
+                "Escape to return to the menu".to_string(),
+            ];
+            for (i, line) in lines.iter().enumerate() {
+                let font_size = if i == 0 { 120 } else { 48 };
+                let size =
+                    macroquad::text::measure_text(line, Some(self.intro_font), font_size, 1.0);
+                let params = macroquad::text::TextParams {
+                    font: self.intro_font,
+                    font_size,
+                    font_scale: 1.0,
+                    font_scale_aspect: 1.0,
+                    color: WHITE,
+                };
+                macroquad::text::draw_text_ex(
+                    line,
+                    PROJECTION_WIDTH / 2.0 - size.width / 2.0,
+                    250.0 + i as f32 * 120.0,
+                    params,
+                );
+            }
+
+            self.music_fade.update();
+
+            next_frame().await;
+        }
+
+        true
+    }
 }
 
 struct Prelude {
@@ -895,35 +2619,62 @@ struct Prelude {
 }
 
 impl MainGame<Prelude> {
-    async fn start(self) -> WeeResult<MainGame<Interlude>> {
+    async fn start(mut self) -> WeeResult<MainGame<Interlude>> {
         log::debug!("prelude");
 
         let (game, assets) = preloaded_game(
             &self.games,
-            &self.preloaded_assets,
+            &mut self.preloaded_assets,
             &self.state.directory,
             "prelude.json",
-        );
+        )?;
 
         let mut game = Game::from_data(game);
 
-        assets.music.play(DEFAULT_PLAYBACK_RATE, VOLUME);
+        self.music_fade
+            .fade_in(&assets.music, DEFAULT_PLAYBACK_RATE, self.volume);
 
         while game.frames.remaining() != FrameCount::Frames(0) && !game.end_early {
-            update_frame(&mut game, assets, DEFAULT_PLAYBACK_RATE)?;
+            if macroquad::input::is_key_pressed(KeyCode::Escape) {
+                pause(
+                    &game,
+                    &assets,
+                    &self.intro_font,
+                    DEFAULT_PLAYBACK_RATE,
+                    self.volume,
+                )
+                .await;
+            }
+
+            update_frame(&mut game, &assets, DEFAULT_PLAYBACK_RATE, self.volume)?;
+
+            handle_hotkeys();
+            draw_game(
+                &game,
+                &assets.images,
+                &assets.fonts,
+                &self.intro_font,
+                DEFAULT_PLAYBACK_RATE,
+            );
 
-            draw_game(&game, &assets.images, &assets.fonts, &self.intro_font);
+            self.music_fade.update();
 
             next_frame().await;
         }
 
-        assets.stop_sounds();
+        assets.stop_sound_effects();
+        self.music_fade.fade_out(assets.music.clone(), self.volume);
 
-        let games_list = GamesList::from_directory(&self.games, self.state.directory);
+        let games_list = GamesList::from_directory(
+            &self.games,
+            self.state.directory,
+            self.seed,
+            game_cooldown(&self.settings),
+        );
 
         Ok(MainGame {
             state: Interlude {
-                progress: Progress::new(),
+                progress: Progress::from_settings(&self.settings),
                 games_list,
             },
             intro_font: self.intro_font,
@@ -931,10 +2682,26 @@ impl MainGame<Prelude> {
             preloaded_assets: self.preloaded_assets,
             high_scores: self.high_scores,
             played_games: self.played_games,
+            volume: self.volume,
+            music_fade: self.music_fade,
+            seed: self.seed,
+            settings: self.settings,
+            resources: self.resources,
         })
     }
 }
 
+// Life indicators are named "1", "2" and so on and are shown while there are enough lives
+fn set_life_switch(object: &mut SerialiseObject, lives: i32) {
+    if let Ok(life) = object.name.parse::<i32>() {
+        object.switch = if lives >= life {
+            Switch::On
+        } else {
+            Switch::Off
+        };
+    }
+}
+
 struct Interlude {
     progress: Progress,
     games_list: GamesList,
@@ -947,7 +2714,7 @@ impl MainGame<Interlude> {
             self = match next_step {
                 NextStep::Play(game) => game.play().await?,
                 NextStep::Finished(game_over) => {
-                    return Ok(game_over);
+                    return Ok(*game_over);
                 }
             }
         }
@@ -961,10 +2728,10 @@ impl MainGame<Interlude> {
 
         let (mut game_data, assets) = preloaded_game(
             &self.games,
-            &self.preloaded_assets,
+            &mut self.preloaded_assets,
             &self.state.games_list.directory,
             "interlude.json",
-        );
+        )?;
 
         if self.state.progress.lives == 0 {
             {
@@ -975,15 +2742,7 @@ impl MainGame<Interlude> {
                     ("{IntroText}", "Game Over".to_string()),
                 ];
                 for object in game_data.objects.iter_mut() {
-                    let mut set_switch = |name, pred| {
-                        if object.name == name {
-                            object.switch = if pred { Switch::On } else { Switch::Off };
-                        }
-                    };
-                    set_switch("1", progress.lives >= 1);
-                    set_switch("2", progress.lives >= 2);
-                    set_switch("3", progress.lives >= 3);
-                    set_switch("4", progress.lives >= 4);
+                    set_life_switch(object, progress.lives);
 
                     object.replace_text(&text_replacements);
                 }
@@ -993,47 +2752,69 @@ impl MainGame<Interlude> {
 
             let playback_rate = self.state.progress.playback_rate;
 
-            assets.music.play(playback_rate, VOLUME);
+            self.music_fade
+                .fade_in(&assets.music, playback_rate, self.volume);
+
+            let mut timestep = FixedTimestep::default();
 
             while game.frames.remaining() != FrameCount::Frames(0) && !game.end_early {
+                if macroquad::input::is_key_pressed(KeyCode::Escape) {
+                    pause(&game, &assets, &self.intro_font, playback_rate, self.volume).await;
+                }
+
                 game.frames.steps_taken += 1;
 
-                let frames_to_run = frames_to_run(game.frames, playback_rate);
+                let frames_to_run = frames_to_run(game.frames, &mut timestep, playback_rate);
                 for _ in 0..frames_to_run {
-                    update_frame(&mut game, assets, playback_rate)?;
+                    update_frame(&mut game, &assets, playback_rate, self.volume)?;
                 }
 
-                draw_game(&game, &assets.images, &assets.fonts, &self.intro_font);
+                handle_hotkeys();
+                draw_game(
+                    &game,
+                    &assets.images,
+                    &assets.fonts,
+                    &self.intro_font,
+                    playback_rate,
+                );
+
+                self.music_fade.update();
 
                 next_frame().await;
             }
 
-            assets.stop_sounds();
+            assets.stop_sound_effects();
+            self.music_fade.fade_out(assets.music.clone(), self.volume);
 
             for key in assets.sounds.keys() {
-                macroquad::audio::stop_sound(assets.sounds[key]);
+                macroquad::audio::stop_sound(assets.sounds[key].sound);
             }
 
-            let next_step = NextStep::Finished(MainGame {
+            let next_step = NextStep::Finished(Box::new(MainGame {
                 state: GameOver {
                     progress: self.state.progress,
-                    directory: self.state.games_list.directory,
+                    games_list: self.state.games_list,
                 },
                 intro_font: self.intro_font,
                 games: self.games,
                 preloaded_assets: self.preloaded_assets,
                 high_scores: self.high_scores,
                 played_games: self.played_games,
-            });
+                volume: self.volume,
+                music_fade: self.music_fade,
+                seed: self.seed,
+                settings: self.settings,
+                resources: self.resources,
+            }));
             Ok(next_step)
         } else {
             let next_filename = if is_boss_game {
-                self.state.games_list.choose_boss()
+                self.state.games_list.choose_boss()?
             } else {
-                self.state.games_list.choose_game()
+                self.state.games_list.choose_game()?
             };
 
-            log::debug!("next filename: {}", next_filename);
+            log::info!("Next game: {} (seed {})", next_filename, self.seed);
 
             self.played_games.insert(next_filename);
 
@@ -1062,6 +2843,7 @@ impl MainGame<Interlude> {
                     ),
                 ];
                 for object in game_data.objects.iter_mut() {
+                    set_life_switch(object, progress.lives);
                     let mut set_switch = |name, pred| {
                         if object.name == name {
                             object.switch = if pred { Switch::On } else { Switch::Off };
@@ -1071,10 +2853,6 @@ impl MainGame<Interlude> {
                         set_switch("Won", last_game.has_won);
                         set_switch("Gained Life", last_game.was_life_gained);
                     }
-                    set_switch("1", progress.lives >= 1);
-                    set_switch("2", progress.lives >= 2);
-                    set_switch("3", progress.lives >= 3);
-                    set_switch("4", progress.lives >= 4);
                     set_switch("Boss", is_boss_game);
 
                     object.replace_text(&text_replacements);
@@ -1083,37 +2861,85 @@ impl MainGame<Interlude> {
 
             let mut game = Game::from_data(game_data);
 
+            let resources = self.resources.clone();
+            let cached_assets = self.preloaded_assets.get(next_filename).cloned();
             let resources_loading = start_coroutine(async move {
-                let base_path = Path::new(next_filename).parent().unwrap();
-                let resources = Assets::load(&new_game_data.asset_files, base_path).await;
-                dispenser::store(resources);
+                let assets = match cached_assets {
+                    Some(assets) => Ok(assets),
+                    None => {
+                        let base_path = Path::new(next_filename).parent().unwrap();
+                        Assets::load(&resources, &new_game_data.asset_files, base_path).await
+                    }
+                };
+                dispenser::store(assets);
             });
 
             let playback_rate = self.state.progress.playback_rate;
 
-            assets.music.play(playback_rate, VOLUME);
+            self.music_fade
+                .fade_in(&assets.music, playback_rate, self.volume);
+
+            let mut timestep = FixedTimestep::default();
 
             while (game.frames.remaining() != FrameCount::Frames(0) && !game.end_early)
                 || !resources_loading.is_done()
             {
+                if macroquad::input::is_key_pressed(KeyCode::Escape) {
+                    pause(&game, &assets, &self.intro_font, playback_rate, self.volume).await;
+                }
+
                 game.frames.steps_taken += 1;
 
-                let frames_to_run = frames_to_run(game.frames, playback_rate);
+                let frames_to_run = frames_to_run(game.frames, &mut timestep, playback_rate);
                 for _ in 0..frames_to_run {
-                    update_frame(&mut game, assets, playback_rate)?;
+                    update_frame(&mut game, &assets, playback_rate, self.volume)?;
                 }
 
-                draw_game(&game, &assets.images, &assets.fonts, &self.intro_font);
+                handle_hotkeys();
+                draw_game(
+                    &game,
+                    &assets.images,
+                    &assets.fonts,
+                    &self.intro_font,
+                    playback_rate,
+                );
+
+                self.music_fade.update();
 
                 next_frame().await;
             }
 
-            assets.stop_sounds();
-
-            let assets = dispenser::take::<WeeResult<Assets>>()?;
+            assets.stop_sound_effects();
+            self.music_fade.fade_out(assets.music.clone(), self.volume);
+
+            let games_list = &mut self.state.games_list;
+            let played_games = &mut self.played_games;
+            let seed = self.seed;
+            let resources = &self.resources;
+            let games = &self.games;
+            let (next_filename, assets) = load_until_one_works(
+                next_filename,
+                dispenser::take::<WeeResult<Assets>>(),
+                || {
+                    let next_filename = if is_boss_game {
+                        games_list.choose_boss()?
+                    } else {
+                        games_list.choose_game()?
+                    };
+                    log::info!("Next game: {} (seed {})", next_filename, seed);
+                    played_games.insert(next_filename);
+                    Ok(next_filename)
+                },
+                |filename| {
+                    let base_path = Path::new(filename).parent().unwrap();
+                    Assets::load(resources, &games[filename].asset_files, base_path)
+                },
+            )
+            .await?;
 
-            let next_step = NextStep::Play(MainGame {
+            let next_step = NextStep::Play(Box::new(MainGame {
                 state: Play {
+                    filename: next_filename,
                     game_data: self.games[next_filename].clone(),
                     assets,
                     progress: self.state.progress,
@@ -1125,18 +2951,54 @@ impl MainGame<Interlude> {
                 preloaded_assets: self.preloaded_assets,
                 high_scores: self.high_scores,
                 played_games: self.played_games,
-            });
+                volume: self.volume,
+                music_fade: self.music_fade,
+                seed: self.seed,
+                settings: self.settings,
+                resources: self.resources,
+            }));
             Ok(next_step)
         }
     }
 }
 
 enum NextStep {
-    Play(MainGame<Play>),
-    Finished(MainGame<GameOver>),
+    Play(Box<MainGame<Play>>),
+    Finished(Box<MainGame<GameOver>>),
+}
+
+// Skips games that fail to load, only giving up when several fail in a row
+async fn load_until_one_works<T, F>(
+    mut filename: &'static str,
+    mut loaded: WeeResult<T>,
+    mut choose_next: impl FnMut() -> WeeResult<&'static str>,
+    mut load: impl FnMut(&'static str) -> F,
+) -> WeeResult<(&'static str, T)>
+where
+    F: std::future::Future<Output = WeeResult<T>>,
+{
+    let mut attempts = 1;
+    loop {
+        match loaded {
+            Ok(value) => return Ok((filename, value)),
+            Err(error) => {
+                log::error!("Failed to load {}: {}", filename, error);
+                if attempts >= MAX_LOAD_ATTEMPTS {
+                    return Err(
+                        format!("Failed to load {} games in a row: {}", attempts, error).into(),
+                    );
+                }
+                attempts += 1;
+
+                filename = choose_next()?;
+                loaded = load(filename).await;
+            }
+        }
+    }
 }
 
 struct Play {
+    filename: &'static str,
     game_data: GameData,
     assets: Assets,
     progress: Progress,
@@ -1144,46 +3006,165 @@ struct Play {
     is_boss_game: bool,
 }
 
+const RELOAD_ERROR_FRAMES: u32 = 300;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_dev_mode() -> bool {
+    std::env::var("WEE_DEV").is_ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn is_dev_mode() -> bool {
+    false
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn is_tracing() -> bool {
+    std::env::var("WEE_TRACE").is_ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn is_tracing() -> bool {
+    false
+}
+
+async fn reload_game(resources: &Resources, filename: &str) -> WeeResult<(GameData, Assets)> {
+    let game_data = resources.load_game_data(filename).await?;
+    game_data.validate()?;
+    let base_path = Path::new(filename).parent().unwrap();
+    let assets = Assets::load(resources, &game_data.asset_files, base_path).await?;
+    Ok((game_data, assets))
+}
+
 impl MainGame<Play> {
+    fn start_game(&self, game_data: GameData) -> Game {
+        let mut game = Game::from_data(game_data);
+        game.isolate_random(self.seed.wrapping_add(self.state.progress.score as u64));
+        game.difficulty = self.state.progress.difficulty;
+        game.score = self.state.progress.score;
+        game.lives = self.state.progress.lives;
+        game.extend_length(self.settings.extra_time.length_multiplier());
+        if is_tracing() {
+            game.enable_trace();
+        }
+        game
+    }
+
     async fn play(mut self) -> WeeResult<MainGame<Interlude>> {
         log::debug!("play");
         log::debug!("playback rate: {}", self.state.progress.playback_rate);
 
-        let mut game = Game::from_data(self.state.game_data);
-        game.difficulty = self.state.progress.difficulty;
+        let mut game = self.start_game(self.state.game_data.clone());
+        let mut reload_error: Option<(String, u32)> = None;
 
         let playback_rate = if self.state.is_boss_game {
             self.state.progress.boss_playback_rate
         } else {
             self.state.progress.playback_rate
         };
-        self.state.assets.music.play(playback_rate, VOLUME);
+        let playback_rate = self.settings.extra_time.cap_playback_rate(playback_rate);
+        self.music_fade
+            .fade_in(&self.state.assets.music, playback_rate, self.volume);
+
+        let mut timestep = FixedTimestep::default();
 
         while game.frames.remaining() != FrameCount::Frames(0) && !game.end_early {
+            if macroquad::input::is_key_pressed(KeyCode::Escape) {
+                pause(
+                    &game,
+                    &self.state.assets,
+                    &self.intro_font,
+                    playback_rate,
+                    self.volume,
+                )
+                .await;
+            }
+
+            if is_dev_mode() && macroquad::input::is_key_pressed(KeyCode::R) {
+                log::info!("Reloading {}", self.state.filename);
+                match reload_game(&self.resources, self.state.filename).await {
+                    Ok((game_data, assets)) => {
+                        self.state.assets.stop_sound_effects();
+                        self.music_fade
+                            .fade_out(std::mem::take(&mut self.state.assets.music), self.volume);
+                        self.state.game_data = game_data;
+                        self.state.assets = assets;
+                        game = self.start_game(self.state.game_data.clone());
+                        self.music_fade.fade_in(
+                            &self.state.assets.music,
+                            playback_rate,
+                            self.volume,
+                        );
+                        reload_error = None;
+                    }
+                    Err(error) => {
+                        log::error!("Failed to reload {}: {}", self.state.filename, error);
+                        reload_error = Some((error.to_string(), RELOAD_ERROR_FRAMES));
+                    }
+                }
+            }
+
             game.frames.steps_taken += 1;
 
-            let frames_to_run = frames_to_run(game.frames, playback_rate);
+            let frames_to_run = frames_to_run(game.frames, &mut timestep, playback_rate);
             for _ in 0..frames_to_run {
-                update_frame(&mut game, &self.state.assets, playback_rate)?;
+                update_frame(&mut game, &self.state.assets, playback_rate, self.volume)?;
             }
 
+            handle_hotkeys();
             draw_game(
                 &game,
                 &self.state.assets.images,
                 &self.state.assets.fonts,
                 &self.intro_font,
+                playback_rate,
             );
 
+            if let Some((error, frames_left)) = &mut reload_error {
+                macroquad::text::draw_text(error, 0.0, 32.0, 32.0, RED);
+                *frames_left -= 1;
+                if *frames_left == 0 {
+                    reload_error = None;
+                }
+            }
+
+            self.music_fade.update();
+
             next_frame().await;
         }
 
-        self.state.assets.stop_sounds();
+        self.state.assets.stop_sound_effects();
+        self.music_fade
+            .fade_out(self.state.assets.music.clone(), self.volume);
 
-        let has_won = match game.status.next_frame {
-            WinStatus::Won | WinStatus::HasBeenWon => true,
-            _ => false,
-        };
-        self.state.progress.update(has_won, self.state.is_boss_game);
+        let has_won = matches!(
+            game.status.next_frame,
+            WinStatus::Won | WinStatus::HasBeenWon
+        );
+        if !has_won {
+            for frame_trace in game.take_trace() {
+                for fired in frame_trace.fired {
+                    log::info!(
+                        "Frame {}: {} instruction {} fired",
+                        frame_trace.frame,
+                        fired.object,
+                        fired.instruction
+                    );
+                }
+            }
+        }
+        self.state.progress.update(
+            has_won,
+            self.state.is_boss_game,
+            game.lives_adjustment,
+            game.score_adjustment,
+            &self
+                .settings
+                .difficulty_curve(&self.state.games_list.directory),
+        );
+
+        self.preloaded_assets
+            .insert(self.state.filename, self.state.assets);
 
         Ok(MainGame {
             state: Interlude {
@@ -1195,55 +3176,51 @@ impl MainGame<Play> {
             preloaded_assets: self.preloaded_assets,
             high_scores: self.high_scores,
             played_games: self.played_games,
+            volume: self.volume,
+            music_fade: self.music_fade,
+            seed: self.seed,
+            settings: self.settings,
+            resources: self.resources,
         })
     }
 }
 
 struct GameOver {
     progress: Progress,
-    directory: String,
+    games_list: GamesList,
+}
+
+enum AfterGameOver {
+    Menu(MainGame<Menu>),
+    Continue(MainGame<Interlude>),
 }
 
 impl MainGame<GameOver> {
-    async fn return_to_menu(mut self) -> WeeResult<MainGame<Menu>> {
+    async fn return_to_menu(mut self) -> WeeResult<AfterGameOver> {
         log::debug!("return to menu");
 
         let (mut game_data, assets) = preloaded_game(
             &self.games,
-            &self.preloaded_assets,
-            &self.state.directory,
+            &mut self.preloaded_assets,
+            &self.state.games_list.directory,
             "game-over.json",
-        );
+        )?;
 
-        let mut high_scores = self
+        let high_scores = self
             .high_scores
-            .entry(self.state.directory)
-            .or_insert((0, 0, 0));
+            .entry(self.state.games_list.directory.clone())
+            .or_default();
         let progress = self.state.progress;
-        let score = progress.score;
-
-        let high_score_position: Option<i32> = if score >= high_scores.0 {
-            high_scores.2 = high_scores.1;
-            high_scores.1 = high_scores.0;
-            high_scores.0 = score;
-            Some(1)
-        } else if score >= high_scores.1 {
-            high_scores.2 = high_scores.1;
-            high_scores.1 = score;
-            Some(2)
-        } else if score >= high_scores.2 {
-            high_scores.2 = score;
-            Some(3)
-        } else {
-            None
-        };
+
+        let high_score_index = high_scores.insert(progress.score);
+        let high_score_position = high_score_index.map(|index| index as i32 + 1);
 
         let text_replacements = vec![
             ("{Score}", progress.score.to_string()),
             ("{Lives}", progress.lives.to_string()),
-            ("{1st}", high_scores.0.to_string()),
-            ("{2nd}", high_scores.1.to_string()),
-            ("{3rd}", high_scores.2.to_string()),
+            ("{1st}", high_scores.entries[0].text()),
+            ("{2nd}", high_scores.entries[1].text()),
+            ("{3rd}", high_scores.entries[2].text()),
         ];
         for object in game_data.objects.iter_mut() {
             object.replace_text(&text_replacements);
@@ -1257,29 +3234,117 @@ impl MainGame<GameOver> {
             set_switch("2nd", high_score_position == Some(2));
             set_switch("3rd", high_score_position == Some(3));
         }
+        if !progress.can_continue() {
+            game_data.objects.retain(|object| object.name != "Continue");
+        }
 
         let mut game = Game::from_data(game_data);
 
-        assets.music.play(1.0, VOLUME);
+        self.music_fade.fade_in(&assets.music, 1.0, self.volume);
 
+        let mut is_continuing = false;
+        let mut name = String::new();
+        let mut is_entering_name = high_score_index.is_some();
         while game.frames.remaining() != FrameCount::Frames(0) && !game.end_early {
-            update_frame(&mut game, assets, DEFAULT_PLAYBACK_RATE)?;
+            update_frame(&mut game, &assets, DEFAULT_PLAYBACK_RATE, self.volume)?;
+
+            if is_entering_name {
+                type_name(
+                    &mut name,
+                    std::iter::from_fn(macroquad::input::get_char_pressed),
+                );
+                if macroquad::input::is_key_pressed(KeyCode::Backspace) {
+                    name.pop();
+                }
+                if macroquad::input::is_key_pressed(KeyCode::Enter) {
+                    is_entering_name = false;
+                }
+            }
+
+            if let Some(object) = game.objects.get("Continue") {
+                if object.switch == SwitchState::SwitchedOn && progress.can_continue() {
+                    is_continuing = true;
+                    break;
+                }
+            }
+
+            handle_hotkeys();
+            draw_game(
+                &game,
+                &assets.images,
+                &assets.fonts,
+                &self.intro_font,
+                DEFAULT_PLAYBACK_RATE,
+            );
+
+            if high_score_index.is_some() {
+                let cursor = if is_entering_name { "_" } else { "" };
+                let line = format!("Name: {}{}", name, cursor);
+                let size = macroquad::text::measure_text(&line, Some(self.intro_font), 48, 1.0);
+                let params = macroquad::text::TextParams {
+                    font: self.intro_font,
+                    font_size: 48,
+                    font_scale: 1.0,
+                    font_scale_aspect: 1.0,
+                    color: WHITE,
+                };
+                macroquad::text::draw_text_ex(
+                    &line,
+                    PROJECTION_WIDTH / 2.0 - size.width / 2.0,
+                    PROJECTION_HEIGHT - 60.0,
+                    params,
+                );
+            }
 
-            draw_game(&game, &assets.images, &assets.fonts, &self.intro_font);
+            self.music_fade.update();
 
             next_frame().await;
         }
 
-        assets.stop_sounds();
+        assets.stop_sound_effects();
+        self.music_fade.fade_out(assets.music.clone(), self.volume);
 
-        Ok(MainGame {
+        if let Some(index) = high_score_index {
+            log::info!("High score {} by {}", progress.score, name.trim());
+            if let Some(high_scores) = self.high_scores.get_mut(&self.state.games_list.directory) {
+                high_scores.set_name(index, &name);
+            }
+        }
+
+        if is_continuing {
+            let mut progress = progress;
+            progress.continue_run();
+            return Ok(AfterGameOver::Continue(MainGame {
+                state: Interlude {
+                    progress,
+                    games_list: self.state.games_list,
+                },
+                intro_font: self.intro_font,
+                games: self.games,
+                preloaded_assets: self.preloaded_assets,
+                high_scores: self.high_scores,
+                played_games: self.played_games,
+                volume: self.volume,
+                music_fade: self.music_fade,
+                seed: self.seed,
+                settings: self.settings,
+                resources: self.resources,
+            }));
+        }
+
+        Ok(AfterGameOver::Menu(MainGame {
             state: Menu {},
             intro_font: self.intro_font,
             games: self.games,
             preloaded_assets: self.preloaded_assets,
             high_scores: self.high_scores,
             played_games: self.played_games,
-        })
+            volume: self.volume,
+            music_fade: self.music_fade,
+            seed: self.seed,
+            settings: self.settings,
+            resources: self.resources,
+        }))
     }
 }
 
@@ -1288,7 +3353,7 @@ fn window_conf() -> Conf {
         window_title: "Weegames Demo".to_string(),
         window_width: 1600,
         window_height: 900,
-        fullscreen: true,
+        fullscreen: IS_FULLSCREEN.load(Ordering::Relaxed),
         ..Default::default()
     }
 }
@@ -1297,17 +3362,18 @@ fn window_conf() -> Conf {
 async fn main() {
     log::debug!("Start game");
 
-    macroquad::rand::srand(macroquad::miniquad::date::now() as _);
+    let seed = random_seed();
+    log::info!("Seed: {}", seed);
+    macroquad::rand::srand(seed);
 
-    let camera = macroquad::camera::Camera2D::from_display_rect(macroquad::math::Rect::new(
+    set_projection_camera(macroquad::math::Rect::new(
         0.0,
         0.0,
         PROJECTION_WIDTH,
         PROJECTION_HEIGHT,
     ));
-    macroquad::camera::set_camera(&camera);
 
-    let main_game = MainGame::<LoadingScreen>::load().await;
+    let main_game = MainGame::<LoadingScreen>::load(seed).await;
 
     let mut main_game = match main_game {
         Ok(main_game) => main_game,
@@ -1333,8 +3399,817 @@ async fn main() {
                     macroquad::text::draw_text(&error, 0.0, 64.0, 64.0, WHITE);
                     next_frame().await;
                 }
-                MainGame::<LoadingScreen>::load().await.unwrap()
+                MainGame::<LoadingScreen>::load(seed).await.unwrap()
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const GAME_JSON: &str = r#"{
+        "format_version": "0.2",
+        "published": true,
+        "game_type": "Minigame",
+        "objects": [],
+        "background": [],
+        "asset_files": { "images": { "Dot": "dot.png" }, "audio": {}, "fonts": {} },
+        "length": { "Seconds": 4.0 },
+        "intro_text": "Zipped!",
+        "attribution": ""
+    }"#;
+    const DOT_PNG: &[u8] = b"\x89PNG not really an image";
+
+    fn zipped_folder() -> WeeResult<Vec<u8>> {
+        zipped_game(GAME_JSON)
+    }
+
+    fn zipped_game(game_json: &str) -> WeeResult<Vec<u8>> {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        zip.start_file("game.json", options)?;
+        zip.write_all(game_json.as_bytes())?;
+        zip.start_file("images/dot.png", options)?;
+        zip.write_all(DOT_PNG)?;
+        Ok(zip.finish()?.into_inner())
+    }
+
+    fn zip_resources() -> WeeResult<Resources> {
+        let source = ZipSource::new("games/zipped", zipped_folder()?)?;
+        Ok(Resources {
+            sources: vec![Arc::new(source)],
+        })
+    }
+
+    #[test]
+    fn zip_source_lists_games() -> WeeResult<()> {
+        let source = ZipSource::new("games/zipped", zipped_folder()?)?;
+
+        assert_eq!(
+            source.game_files()?,
+            vec![PathBuf::from("games/zipped/game.json")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn zip_source_only_claims_its_folder() -> WeeResult<()> {
+        let source = ZipSource::new("games/zipped", zipped_folder()?)?;
+
+        assert!(source.contains(Path::new("games/zipped/game.json")));
+        assert!(!source.contains(Path::new("games/loose/game.json")));
+        Ok(())
+    }
+
+    #[test]
+    fn loads_asset_bytes_from_zip() -> WeeResult<()> {
+        let resources = zip_resources()?;
+
+        let bytes =
+            futures::executor::block_on(resources.load_bytes("games/zipped/images/dot.png"))?;
+
+        assert_eq!(bytes, DOT_PNG);
+        Ok(())
+    }
+
+    #[test]
+    fn loads_game_data_from_zip() -> WeeResult<()> {
+        let resources = zip_resources()?;
+
+        let game_data =
+            futures::executor::block_on(resources.load_game_data("games/zipped/game.json"))?;
+
+        assert_eq!(game_data.intro_text.as_deref(), Some("Zipped!"));
+        assert_eq!(
+            game_data.asset_files.images.get("Dot").map(String::as_str),
+            Some("dot.png")
+        );
+        Ok(())
+    }
+
+    fn games_folder(filenames: &[&'static str]) -> HashMap<&'static str, GameData> {
+        filenames
+            .iter()
+            .map(|filename| (*filename, GameData::builder().published(true).build()))
+            .collect()
+    }
+
+    #[test]
+    fn recently_played_games_are_not_chosen_again() -> WeeResult<()> {
+        let games = games_folder(&["games/a/1.json", "games/a/2.json", "games/a/3.json"]);
+        let mut games_list = GamesList::from_directory(&games, "games/a".to_string(), 7, 2);
+
+        let mut chosen = Vec::new();
+        for _ in 0..30 {
+            chosen.push(games_list.choose_game()?);
+        }
+
+        for window in chosen.windows(3) {
+            assert!(window[0] != window[1] && window[1] != window[2] && window[0] != window[2]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn small_folders_allow_repeats() -> WeeResult<()> {
+        let games = games_folder(&["games/a/1.json", "games/a/2.json"]);
+        let mut games_list = GamesList::from_directory(&games, "games/a".to_string(), 7, 2);
+
+        let mut chosen = Vec::new();
+        for _ in 0..10 {
+            chosen.push(games_list.choose_game()?);
+        }
+
+        for window in chosen.windows(2) {
+            assert_ne!(window[0], window[1]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn folders_without_bosses_choose_a_regular_game() -> WeeResult<()> {
+        let games = games_folder(&["games/a/1.json", "games/a/2.json"]);
+        let mut games_list = GamesList::from_directory(&games, "games/a".to_string(), 7, 2);
+
+        let boss = games_list.choose_boss()?;
+
+        assert!(games.contains_key(boss));
+        assert_ne!(games_list.choose_game()?, boss);
+
+        let mut empty_list = GamesList::from_directory(&games, "games/b".to_string(), 7, 2);
+        assert!(empty_list.choose_boss().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn sound_limiter_caps_simultaneous_sounds() {
+        let mut limiter = SoundLimiter::new(3);
+        limiter.advance();
+
+        let started = (0..10).filter(|_| limiter.try_start("pop")).count();
+
+        assert_eq!(started, 3);
+        assert!(limiter.try_start("bang"));
+    }
+
+    #[test]
+    fn sound_limiter_allows_sounds_again_after_a_few_frames() {
+        let mut limiter = SoundLimiter::new(2);
+        limiter.advance();
+        assert_eq!((0..10).filter(|_| limiter.try_start("pop")).count(), 2);
+
+        for _ in 0..SOUND_LIMIT_FRAMES {
+            limiter.advance();
+        }
+
+        assert_eq!((0..10).filter(|_| limiter.try_start("pop")).count(), 2);
+    }
+
+    fn no_assets() -> Assets {
+        Assets {
+            images: Images::new(),
+            music: Vec::new(),
+            sounds: Sounds::new(),
+            fonts: Fonts::new(),
+        }
+    }
+
+    #[test]
+    fn asset_cache_keeps_at_most_capacity_and_pinned_entries() {
+        let pinned = vec![
+            ("games/system/a.json", no_assets()),
+            ("games/system/b.json", no_assets()),
+        ]
+        .into_iter()
+        .collect();
+        let mut cache = AssetCache::new(pinned, 3);
+
+        for filename in &["1", "2", "3", "4", "5", "6", "6", "games/system/a.json"] {
+            cache.insert(filename, no_assets());
+        }
+
+        assert_eq!(cache.pinned.len() + cache.recent.len(), 2 + 3);
+        assert!(cache.get("games/system/a.json").is_some());
+        assert!(cache.get("1").is_none());
+        assert!(cache.get("6").is_some());
+    }
+
+    #[test]
+    fn asset_cache_evicts_the_least_recently_used_entry() {
+        let mut cache = AssetCache::new(HashMap::new(), 3);
+        for filename in &["1", "2", "3"] {
+            cache.insert(filename, no_assets());
+        }
+
+        assert!(cache.get("1").is_some());
+        cache.insert("4", no_assets());
+
+        assert!(cache.get("1").is_some());
+        assert!(cache.get("2").is_none());
+        assert!(cache.get("3").is_some());
+        assert!(cache.get("4").is_some());
+    }
+
+    fn wav_header(chunk_size: u32) -> Vec<u8> {
+        let mut bytes = b"RIFF\0\0\0\0WAVE".to_vec();
+        bytes.extend_from_slice(b"LIST");
+        bytes.extend_from_slice(&chunk_size.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn wav_with_oversized_chunk_has_no_length() {
+        assert_eq!(sound_length(&wav_header(u32::MAX)), None);
+    }
+
+    #[test]
+    fn volume_stays_between_zero_and_one() {
+        assert_eq!(adjust_volume(0.95, VOLUME_STEP), 1.0);
+        assert_eq!(adjust_volume(0.05, -VOLUME_STEP), 0.0);
+        assert_eq!(adjust_volume(1.5, 0.0), 1.0);
+        assert!((adjust_volume(DEFAULT_VOLUME, VOLUME_STEP) - 0.6).abs() < 0.0001);
+    }
+
+    #[test]
+    fn background_part_is_drawn_at_its_own_size() {
+        let area = wee::AABB {
+            min: wee::Vec2::new(400.0, 400.0),
+            max: wee::Vec2::new(600.0, 600.0),
+        };
+
+        let rect = background_rect(area);
+
+        assert_eq!(
+            (rect.x, rect.y, rect.w, rect.h),
+            (400.0, 400.0, 200.0, 200.0)
+        );
+    }
+
+    fn region(min: (f32, f32), max: (f32, f32)) -> Sprite {
+        Sprite::ImageRegion {
+            name: "atlas".to_string(),
+            source: wee::AABB {
+                min: wee::Vec2::new(min.0, min.1),
+                max: wee::Vec2::new(max.0, max.1),
+            },
+        }
+    }
+
+    #[test]
+    fn image_region_is_drawn_from_its_source_rect() {
+        let rect = sprite_source(&region((32.0, 0.0), (64.0, 48.0))).expect("source rect");
+
+        assert_eq!((rect.x, rect.y, rect.w, rect.h), (32.0, 0.0, 32.0, 48.0));
+    }
+
+    #[test]
+    fn whole_images_have_no_source_rect() {
+        let sprite = Sprite::Image {
+            name: "atlas".to_string(),
+        };
+
+        assert!(sprite_source(&sprite).is_none());
+    }
+
+    // Every character is 10 wide so this wraps at the width of max_characters letters
+    fn wrapped(text: &str, max_characters: usize) -> Vec<String> {
+        let width = |text: &str| text.chars().count() as f32 * 10.0;
+        wrap_text(text, max_characters as f32 * 10.0, width)
+    }
+
+    #[test]
+    fn text_wraps_onto_two_lines() {
+        assert_eq!(wrapped("hello world foo", 10), vec!["hello", "world foo"]);
+    }
+
+    #[test]
+    fn long_words_are_broken_at_the_width() {
+        assert_eq!(
+            wrapped("abcdefghijklmnop", 10),
+            vec!["abcdefghij", "klmnop"]
+        );
+    }
+
+    #[test]
+    fn empty_text_wraps_to_one_empty_line() {
+        assert_eq!(wrapped("", 10), vec![""]);
+    }
+
+    #[test]
+    fn adjusted_lives_are_capped_at_the_maximum() {
+        let curve = DifficultyCurve::default();
+        let mut progress = Progress::new(4);
+        progress.lives = 3;
+
+        progress.update(true, false, 1, 0, &curve);
+        assert_eq!(progress.lives, 4);
+        assert!(progress.last_game.unwrap().was_life_gained);
+
+        progress.update(true, false, 1, 0, &curve);
+        assert_eq!(progress.lives, 4);
+        assert!(!progress.last_game.unwrap().was_life_gained);
+    }
+
+    #[test]
+    fn reload_fails_when_the_game_no_longer_validates() -> WeeResult<()> {
+        let game_json = GAME_JSON.replace(
+            r#""background": []"#,
+            r#""background": [{
+                "sprite": { "Image": { "name": "Missing" } },
+                "area": { "min": { "x": 0.0, "y": 0.0 }, "max": { "x": 1600.0, "y": 900.0 } }
+            }]"#,
+        );
+        let source = ZipSource::new("games/zipped", zipped_game(&game_json)?)?;
+        let resources = Resources {
+            sources: vec![Arc::new(source)],
+        };
+
+        let error = futures::executor::block_on(reload_game(&resources, "games/zipped/game.json"))
+            .err()
+            .unwrap();
+
+        assert!(error.to_string().contains("image 'Missing'"));
+        Ok(())
+    }
+
+    fn missing_image() -> AssetFiles {
+        let mut asset_files = AssetFiles::default();
+        asset_files
+            .images
+            .insert("Dot".to_string(), "images/missing.png".to_string());
+        asset_files
+    }
+
+    #[test]
+    fn skips_a_game_whose_asset_file_is_missing() -> WeeResult<()> {
+        let resources = zip_resources()?;
+        let mut games = HashMap::new();
+        games.insert("games/zipped/broken.json", missing_image());
+        games.insert("games/zipped/game.json", AssetFiles::default());
+        let load = |filename: &'static str| {
+            Assets::load(&resources, &games[filename], Path::new("games/zipped"))
+        };
+
+        let first = futures::executor::block_on(load("games/zipped/broken.json"));
+        assert!(first.is_err());
+        let (filename, _) = futures::executor::block_on(load_until_one_works(
+            "games/zipped/broken.json",
+            first,
+            || Ok("games/zipped/game.json"),
+            load,
+        ))?;
+
+        assert_eq!(filename, "games/zipped/game.json");
+        Ok(())
+    }
+
+    #[test]
+    fn gives_up_after_several_games_fail_to_load_in_a_row() {
+        let mut attempts = 0;
+
+        let result = futures::executor::block_on(load_until_one_works(
+            "games/zipped/broken.json",
+            Err::<(), _>("missing".into()),
+            || Ok("games/zipped/broken.json"),
+            |_| {
+                attempts += 1;
+                async { Err("missing".into()) }
+            },
+        ));
+
+        assert!(result.is_err());
+        assert_eq!(attempts, MAX_LOAD_ATTEMPTS - 1);
+    }
+
+    fn assert_maps_to(letterbox: Letterbox, screen: (f32, f32), projection: (f32, f32)) {
+        let mapped = letterbox.to_projection(screen.0, screen.1);
+        assert!((mapped.x - projection.0).abs() < 0.01, "{:?}", mapped);
+        assert!((mapped.y - projection.1).abs() < 0.01, "{:?}", mapped);
+    }
+
+    #[test]
+    fn letterbox_fills_a_16_by_9_window() {
+        let letterbox = Letterbox::fit(1920.0, 1080.0);
+
+        assert_eq!(letterbox.viewport(), (0, 0, 1920, 1080));
+        assert_maps_to(letterbox, (0.0, 0.0), (0.0, 0.0));
+        assert_maps_to(letterbox, (960.0, 540.0), (800.0, 450.0));
+        assert_maps_to(letterbox, (1920.0, 1080.0), (1600.0, 900.0));
+    }
+
+    #[test]
+    fn letterbox_adds_bars_to_a_square_window() {
+        let letterbox = Letterbox::fit(1000.0, 1000.0);
+
+        assert_eq!(letterbox.viewport(), (0, 219, 1000, 563));
+        assert_maps_to(letterbox, (0.0, 218.75), (0.0, 0.0));
+        assert_maps_to(letterbox, (500.0, 500.0), (800.0, 450.0));
+        assert_maps_to(letterbox, (1000.0, 781.25), (1600.0, 900.0));
+    }
+
+    #[test]
+    fn screenshots_crop_off_the_letterbox_bars() {
+        // Each pixel's bytes are its row, counting from the bottom of the window
+        let screen = Image {
+            bytes: (0..3u8).flat_map(|row| vec![row; 4 * 4]).collect(),
+            width: 4,
+            height: 3,
+        };
+        let letterbox = Letterbox {
+            x: 0.0,
+            y: 1.0,
+            width: 4.0,
+            height: 1.0,
+        };
+
+        let screenshot = crop_to_letterbox(&screen, letterbox);
+
+        assert_eq!((screenshot.width, screenshot.height), (4, 1));
+        assert_eq!(screenshot.bytes, vec![1; 4 * 4]);
+    }
+
+    #[test]
+    fn screenshot_file_is_written() {
+        let directory = std::env::temp_dir().join("weegames-screenshot-test");
+        std::fs::create_dir_all(&directory).unwrap();
+        let screenshot = Image {
+            bytes: vec![255; 2 * 2 * 4],
+            width: 2,
+            height: 2,
+        };
+
+        let path = save_screenshot_in(&directory, &screenshot);
+
+        assert!(path.exists());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn practice_rounds_start_with_full_lives_at_the_chosen_difficulty() {
+        let settings = Settings {
+            lives: Some(3),
+            ..Settings::default()
+        };
+        let practice = Practice {
+            filename: "games/yeah/balloon.json",
+            difficulty: 3,
+            playback_rate: 1.5,
+        };
+
+        let progress = practice.progress(&settings);
+
+        assert_eq!(progress.lives, 3);
+        assert_eq!(progress.difficulty, 3);
+        assert_eq!(progress.playback_rate, 1.5);
+        assert_eq!(progress.boss_playback_rate, 1.5);
+    }
+
+    #[test]
+    fn practice_speed_stays_between_the_limits() {
+        let mut practice = Practice {
+            filename: "games/yeah/balloon.json",
+            difficulty: 1,
+            playback_rate: INITIAL_PLAYBACK_RATE,
+        };
+
+        practice.change_speed(-PLAYBACK_RATE_INCREASE);
+        assert_eq!(practice.playback_rate, INITIAL_PLAYBACK_RATE);
+
+        practice.change_speed(PLAYBACK_RATE_MAX);
+        assert_eq!(practice.playback_rate, PLAYBACK_RATE_MAX);
+    }
+
+    fn difficulty_after_wins(curve: &DifficultyCurve, games: i32) -> u32 {
+        let mut progress = Progress::new(4);
+        for _ in 0..games {
+            progress.update(true, false, 0, 0, curve);
+        }
+        progress.difficulty
+    }
+
+    #[test]
+    fn custom_difficulty_curve_increases_difficulty_earlier() {
+        let curve = DifficultyCurve {
+            up_to_difficulty_two: 10,
+            ..DifficultyCurve::default()
+        };
+
+        assert_eq!(difficulty_after_wins(&curve, 9), 1);
+        assert_eq!(difficulty_after_wins(&curve, 10), 2);
+        assert_eq!(difficulty_after_wins(&DifficultyCurve::default(), 10), 1);
+    }
+
+    #[test]
+    fn score_and_difficulty_survive_a_continue() {
+        let curve = DifficultyCurve::default();
+        let mut progress = Progress::new(1);
+        for _ in 0..24 {
+            progress.update(true, false, 0, 0, &curve);
+        }
+        progress.update(false, false, 0, 0, &curve);
+        assert_eq!(progress.lives, 0);
+
+        progress.continue_run();
+
+        assert_eq!(progress.score, 25);
+        assert_eq!(progress.difficulty, 2);
+        assert_eq!(progress.lives, 1);
+        assert_eq!(progress.playback_rate, INITIAL_PLAYBACK_RATE);
+    }
+
+    #[test]
+    fn continues_are_limited_per_run() {
+        let mut progress = Progress::new(4);
+        for _ in 0..MAX_CONTINUES {
+            assert!(progress.can_continue());
+            progress.continue_run();
+        }
+
+        assert!(!progress.can_continue());
+    }
+
+    type TrackEvents = RefCell<Vec<(usize, &'static str)>>;
+
+    #[derive(Copy, Clone, PartialEq)]
+    struct FakeTrack {
+        id: usize,
+        events: &'static TrackEvents,
+    }
+
+    impl SoundHandle for FakeTrack {
+        fn play(self, _params: PlaySoundParams) {
+            self.events.borrow_mut().push((self.id, "play"));
+        }
+
+        fn stop(self) {
+            self.events.borrow_mut().push((self.id, "stop"));
+        }
+
+        fn set_volume(self, _volume: f32) {}
+    }
+
+    #[test]
+    fn music_layers_start_and_stop_together() {
+        let events: &'static TrackEvents = Box::leak(Box::new(RefCell::new(Vec::new())));
+        let layer = |id: usize, looped: bool| Music {
+            data: FakeTrack { id, events },
+            looped,
+            volume: 1.0,
+        };
+        let layers = vec![layer(0, true), layer(1, false)];
+
+        layers.play(1.0, DEFAULT_VOLUME);
+        assert_eq!(events.replace(Vec::new()), vec![(0, "play"), (1, "play")]);
+
+        layers.stop();
+        assert_eq!(events.replace(Vec::new()), vec![(0, "stop"), (1, "stop")]);
+    }
+
+    #[test]
+    fn typed_name_is_kept_with_the_high_score() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(10);
+        let index = high_scores.insert(15).unwrap();
+
+        let mut name = String::new();
+        type_name(&mut name, "ab!x".chars());
+        name.pop();
+        type_name(&mut name, " cdefghijk".chars());
+        high_scores.set_name(index, &name);
+
+        assert_eq!(high_scores.entries[0].name, "AB CDEFG");
+        assert_eq!(high_scores.entries[0].text(), "AB CDEFG 15");
+        assert_eq!(high_scores.entries[1].text(), "10");
+    }
+
+    fn logic_steps_in_4_seconds(refresh_rate: u32) -> u32 {
+        let mut timestep = FixedTimestep::default();
+        (0..refresh_rate * 4)
+            .map(|_| timestep.steps(1.0 / refresh_rate as f32, 1.0, false))
+            .sum()
+    }
+
+    #[test]
+    fn logic_steps_do_not_depend_on_the_refresh_rate() {
+        for refresh_rate in [30, 60, 144].iter() {
+            let steps = logic_steps_in_4_seconds(*refresh_rate);
+            // Allow for a step still being saved up at the end
+            assert!(
+                (239..=240).contains(&steps),
+                "{} at {}Hz",
+                steps,
+                refresh_rate
+            );
+        }
+    }
+
+    #[test]
+    fn new_input_borrows_a_step_early() {
+        let mut timestep = FixedTimestep::default();
+
+        assert_eq!(timestep.steps(1.0 / 144.0, 1.0, true), 1);
+        assert_eq!(timestep.steps(1.0 / 144.0, 1.0, false), 0);
+        assert_eq!(timestep.steps(1.0 / 144.0, 1.0, false), 0);
+    }
+
+    #[test]
+    fn stick_moves_the_virtual_cursor_within_the_projection() {
+        let centre = wee::Vec2::new(800.0, 450.0);
+
+        let moved = gamepad::move_cursor(centre, wee::Vec2::new(1.0, -0.5));
+        assert!(moved.x > centre.x && moved.y < centre.y);
+
+        let corner = wee::Vec2::new(PROJECTION_WIDTH, 0.0);
+        let clamped = gamepad::move_cursor(corner, wee::Vec2::new(1.0, -1.0));
+        assert_eq!(clamped, corner);
+    }
+
+    #[test]
+    fn click_button_presses_then_holds_then_releases() {
+        let pressed = gamepad::next_button_state(ButtonState::Up, true);
+        assert_eq!(pressed, ButtonState::Press);
+
+        let held = gamepad::next_button_state(pressed, true);
+        assert_eq!(held, ButtonState::Down);
+
+        let released = gamepad::next_button_state(held, false);
+        assert_eq!(released, ButtonState::Release);
+        assert_eq!(gamepad::next_button_state(released, false), ButtonState::Up);
+    }
+
+    #[test]
+    fn unknown_font_falls_back_to_the_intro_font() {
+        let mut fonts = HashMap::new();
+        fonts.insert("Fonty".to_string(), ("fonty.ttf", 60));
+
+        assert_eq!(
+            font_or_fallback(&fonts, "Fonty", "intro.ttf"),
+            ("fonty.ttf", 60)
+        );
+        assert_eq!(
+            font_or_fallback(&fonts, "Fontee", "intro.ttf"),
+            ("intro.ttf", FALLBACK_FONT_SIZE)
+        );
+        assert_eq!(
+            font_or_fallback(&fonts, "Fontee", "intro.ttf"),
+            ("intro.ttf", FALLBACK_FONT_SIZE)
+        );
+    }
+
+    #[test]
+    fn one_life_run_ends_after_the_first_loss() {
+        let settings = Settings {
+            lives: Some(1),
+            ..Settings::default()
+        };
+        let curve = DifficultyCurve::default();
+        let mut progress = Progress::from_settings(&settings);
+        assert_eq!(progress.lives, 1);
+
+        progress.update(true, true, 0, 0, &curve);
+        assert_eq!(progress.lives, 1);
+
+        progress.update(false, false, 0, 0, &curve);
+        assert_eq!(progress.lives, 0);
+
+        let mut indicator = SerialiseObject::builder().name("1").build();
+        set_life_switch(&mut indicator, progress.lives);
+        assert_eq!(indicator.switch, Switch::Off);
+    }
+
+    const LONG_TEXT: &str = "a long line of text";
+
+    // Every character is half as wide as the font size is tall
+    fn fake_measure(text: &str, _font: (), font_size: u16) -> wee::Size {
+        let size = font_size as f32;
+        wee::Size::new(text.chars().count() as f32 * size / 2.0, size)
+    }
+
+    fn tiny_sign(resize: TextResize) -> WeeResult<Game> {
+        let sign = SerialiseObject::builder()
+            .name("Sign")
+            .size(wee::Size::new(10.0, 10.0))
+            .instruction(Instruction {
+                triggers: vec![Trigger::Time(When::Start)],
+                actions: vec![Action::DrawText {
+                    text: LONG_TEXT.to_string(),
+                    font: "Fonty".to_string(),
+                    colour: Colour {
+                        r: 1.0,
+                        g: 1.0,
+                        b: 1.0,
+                        a: 1.0,
+                    },
+                    resize,
+                    justify: JustifyText::Centre,
+                    max_width: None,
+                }],
+                cooldown: 0,
+            })
+            .build();
+        let game_data = GameData::builder()
+            .object(sign)
+            .length(Length::Seconds(4.0))
+            .build();
+        let mut game = Game::from_data(game_data);
+        game.run_headless(std::iter::empty(), 1)?;
+
+        let mut fonts = HashMap::new();
+        fonts.insert("Fonty".to_string(), ((), 20));
+        resize_objects_to_text(&mut game, &fonts, fake_measure);
+        Ok(game)
+    }
+
+    #[test]
+    fn match_text_grows_a_tiny_object_to_fit_long_text() -> WeeResult<()> {
+        let game = tiny_sign(TextResize::MatchText)?;
+
+        assert_eq!(game.objects["Sign"].size, wee::Size::new(190.0, 20.0));
+        Ok(())
+    }
+
+    #[test]
+    fn match_object_shrinks_long_text_into_a_tiny_object() -> WeeResult<()> {
+        let game = tiny_sign(TextResize::MatchObject)?;
+        let sign = &game.objects["Sign"];
+        assert_eq!(sign.size, wee::Size::new(10.0, 10.0));
+
+        let lines = text_lines(&game.drawn_text["Sign"], sign, |line| {
+            fake_measure(line, (), 20).width
+        });
+        let text_size = measure_lines(&lines, 20, |line| fake_measure(line, (), 20));
+        let scale = scale_to_fit(text_size, sign.size);
+
+        assert!((scale - 10.0 / 190.0).abs() < 0.0001);
+        assert!(text_size.width * scale <= 10.0 + 0.0001);
+        assert!(text_size.height * scale <= 10.0);
+        Ok(())
+    }
+
+    #[test]
+    fn panned_sounds_are_quieter_at_either_edge() {
+        assert_eq!(pan_volume(None), 1.0);
+        assert_eq!(pan_volume(Some(0.0)), 1.0);
+        assert_eq!(pan_volume(Some(-1.0)), 1.0 - EDGE_ATTENUATION);
+        assert_eq!(pan_volume(Some(1.0)), 1.0 - EDGE_ATTENUATION);
+    }
+
+    #[test]
+    fn mirrored_clicks_hit_the_object_on_the_other_side() -> WeeResult<()> {
+        let button = |name: &str, x: f32| {
+            SerialiseObject::builder()
+                .name(name)
+                .position(wee::Vec2::new(x, 450.0))
+                .instruction(Instruction {
+                    triggers: vec![Trigger::Input(Input::Mouse {
+                        over: MouseOver::Object {
+                            name: name.to_string(),
+                        },
+                        interaction: MouseInteraction::Button {
+                            state: ButtonState::Press,
+                        },
+                    })],
+                    actions: vec![Action::SetProperty(PropertySetter::Switch(Switch::On))],
+                    cooldown: 0,
+                })
+                .build()
+        };
+        let game_data = GameData::builder()
+            .object(button("Left", 400.0))
+            .object(button("Right", PROJECTION_WIDTH - 400.0))
+            .length(Length::Seconds(4.0))
+            .build();
+        let mut game = Game::from_data(game_data);
+
+        MIRRORED.store(true, Ordering::Relaxed);
+        let position = mirror_point(wee::Vec2::new(400.0, 450.0));
+        MIRRORED.store(false, Ordering::Relaxed);
+        let click = Mouse {
+            position,
+            state: ButtonState::Press,
+        };
+        game.run_headless(std::iter::once(click), 1)?;
+
+        assert_eq!(position.x, PROJECTION_WIDTH - 400.0);
+        assert_eq!(game.objects["Right"].switch, SwitchState::SwitchedOn);
+        assert_eq!(game.objects["Left"].switch, SwitchState::Off);
+        Ok(())
+    }
+
+    #[test]
+    fn high_scores_keep_older_ties_and_ignore_empty_runs() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.insert(0), None);
+
+        assert_eq!(high_scores.insert(10), Some(0));
+        high_scores.set_name(0, "OLD");
+        assert_eq!(high_scores.insert(10), Some(1));
+
+        assert_eq!(high_scores.entries[0].text(), "OLD 10");
+        assert_eq!(high_scores.entries[1].text(), "10");
+        assert_eq!(high_scores.entries[2].score, 0);
+    }
+}
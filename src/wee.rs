@@ -5,15 +5,19 @@ use c2::prelude::*;
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use std::{
+    cell::RefCell,
     collections::{HashMap, HashSet},
     default::Default,
     error::Error,
+    fmt,
     ops::Not,
     path::Path,
     str,
 };
 
 pub const FPS: f32 = 60.0;
+pub const PROJECTION_WIDTH: f32 = 1600.0;
+pub const PROJECTION_HEIGHT: f32 = 900.0;
 
 pub type WeeResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
 
@@ -29,7 +33,7 @@ impl Vec2 {
     fn zero() -> Vec2 {
         Vec2::new(0.0, 0.0)
     }
-    fn magnitude(self) -> f32 {
+    pub fn magnitude(self) -> f32 {
         (self.x * self.x + self.y * self.y).sqrt()
     }
     fn unit(self) -> Vec2 {
@@ -120,14 +124,20 @@ impl AABB {
             max: self.max + pos,
         }
     }
-}
 
-#[derive(Copy, Clone, Serialize, Deserialize, Debug, PartialEq)]
-struct Rect {
-    x: f32, // centre x
-    y: f32, // centre y
-    w: f32,
-    h: f32,
+    fn overlaps(self, other: AABB) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+
+    fn union(self, other: AABB) -> AABB {
+        AABB {
+            min: Vec2::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            max: Vec2::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -146,6 +156,10 @@ impl Colour {
     fn black() -> Colour {
         Colour::rgb(0.0, 0.0, 0.0)
     }
+
+    fn white() -> Colour {
+        Colour::rgb(1.0, 1.0, 1.0)
+    }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
@@ -155,26 +169,17 @@ pub struct Size {
 }
 
 impl Size {
-    fn new(width: f32, height: f32) -> Size {
+    pub fn new(width: f32, height: f32) -> Size {
         Size { width, height }
     }
 }
 
-#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct Flip {
     pub horizontal: bool,
     pub vertical: bool,
 }
 
-impl Default for Flip {
-    fn default() -> Flip {
-        Flip {
-            horizontal: false,
-            vertical: false,
-        }
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SerialiseObject {
     pub name: String,
@@ -188,6 +193,263 @@ pub struct SerialiseObject {
     layer: u8,
     pub switch: Switch,
     instructions: Vec<Instruction>,
+    #[serde(default = "Colour::white")]
+    tint: Colour,
+    #[serde(default = "CollisionShape::box_shape")]
+    collision_shape: CollisionShape,
+    #[serde(default = "default_solid")]
+    solid: bool,
+    #[serde(default = "CursorStyle::default_cursor")]
+    cursor: CursorStyle,
+    #[serde(default)]
+    keep_on_screen: bool,
+    #[serde(default)]
+    health: Option<Health>,
+}
+
+fn default_solid() -> bool {
+    true
+}
+
+impl SerialiseObject {
+    pub fn builder() -> SerialiseObjectBuilder {
+        SerialiseObjectBuilder {
+            object: SerialiseObject {
+                name: String::new(),
+                sprite: Sprite::Colour(Colour::white()),
+                position: Vec2::new(PROJECTION_WIDTH / 2.0, PROJECTION_HEIGHT / 2.0),
+                size: Size::new(100.0, 100.0),
+                angle: 0.0,
+                origin: None,
+                collision_area: None,
+                flip: Flip::default(),
+                layer: 0,
+                switch: Switch::Off,
+                instructions: Vec::new(),
+                tint: Colour::white(),
+                collision_shape: CollisionShape::Box,
+                solid: default_solid(),
+                cursor: CursorStyle::Default,
+                keep_on_screen: false,
+                health: None,
+            },
+        }
+    }
+}
+
+pub struct SerialiseObjectBuilder {
+    object: SerialiseObject,
+}
+
+impl SerialiseObjectBuilder {
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.object.name = name.into();
+        self
+    }
+
+    pub fn sprite(mut self, sprite: Sprite) -> Self {
+        self.object.sprite = sprite;
+        self
+    }
+
+    pub fn position(mut self, position: Vec2) -> Self {
+        self.object.position = position;
+        self
+    }
+
+    pub fn size(mut self, size: Size) -> Self {
+        self.object.size = size;
+        self
+    }
+
+    pub fn angle(mut self, angle: f32) -> Self {
+        self.object.angle = angle;
+        self
+    }
+
+    pub fn origin(mut self, origin: Vec2) -> Self {
+        self.object.origin = Some(origin);
+        self
+    }
+
+    pub fn collision_area(mut self, collision_area: AABB) -> Self {
+        self.object.collision_area = Some(collision_area);
+        self
+    }
+
+    pub fn collision_shape(mut self, collision_shape: CollisionShape) -> Self {
+        self.object.collision_shape = collision_shape;
+        self
+    }
+
+    pub fn flip(mut self, flip: Flip) -> Self {
+        self.object.flip = flip;
+        self
+    }
+
+    pub fn layer(mut self, layer: u8) -> Self {
+        self.object.layer = layer;
+        self
+    }
+
+    pub fn switch(mut self, switch: Switch) -> Self {
+        self.object.switch = switch;
+        self
+    }
+
+    pub fn tint(mut self, tint: Colour) -> Self {
+        self.object.tint = tint;
+        self
+    }
+
+    pub fn solid(mut self, solid: bool) -> Self {
+        self.object.solid = solid;
+        self
+    }
+
+    pub fn cursor(mut self, cursor: CursorStyle) -> Self {
+        self.object.cursor = cursor;
+        self
+    }
+
+    pub fn keep_on_screen(mut self, keep_on_screen: bool) -> Self {
+        self.object.keep_on_screen = keep_on_screen;
+        self
+    }
+
+    pub fn health(mut self, health: Health) -> Self {
+        self.object.health = Some(health);
+        self
+    }
+
+    pub fn instruction(mut self, instruction: Instruction) -> Self {
+        self.object.instructions.push(instruction);
+        self
+    }
+
+    pub fn build(self) -> SerialiseObject {
+        self.object
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CollisionShape {
+    Box,
+    Circle { radius: f32 },
+}
+
+impl CollisionShape {
+    fn box_shape() -> CollisionShape {
+        CollisionShape::Box
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CursorStyle {
+    Default,
+    Pointer,
+}
+
+impl CursorStyle {
+    fn default_cursor() -> CursorStyle {
+        CursorStyle::Default
+    }
+}
+
+// Health is kept in the object's variables so it can be checked and drawn like any other
+pub const HEALTH_VARIABLE: &str = "health";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Health {
+    pub amount: i32,
+    // The sprite for a stage is used once health drops to its threshold
+    #[serde(default)]
+    pub stages: Vec<(i32, Sprite)>,
+}
+
+impl Health {
+    fn stage_sprite(&self, health: i32) -> Option<&Sprite> {
+        self.stages
+            .iter()
+            .filter(|(threshold, _)| health <= *threshold)
+            .min_by_key(|(threshold, _)| *threshold)
+            .map(|(_, sprite)| sprite)
+    }
+}
+
+pub const MOUSE_HIT_RADIUS: f32 = 1.0;
+
+#[derive(Clone, Debug)]
+pub enum CollisionOutline {
+    Polygon(Vec<Vec2>),
+    Circle { centre: Vec2, radius: f32 },
+}
+
+enum ObjectShape {
+    Poly(c2::Poly),
+    MovedPoly((c2::Poly, c2::Transformation)),
+    Circle(c2::Circle),
+}
+
+impl ObjectShape {
+    fn collides_with_shape<T: BasicShape>(&self, other: &T) -> bool {
+        match self {
+            ObjectShape::Poly(poly) => poly.collides_with(other),
+            ObjectShape::MovedPoly(moved) => moved.collides_with(other),
+            ObjectShape::Circle(circle) => circle.collides_with(other),
+        }
+    }
+
+    fn collides_with(&self, other: &ObjectShape) -> bool {
+        match other {
+            ObjectShape::Poly(poly) => self.collides_with_shape(poly),
+            ObjectShape::MovedPoly(moved) => self.collides_with_shape(moved),
+            ObjectShape::Circle(circle) => self.collides_with_shape(circle),
+        }
+    }
+
+    fn manifold_with_shape<T: BasicShape>(&self, other: &T) -> c2::Manifold {
+        match self {
+            ObjectShape::Poly(poly) => poly.manifold(other),
+            ObjectShape::MovedPoly(moved) => moved.manifold(other),
+            ObjectShape::Circle(circle) => circle.manifold(other),
+        }
+    }
+
+    fn manifold(&self, other: &ObjectShape) -> c2::Manifold {
+        match other {
+            ObjectShape::Poly(poly) => self.manifold_with_shape(poly),
+            ObjectShape::MovedPoly(moved) => self.manifold_with_shape(moved),
+            ObjectShape::Circle(circle) => self.manifold_with_shape(circle),
+        }
+    }
+
+    fn contains_point(&self, point: Vec2) -> bool {
+        let point = c2::Circle::new(c2::Vec2::new(point.x, point.y), MOUSE_HIT_RADIUS);
+        let distance = match self {
+            ObjectShape::Poly(poly) => poly.gjk(&point).use_radius(false).run().distance(),
+            ObjectShape::MovedPoly(moved) => moved.gjk(&point).use_radius(false).run().distance(),
+            ObjectShape::Circle(circle) => circle.gjk(&point).use_radius(false).run().distance(),
+        };
+        distance == 0.0
+    }
+
+    fn moved_by(self, offset: Vec2) -> ObjectShape {
+        match self {
+            ObjectShape::Poly(poly) | ObjectShape::MovedPoly((poly, _)) => {
+                let transformation =
+                    c2::Transformation::new([offset.x, offset.y], c2::Rotation::zero());
+                ObjectShape::MovedPoly((poly, transformation))
+            }
+            ObjectShape::Circle(circle) => {
+                let position = circle.position();
+                ObjectShape::Circle(c2::Circle::new(
+                    c2::Vec2::new(position.x() + offset.x, position.y() + offset.y),
+                    circle.radius(),
+                ))
+            }
+        }
+    }
 }
 
 impl Default for SerialiseObject {
@@ -204,6 +466,12 @@ impl Default for SerialiseObject {
             layer: 0,
             switch: Switch::Off,
             instructions: Vec::new(),
+            tint: Colour::white(),
+            collision_shape: CollisionShape::Box,
+            solid: default_solid(),
+            cursor: CursorStyle::Default,
+            keep_on_screen: false,
+            health: None,
         }
     }
 }
@@ -213,18 +481,31 @@ impl SerialiseObject {
         fn replace_text_in_action(action: &mut Action, text_replacements: &[(&str, String)]) {
             if let Action::DrawText { text, .. } = action {
                 for (before, after) in text_replacements {
-                    *text = text.replace(before, &after);
+                    *text = text.replace(before, after);
                 }
             } else if let Action::Random { random_actions } = action {
                 for action in random_actions {
                     replace_text_in_action(action, text_replacements);
                 }
+            } else if let Action::If {
+                then, otherwise, ..
+            } = action
+            {
+                for action in then.iter_mut().chain(otherwise.iter_mut()) {
+                    replace_text_in_action(action, text_replacements);
+                }
+            } else if let Action::Sequence { steps } = action {
+                for (_, action) in steps {
+                    replace_text_in_action(action, text_replacements);
+                }
+            } else if let Action::Repeat { action, .. } = action {
+                replace_text_in_action(action, text_replacements);
             }
         }
 
         for instruction in self.instructions.iter_mut() {
             for action in instruction.actions.iter_mut() {
-                replace_text_in_action(action, &text_replacements);
+                replace_text_in_action(action, text_replacements);
             }
         }
     }
@@ -242,6 +523,11 @@ impl SerialiseObject {
             angle: self.angle,
             origin: self.origin,
             collision_area: self.collision_area,
+            collision_shape: self.collision_shape,
+            solid: self.solid,
+            cursor: self.cursor,
+            keep_on_screen: self.keep_on_screen,
+            health: self.health,
             flip: self.flip,
             layer: self.layer,
             switch,
@@ -250,14 +536,65 @@ impl SerialiseObject {
             active_motion: ActiveMotion::Stop,
             animation: AnimationStatus::None,
             timer: None,
+            alpha: 1.0,
+            tint: self.tint,
+            queued_actions: Vec::new(),
+            poly_cache: PolyCache::default(),
+            angular_velocity: 0.0,
+            angle_set: false,
+            blink: None,
+            variables: HashMap::new(),
+            last_fired: HashMap::new(),
+            was_out_of_health: false,
         };
+        if let Some(health) = &object.health {
+            object
+                .variables
+                .insert(HEALTH_VARIABLE.to_string(), health.amount);
+        }
+        fn choose_random_time(trigger: &mut Trigger) {
+            if let Trigger::Time(When::Random { start, end }) = trigger {
+                *trigger = Trigger::Time(When::Exact {
+                    time: rand::gen_range(*start, *end + 1),
+                });
+            } else if let Trigger::AnyOf { triggers } = trigger {
+                for trigger in triggers.iter_mut() {
+                    choose_random_time(trigger);
+                }
+            } else if let Trigger::Not { trigger } = trigger {
+                choose_random_time(trigger);
+            }
+        }
+        fn choose_random_time_in_action(action: &mut Action) {
+            if let Action::If {
+                check,
+                then,
+                otherwise,
+            } = action
+            {
+                choose_random_time(check);
+                for action in then.iter_mut().chain(otherwise.iter_mut()) {
+                    choose_random_time_in_action(action);
+                }
+            } else if let Action::Random { random_actions } = action {
+                for action in random_actions {
+                    choose_random_time_in_action(action);
+                }
+            } else if let Action::Sequence { steps } = action {
+                for (_, action) in steps {
+                    choose_random_time_in_action(action);
+                }
+            } else if let Action::Repeat { action, .. } = action {
+                choose_random_time_in_action(action);
+            }
+        }
+
         for instruction in object.instructions.iter_mut() {
             for trigger in instruction.triggers.iter_mut() {
-                if let Trigger::Time(When::Random { start, end }) = trigger {
-                    *trigger = Trigger::Time(When::Exact {
-                        time: rand::gen_range(*start, *end + 1),
-                    });
-                }
+                choose_random_time(trigger);
+            }
+            for action in instruction.actions.iter_mut() {
+                choose_random_time_in_action(action);
             }
         }
 
@@ -267,50 +604,134 @@ impl SerialiseObject {
 
 trait SerialiseObjectList {
     fn get_obj(&self, name: &str) -> WeeResult<&SerialiseObject>;
-
-    fn sprites(&self) -> HashMap<&str, &Sprite>;
 }
 
 impl SerialiseObjectList for Vec<SerialiseObject> {
     fn get_obj(&self, name: &str) -> WeeResult<&SerialiseObject> {
         let index = self.iter().position(|o| o.name == name);
         index
-            .map(|index| self.get(index))
-            .flatten()
+            .and_then(|index| self.get(index))
             .ok_or_else(|| format!("Couldn't find object with name {}", name).into())
     }
-
-    fn sprites(&self) -> HashMap<&str, &Sprite> {
-        let mut sprites = HashMap::new();
-        for obj in self {
-            sprites.insert(&*obj.name, &obj.sprite);
-        }
-        sprites
-    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SerialiseMusic {
     pub filename: String,
     pub looped: bool,
+    #[serde(default = "default_volume")]
+    pub volume: f32,
+}
+
+// Older games have a single music track rather than a list of layers
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SerialiseMusicTracks {
+    Single(SerialiseMusic),
+    Layered(Vec<SerialiseMusic>),
+}
+
+fn deserialize_music_tracks<'de, D>(deserializer: D) -> Result<Vec<SerialiseMusic>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let tracks = match Option::<SerialiseMusicTracks>::deserialize(deserializer)? {
+        Some(SerialiseMusicTracks::Single(music)) => vec![music],
+        Some(SerialiseMusicTracks::Layered(tracks)) => tracks,
+        None => Vec::new(),
+    };
+    Ok(tracks)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(from = "SerialiseSound")]
+pub struct SoundLoadInfo {
+    pub filename: String,
+    pub volume: f32,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SerialiseSound {
+    Filename(String),
+    LoadInfo {
+        filename: String,
+        #[serde(default = "default_volume")]
+        volume: f32,
+    },
+}
+
+fn default_volume() -> f32 {
+    1.0
+}
+
+impl From<SerialiseSound> for SoundLoadInfo {
+    fn from(sound: SerialiseSound) -> SoundLoadInfo {
+        match sound {
+            SerialiseSound::Filename(filename) => SoundLoadInfo {
+                filename,
+                volume: default_volume(),
+            },
+            SerialiseSound::LoadInfo { filename, volume } => SoundLoadInfo { filename, volume },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct AssetFiles {
     pub images: HashMap<String, String>,
-    pub audio: HashMap<String, String>,
-    pub music: Option<SerialiseMusic>,
+    pub audio: HashMap<String, SoundLoadInfo>,
+    #[serde(default, deserialize_with = "deserialize_music_tracks")]
+    pub music: Vec<SerialiseMusic>,
     pub fonts: HashMap<String, FontLoadInfo>,
+    #[serde(default)]
+    pub sequences: HashMap<String, ImageSequence>,
 }
 
-impl Default for AssetFiles {
-    fn default() -> AssetFiles {
-        AssetFiles {
-            images: HashMap::new(),
-            audio: HashMap::new(),
-            music: None,
-            fonts: HashMap::new(),
+impl AssetFiles {
+    // Single images along with every frame of each sequence
+    pub fn image_files(&self) -> HashMap<String, String> {
+        let mut files = self.images.clone();
+        for (name, sequence) in &self.sequences {
+            files.extend(sequence.frame_files(name));
         }
+        files
+    }
+
+    fn has_image(&self, name: &str) -> bool {
+        self.images.contains_key(name)
+            || self.sequences.iter().any(|(sequence_name, sequence)| {
+                (0..sequence.count)
+                    .any(|index| ImageSequence::frame_name(sequence_name, index) == name)
+            })
+    }
+}
+
+// Frames exported as numbered images. With the pattern "walk_{}.png" and a count of 3,
+// walk_0.png, walk_1.png and walk_2.png are loaded as the images walk#0, walk#1 and walk#2
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImageSequence {
+    pub pattern: String,
+    pub count: u32,
+    #[serde(default)]
+    pub first: u32,
+}
+
+impl ImageSequence {
+    pub fn frame_name(name: &str, index: u32) -> String {
+        format!("{}#{}", name, index)
+    }
+
+    fn frame_files(&self, name: &str) -> Vec<(String, String)> {
+        (0..self.count)
+            .map(|index| {
+                let number = (self.first + index).to_string();
+                (
+                    ImageSequence::frame_name(name, index),
+                    self.pattern.replace("{}", &number),
+                )
+            })
+            .collect()
     }
 }
 
@@ -349,7 +770,7 @@ pub struct GameData {
 impl Default for GameData {
     fn default() -> GameData {
         GameData {
-            format_version: "0.2".to_string(),
+            format_version: CURRENT_FORMAT_VERSION.to_string(),
             published: false,
             game_type: GameType::Minigame,
             objects: Vec::new(),
@@ -362,22 +783,81 @@ impl Default for GameData {
     }
 }
 
+impl GameData {
+    pub fn builder() -> GameDataBuilder {
+        GameDataBuilder {
+            game_data: GameData::default(),
+        }
+    }
+}
+
+pub struct GameDataBuilder {
+    game_data: GameData,
+}
+
+impl GameDataBuilder {
+    pub fn object(mut self, object: SerialiseObject) -> Self {
+        self.game_data.objects.push(object);
+        self
+    }
+
+    pub fn background(mut self, part: BackgroundPart) -> Self {
+        self.game_data.background.push(part);
+        self
+    }
+
+    pub fn length(mut self, length: Length) -> Self {
+        self.game_data.length = length;
+        self
+    }
+
+    pub fn game_type(mut self, game_type: GameType) -> Self {
+        self.game_data.game_type = game_type;
+        self
+    }
+
+    pub fn published(mut self, published: bool) -> Self {
+        self.game_data.published = published;
+        self
+    }
+
+    pub fn asset_files(mut self, asset_files: AssetFiles) -> Self {
+        self.game_data.asset_files = asset_files;
+        self
+    }
+
+    pub fn intro_text(mut self, intro_text: impl Into<String>) -> Self {
+        self.game_data.intro_text = Some(intro_text.into());
+        self
+    }
+
+    pub fn attribution(mut self, attribution: impl Into<String>) -> Self {
+        self.game_data.attribution = attribution.into();
+        self
+    }
+
+    pub fn build(self) -> GameData {
+        self.game_data
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum When {
+pub enum When {
     Start,
     End,
     Exact { time: u32 },
     Random { start: u32, end: u32 },
+    Fraction { elapsed: f32 },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum CollisionWith {
+pub enum CollisionWith {
     Object { name: String },
     Area(AABB),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum MouseOver {
+pub enum MouseOver {
     Object { name: String },
     Area(AABB),
     Anywhere,
@@ -393,17 +873,105 @@ pub enum ButtonState {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum MouseInteraction {
+pub enum MouseInteraction {
     Button { state: ButtonState },
     Hover,
 }
 
+macro_rules! keys {
+    ($($key:ident),*) => {
+        #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+        #[serde(from = "String", into = "String")]
+        pub enum Key {
+            $($key,)*
+            Unknown,
+        }
+
+        impl Key {
+            pub fn all() -> Vec<Key> {
+                vec![$(Key::$key),*]
+            }
+
+            pub fn to_key_code(self) -> KeyCode {
+                match self {
+                    $(Key::$key => KeyCode::$key,)*
+                    Key::Unknown => KeyCode::Unknown,
+                }
+            }
+        }
+
+        impl From<String> for Key {
+            fn from(name: String) -> Key {
+                match name.as_str() {
+                    $(stringify!($key) => Key::$key,)*
+                    _ => Key::Unknown,
+                }
+            }
+        }
+
+        impl From<Key> for String {
+            fn from(key: Key) -> String {
+                match key {
+                    $(Key::$key => stringify!($key).to_string(),)*
+                    Key::Unknown => "Unknown".to_string(),
+                }
+            }
+        }
+    };
+}
+
+keys! {
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    Space, Enter, Escape, Tab, Backspace, Up, Down, Left, Right,
+    LeftShift, RightShift, LeftControl, RightControl
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum Input {
+pub enum Input {
     Mouse {
         over: MouseOver,
         interaction: MouseInteraction,
     },
+    Keyboard {
+        key: Key,
+        state: ButtonState,
+    },
+    Gamepad {
+        button: GamepadButton,
+        state: ButtonState,
+    },
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum GamepadButton {
+    A,
+    B,
+    X,
+    Y,
+    Start,
+    Select,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl GamepadButton {
+    pub fn all() -> Vec<GamepadButton> {
+        vec![
+            GamepadButton::A,
+            GamepadButton::B,
+            GamepadButton::X,
+            GamepadButton::Y,
+            GamepadButton::Start,
+            GamepadButton::Select,
+            GamepadButton::Up,
+            GamepadButton::Down,
+            GamepadButton::Left,
+            GamepadButton::Right,
+        ]
+    }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
@@ -425,39 +993,128 @@ pub enum SwitchState {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum PropertyCheck {
+pub enum PropertyCheck {
     Switch(SwitchState),
     Sprite(Sprite),
     FinishedAnimation,
+    AnimationFrame(usize),
+    AngleBetween { min: f32, max: f32 },
     Timer,
+    TimerBelow(u32),
+    TimerEquals(u32),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum Trigger {
+pub enum Trigger {
     Time(When),
     Collision(CollisionWith),
     Input(Input),
     WinStatus(WinStatus),
-    Random { chance: f32 },
-    CheckProperty { name: String, check: PropertyCheck },
-    DifficultyLevel { level: u32 },
+    Random {
+        chance: f32,
+    },
+    CheckProperty {
+        name: String,
+        check: PropertyCheck,
+    },
+    DifficultyLevel {
+        level: u32,
+    },
+    Proximity {
+        name: String,
+        distance: f32,
+    },
+    AnyOf {
+        triggers: Vec<Trigger>,
+    },
+    Not {
+        trigger: Box<Trigger>,
+    },
+    AllMatch {
+        pattern: String,
+        check: PropertyCheck,
+    },
+    SoundFinished {
+        name: String,
+    },
+    Speed {
+        comparison: Comparison,
+        value: f32,
+    },
+    OffScreen,
+    OnScreen,
+    ScoreAtLeast {
+        score: i32,
+    },
+    Lives {
+        comparison: Comparison,
+        value: i32,
+    },
+    Swipe {
+        direction: CompassDirection,
+        min_distance: f32,
+        #[serde(default = "default_swipe_tolerance")]
+        tolerance: f32,
+    },
+    CheckVariable {
+        object: String,
+        name: String,
+        comparison: Comparison,
+        value: i32,
+    },
+    // The object's health has run out
+    Destroyed,
+}
+
+fn default_swipe_tolerance() -> f32 {
+    30.0
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Comparison {
+    Greater,
+    Less,
+    Equal,
+}
+
+impl Comparison {
+    fn compare(self, a: f32, b: f32) -> bool {
+        const EPSILON: f32 = 0.001;
+        match self {
+            Comparison::Greater => a > b,
+            Comparison::Less => a < b,
+            Comparison::Equal => (a - b).abs() < EPSILON,
+        }
+    }
+}
+
+const MAX_TRIGGER_DEPTH: u32 = 16;
+
+// Handles ranges that wrap around through 0, e.g. from 340 to 10
+fn is_between_angles(angle: f32, min: f32, max: f32) -> bool {
+    if min < max {
+        angle >= min && angle <= max
+    } else {
+        angle >= min && angle <= (max + 360.0) || angle >= (min - 360.0) && angle <= max
+    }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Effect {
     Freeze,
     None,
+    SlowMotion { factor: f32 },
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
-enum Angle {
+pub enum Angle {
     Current,
     Degrees(f32),
     Random { min: f32, max: f32 },
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
-enum CompassDirection {
+pub enum CompassDirection {
     Up,
     UpRight,
     Right,
@@ -537,7 +1194,7 @@ fn vector_from_angle(angle: f32, speed: Speed) -> Vec2 {
     Vec2::new(speed * angle.cos(), speed * angle.sin())
 }
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum MovementDirection {
+pub enum MovementDirection {
     Angle(Angle),
     Direction {
         possible_directions: HashSet<CompassDirection>,
@@ -554,7 +1211,7 @@ impl MovementDirection {
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
-enum Speed {
+pub enum Speed {
     VerySlow,
     Slow,
     Normal,
@@ -599,29 +1256,158 @@ fn clamp_position(position: &mut Vec2, area: AABB) {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum RelativeTo {
-    CurrentPosition,
+#[serde(untagged)]
+pub enum RoamArea {
+    Rectangle(AABB),
+    Polygon(Vec<Vec2>),
+}
+
+impl RoamArea {
+    fn bounds(&self) -> AABB {
+        match self {
+            RoamArea::Rectangle(area) => *area,
+            RoamArea::Polygon(points) => {
+                let mut bounds = AABB {
+                    min: Vec2::new(f32::INFINITY, f32::INFINITY),
+                    max: Vec2::new(f32::NEG_INFINITY, f32::NEG_INFINITY),
+                };
+                for point in points {
+                    bounds.min.x = bounds.min.x.min(point.x);
+                    bounds.min.y = bounds.min.y.min(point.y);
+                    bounds.max.x = bounds.max.x.max(point.x);
+                    bounds.max.y = bounds.max.y.max(point.y);
+                }
+                bounds
+            }
+        }
+    }
+}
+
+// Even-odd rule so concave polygons work too
+fn is_point_in_polygon(point: Vec2, polygon: &[Vec2]) -> bool {
+    let mut inside = false;
+    for (i, a) in polygon.iter().enumerate() {
+        let b = polygon[(i + 1) % polygon.len()];
+        if (a.y > point.y) != (b.y > point.y) {
+            let crossing_x = a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if point.x < crossing_x {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+// The unit normal of the first polygon edge crossed moving from start to end
+fn crossed_edge_normal(start: Vec2, end: Vec2, polygon: &[Vec2]) -> Option<Vec2> {
+    let cross = |a: Vec2, b: Vec2| a.x * b.y - a.y * b.x;
+    let movement = end - start;
+    let mut closest: Option<(f32, Vec2)> = None;
+    for (i, a) in polygon.iter().enumerate() {
+        let edge = polygon[(i + 1) % polygon.len()] - *a;
+        let denominator = cross(movement, edge);
+        if denominator == 0.0 {
+            continue;
+        }
+        let t = cross(*a - start, edge) / denominator;
+        let u = cross(*a - start, movement) / denominator;
+        if (0.0..=1.0).contains(&t)
+            && (0.0..=1.0).contains(&u)
+            && closest.map(|(closest_t, _)| t < closest_t).unwrap_or(true)
+        {
+            closest = Some((t, Vec2::new(-edge.y, edge.x).unit()));
+        }
+    }
+    closest.map(|(_, normal)| normal)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum RelativeTo {
+    CurrentPosition,
     CurrentAngle,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum JumpLocation {
+pub enum JumpLocation {
     Point(Vec2),
     Area(AABB),
-    Relative { to: RelativeTo, distance: Vec2 },
-    Object { name: String },
+    Relative {
+        to: RelativeTo,
+        distance: Vec2,
+    },
+    Object {
+        name: String,
+    },
+    ObjectAnchor {
+        name: String,
+        anchor: Anchor,
+        #[serde(default = "Vec2::zero")]
+        offset: Vec2,
+    },
     Mouse,
-    ClampPosition { area: AABB },
+    ClampPosition {
+        area: AABB,
+    },
+    Grid {
+        origin: Vec2,
+        cell: Size,
+    },
+    SnapToNearest {
+        points: Vec<Vec2>,
+    },
+    RandomInAreaAvoiding {
+        area: AABB,
+        avoid: Vec<String>,
+        min_gap: f32,
+    },
+}
+
+// Which edge or corner of the other object to sit against
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Anchor {
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum BounceDirection {
+pub enum BounceDirection {
     Left,
     Right,
 }
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Falloff {
+    Constant,
+    Linear,
+    InverseSquare,
+}
+
+impl Falloff {
+    fn pull(self, strength: f32, distance: f32) -> f32 {
+        // Don't let the pull blow up when the object reaches the target
+        let distance = distance.max(1.0);
+        match self {
+            Falloff::Constant => strength,
+            Falloff::Linear => strength / distance,
+            Falloff::InverseSquare => strength / (distance * distance),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum MovementType {
+pub enum MovementType {
     Wiggle,
     Insect,
     Reflect {
@@ -634,25 +1420,53 @@ enum MovementType {
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
-enum MovementHandling {
+pub enum MovementHandling {
     Anywhere,
     TryNotToOverlap,
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
-enum Target {
+pub enum Target {
     Object { name: String },
     Mouse,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
-enum TargetType {
+pub enum TargetType {
     Follow,
     StopWhenReached,
 }
 
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    fn linear() -> Easing {
+        Easing::Linear
+    }
+
+    // How far to move this frame given the distance travelled so far and the distance left
+    fn step(self, speed: f32, travelled: f32, remaining: f32) -> f32 {
+        const EASE_RATE: f32 = 0.1;
+        const MIN_STEP: f32 = 0.5;
+        let ease_out = (remaining * EASE_RATE).max(MIN_STEP);
+        match self {
+            Easing::Linear => speed,
+            Easing::EaseOut => ease_out.min(speed),
+            Easing::EaseInOut => {
+                let ease_in = travelled * EASE_RATE + MIN_STEP;
+                ease_in.min(ease_out).min(speed)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum Acceleration {
+pub enum Acceleration {
     Continuous {
         direction: MovementDirection,
         speed: Speed,
@@ -663,7 +1477,7 @@ enum Acceleration {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum Motion {
+pub enum Motion {
     GoStraight {
         direction: MovementDirection,
         speed: Speed,
@@ -671,7 +1485,7 @@ enum Motion {
     JumpTo(JumpLocation),
     Roam {
         movement_type: MovementType,
-        area: AABB,
+        area: RoamArea,
         speed: Speed,
     },
     Swap {
@@ -682,17 +1496,48 @@ enum Motion {
         target_type: TargetType,
         offset: Vec2,
         speed: Speed,
+        #[serde(default = "Easing::linear")]
+        easing: Easing,
     },
     Accelerate(Acceleration),
+    Orbit {
+        center: Target,
+        radius: f32,
+        speed: Speed,
+        clockwise: bool,
+    },
+    ApplyGravity {
+        strength: f32,
+        terminal_velocity: Option<f32>,
+    },
+    FollowPath {
+        points: Vec<Vec2>,
+        speed: Speed,
+        loop_path: bool,
+    },
+    Wave {
+        axis: Axis,
+        amplitude: f32,
+        period_frames: u32,
+        speed: Speed,
+    },
+    Attract {
+        target: Target,
+        strength: f32,
+        falloff: Falloff,
+    },
+    SetVelocity(Vec2),
     Stop,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
-enum AnimationType {
+pub enum AnimationType {
     Loop,
     PlayOnce,
+    PingPong,
 }
 
+// Toggling a ping-pong animation keeps it repeating, just without bouncing back
 impl Not for AnimationType {
     type Output = AnimationType;
 
@@ -700,12 +1545,13 @@ impl Not for AnimationType {
         match self {
             AnimationType::Loop => AnimationType::PlayOnce,
             AnimationType::PlayOnce => AnimationType::Loop,
+            AnimationType::PingPong => AnimationType::Loop,
         }
     }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum AngleSetter {
+pub enum AngleSetter {
     Value(f32),
     Increase(f32),
     Decrease(f32),
@@ -713,16 +1559,17 @@ enum AngleSetter {
     Clamp { min: f32, max: f32 },
     RotateToObject { name: String },
     RotateToMouse,
+    RotateToVelocity,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum SizeDifference {
+pub enum SizeDifference {
     Value(Size),
     Percent(Size),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum SizeSetter {
+pub enum SizeSetter {
     Value(Size),
     Grow(SizeDifference),
     Shrink(SizeDifference),
@@ -747,32 +1594,58 @@ impl Not for Switch {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum FlipSetter {
+pub enum FlipSetter {
     Flip,
     SetFlip(bool),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum LayerSetter {
+pub enum LayerSetter {
     Value(u8),
     Increase,
     Decrease,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum PropertySetter {
+pub enum PropertySetter {
     Sprite(Sprite),
     Angle(AngleSetter),
     Size(SizeSetter),
     Switch(Switch),
-    Timer { time: u32 },
+    Timer {
+        time: u32,
+    },
     FlipHorizontal(FlipSetter),
     FlipVertical(FlipSetter),
     Layer(LayerSetter),
+    Opacity(f32),
+    Tint(Colour),
+    AngularVelocity(f32),
+    Variable {
+        name: String,
+        op: VarOp,
+    },
+    CopySize {
+        name: String,
+    },
+    CopySprite {
+        name: String,
+    },
+    CopyPosition {
+        name: String,
+        #[serde(default = "Vec2::zero")]
+        offset: Vec2,
+    },
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+pub enum VarOp {
+    Set(i32),
+    Add(i32),
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
-enum TextResize {
+pub enum TextResize {
     MatchText,
     MatchObject,
 }
@@ -795,13 +1668,26 @@ pub enum JustifyText {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-enum Action {
+pub enum Action {
     Win,
     Lose,
+    WinAfter {
+        frames: u32,
+    },
+    LoseAfter {
+        frames: u32,
+    },
     Effect(Effect),
     Motion(Motion),
+    SetBackground {
+        parts: Vec<BackgroundPart>,
+    },
+    SetBackgroundColour(Colour),
     PlaySound {
         name: String,
+        // Pans with the object's x position, see PlayedSound::pan
+        #[serde(default)]
+        attenuate: bool,
     },
     StopMusic,
     SetProperty(PropertySetter),
@@ -809,6 +1695,19 @@ enum Action {
         animation_type: AnimationType,
         sprites: Vec<Sprite>,
         speed: Speed,
+        #[serde(default)]
+        reverse: bool,
+    },
+    AnimateTimed {
+        frames: Vec<(Sprite, u32)>,
+        animation_type: AnimationType,
+    },
+    AnimateSequence {
+        name: String,
+        animation_type: AnimationType,
+        speed: Speed,
+        #[serde(default)]
+        reverse: bool,
     },
     DrawText {
         text: String,
@@ -816,17 +1715,58 @@ enum Action {
         colour: Colour,
         resize: TextResize,
         justify: JustifyText,
+        #[serde(default)]
+        max_width: Option<f32>,
     },
     Random {
         random_actions: Vec<Action>,
     },
     EndEarly,
+    Spawn {
+        template: String,
+        position: JumpLocation,
+        name_prefix: String,
+    },
+    Destroy,
+    DestroyNamed {
+        name: String,
+    },
+    ScreenShake {
+        intensity: f32,
+        frames: u32,
+    },
+    If {
+        check: Trigger,
+        then: Vec<Action>,
+        otherwise: Vec<Action>,
+    },
+    Sequence {
+        steps: Vec<(u32, Action)>,
+    },
+    Repeat {
+        count: u32,
+        every: u32,
+        action: Box<Action>,
+    },
+    Blink {
+        frames: u32,
+        interval: u32,
+    },
+    AdjustLives(i32),
+    AdjustScore(i32),
+    SwapSprite {
+        name: String,
+    },
+    Damage(i32),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-struct Instruction {
-    triggers: Vec<Trigger>,
-    actions: Vec<Action>,
+pub struct Instruction {
+    pub triggers: Vec<Trigger>,
+    pub actions: Vec<Action>,
+    // Frames after firing before the instruction can fire again
+    #[serde(default)]
+    pub cooldown: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -838,6 +1778,7 @@ pub struct FontLoadInfo {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum Sprite {
     Image { name: String },
+    ImageRegion { name: String, source: AABB },
     Colour(Colour),
 }
 
@@ -849,13 +1790,45 @@ pub struct BackgroundPart {
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 struct Animation {
-    should_loop: bool,
+    animation_type: AnimationType,
     index: usize,
+    backwards: bool,
     sprites: Vec<Sprite>,
-    speed: Speed,
+    // How long to wait before moving on from each sprite
+    durations: Vec<u32>,
     time_to_next_change: u32,
 }
 
+impl Animation {
+    // None once a PlayOnce animation has shown its last sprite
+    fn next_index(&mut self) -> Option<usize> {
+        let last = self.sprites.len() - 1;
+        let is_at_end = if self.backwards {
+            self.index == 0
+        } else {
+            self.index == last
+        };
+        if !is_at_end {
+            return Some(if self.backwards {
+                self.index - 1
+            } else {
+                self.index + 1
+            });
+        }
+        match self.animation_type {
+            AnimationType::Loop => Some(if self.backwards { last } else { 0 }),
+            AnimationType::PlayOnce => None,
+            AnimationType::PingPong => {
+                if last == 0 {
+                    return Some(0);
+                }
+                self.backwards = !self.backwards;
+                Some(if self.backwards { last - 1 } else { 1 })
+            }
+        }
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize, Debug)]
 enum AnimationStatus {
     Animating(Animation),
@@ -864,37 +1837,60 @@ enum AnimationStatus {
 }
 
 impl AnimationStatus {
-    fn start(animation_type: AnimationType, sprites: &[Sprite], speed: Speed) -> AnimationStatus {
-        let should_loop = match animation_type {
-            AnimationType::Loop => true,
-            AnimationType::PlayOnce => false,
+    fn start(
+        animation_type: AnimationType,
+        sprites: Vec<Sprite>,
+        durations: Vec<u32>,
+        reverse: bool,
+    ) -> AnimationStatus {
+        let index = if reverse {
+            sprites.len().saturating_sub(1)
+        } else {
+            0
         };
+        let time_to_next_change = durations.get(index).copied().unwrap_or(0);
         AnimationStatus::Animating(Animation {
-            should_loop,
-            sprites: sprites.to_vec(),
-            index: 0,
-            speed,
-            time_to_next_change: speed.to_animation_time(),
+            animation_type,
+            sprites,
+            index,
+            backwards: reverse,
+            durations,
+            time_to_next_change,
         })
     }
 
+    fn current_sprite(&self) -> Option<Sprite> {
+        match self {
+            AnimationStatus::Animating(animation) => {
+                animation.sprites.get(animation.index).cloned()
+            }
+            _ => None,
+        }
+    }
+
+    // Only true on the first frame a sprite is shown so looping animations
+    // fire each time round rather than for as long as the sprite is showing
+    fn has_reached(&self, index: usize) -> bool {
+        match self {
+            AnimationStatus::Animating(animation) => {
+                animation.index == index
+                    && Some(&animation.time_to_next_change) == animation.durations.get(index)
+            }
+            _ => false,
+        }
+    }
+
     fn update(&mut self) -> Option<Sprite> {
         match self {
             AnimationStatus::Animating(animation) => {
                 if animation.time_to_next_change == 0 {
                     if animation.sprites.is_empty() {
-                    } else if animation.index == animation.sprites.len() - 1 {
-                        if animation.should_loop {
-                            animation.index = 0;
-                            animation.time_to_next_change = animation.speed.to_animation_time();
-                            return Some(animation.sprites[0].clone());
-                        } else {
-                            *self = AnimationStatus::Finished;
-                        }
+                    } else if let Some(index) = animation.next_index() {
+                        animation.index = index;
+                        animation.time_to_next_change = animation.durations[index];
+                        return Some(animation.sprites[index].clone());
                     } else {
-                        animation.index += 1;
-                        animation.time_to_next_change = animation.speed.to_animation_time();
-                        return Some(animation.sprites[animation.index].clone());
+                        *self = AnimationStatus::Finished;
                     }
                 } else {
                     animation.time_to_next_change -= 1;
@@ -917,7 +1913,7 @@ enum ActiveMotion {
     },
     Roam {
         movement_type: ActiveRoam,
-        area: AABB,
+        area: RoamArea,
         speed: Speed,
     },
     Target {
@@ -925,6 +1921,8 @@ enum ActiveMotion {
         target_type: TargetType,
         offset: Vec2,
         speed: Speed,
+        easing: Easing,
+        travelled: f32,
     },
     Accelerate {
         velocity: Vec2,
@@ -934,6 +1932,37 @@ enum ActiveMotion {
         velocity: Vec2,
         deceleration: Vec2,
     },
+    Orbit {
+        center: Target,
+        radius: f32,
+        speed: Speed,
+        clockwise: bool,
+        angle: f32,
+    },
+    Gravity {
+        velocity: Vec2,
+        strength: f32,
+        terminal_velocity: Option<f32>,
+    },
+    FollowPath {
+        points: Vec<Vec2>,
+        speed: Speed,
+        loop_path: bool,
+        index: usize,
+    },
+    Wave {
+        axis: Axis,
+        amplitude: f32,
+        period_frames: u32,
+        speed: Speed,
+        frame: u32,
+    },
+    Attract {
+        target: Target,
+        strength: f32,
+        falloff: Falloff,
+        velocity: Vec2,
+    },
     Stop,
 }
 #[derive(Clone, Debug)]
@@ -954,6 +1983,66 @@ enum ActiveRoam {
     },
 }
 
+impl ActiveRoam {
+    fn reflect(&mut self, normal: Vec2) {
+        let reflect = |velocity: &mut Vec2| {
+            let dot = velocity.x * normal.x + velocity.y * normal.y;
+            *velocity -= normal * (2.0 * dot);
+        };
+        match self {
+            ActiveRoam::Wiggle => {}
+            ActiveRoam::Insect { velocity } => reflect(velocity),
+            ActiveRoam::Reflect { velocity, .. } => reflect(velocity),
+            ActiveRoam::Bounce {
+                velocity,
+                direction,
+                ..
+            } => {
+                reflect(velocity);
+                *direction = if velocity.x < 0.0 {
+                    BounceDirection::Left
+                } else {
+                    BounceDirection::Right
+                };
+            }
+        }
+    }
+}
+
+impl ActiveMotion {
+    fn velocity(&self) -> Vec2 {
+        match self {
+            ActiveMotion::Accelerate { velocity, .. } => *velocity,
+            ActiveMotion::GoStraight { velocity } => *velocity,
+            ActiveMotion::Roam { movement_type, .. } => match movement_type {
+                ActiveRoam::Insect { velocity } => *velocity,
+                ActiveRoam::Bounce { velocity, .. } => *velocity,
+                ActiveRoam::Reflect { velocity, .. } => *velocity,
+                _ => Vec2::zero(),
+            },
+            ActiveMotion::Target { .. } => Vec2::zero(),
+            ActiveMotion::SlowDown { velocity, .. } => *velocity,
+            ActiveMotion::Orbit { .. } => Vec2::zero(),
+            ActiveMotion::Gravity { velocity, .. } => *velocity,
+            ActiveMotion::FollowPath { .. } => Vec2::zero(),
+            ActiveMotion::Wave { axis, speed, .. } => match axis {
+                Axis::Horizontal => Vec2::new(speed.as_value(), 0.0),
+                Axis::Vertical => Vec2::new(0.0, speed.as_value()),
+            },
+            ActiveMotion::Attract { velocity, .. } => *velocity,
+            ActiveMotion::Stop => Vec2::zero(),
+        }
+    }
+}
+
+fn wave_offset(amplitude: f32, period_frames: u32, frame: u32) -> f32 {
+    if period_frames == 0 {
+        return 0.0;
+    }
+    let phase = (frame % period_frames) as f32 / period_frames as f32;
+    amplitude * (2.0 * std::f32::consts::PI * phase).sin()
+}
+
 #[derive(Clone, Debug)]
 pub struct Object {
     pub sprite: Sprite,
@@ -962,6 +2051,11 @@ pub struct Object {
     pub angle: f32,
     origin: Option<Vec2>,
     collision_area: Option<AABB>,
+    collision_shape: CollisionShape,
+    pub solid: bool,
+    pub cursor: CursorStyle,
+    pub keep_on_screen: bool,
+    health: Option<Health>,
     pub flip: Flip,
     pub layer: u8,
     instructions: Vec<Instruction>,
@@ -970,6 +2064,42 @@ pub struct Object {
     pub switch: SwitchState,
     pub timer: Option<u32>,
     animation: AnimationStatus,
+    pub alpha: f32,
+    pub tint: Colour,
+    queued_actions: Vec<QueuedAction>,
+    poly_cache: PolyCache,
+    angular_velocity: f32,
+    angle_set: bool,
+    blink: Option<Blink>,
+    pub variables: HashMap<String, i32>,
+    // The frame each instruction with a cooldown last fired on
+    last_fired: HashMap<usize, u32>,
+    // Whether health had run out at the start of the object's last update
+    was_out_of_health: bool,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Blink {
+    frames_left: u32,
+    interval: u32,
+    elapsed: u32,
+}
+
+// Collision polys get requested many times a frame so keep the last one around
+// along with the values it was built from
+#[derive(Clone, Default)]
+struct PolyCache(RefCell<Option<(AABB, Vec2, f32, c2::Poly)>>);
+
+impl fmt::Debug for PolyCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PolyCache")
+    }
+}
+
+#[derive(Clone, Debug)]
+struct QueuedAction {
+    frames_left: u32,
+    action: Action,
 }
 
 impl Object {
@@ -1008,6 +2138,124 @@ impl Object {
         )
     }
 
+    pub fn health(&self) -> Option<i32> {
+        self.health
+            .as_ref()
+            .map(|_| self.variables.get(HEALTH_VARIABLE).copied().unwrap_or(0))
+    }
+
+    fn damage(&mut self, amount: i32) -> bool {
+        if let Some(health) = &self.health {
+            let value = self
+                .variables
+                .entry(HEALTH_VARIABLE.to_string())
+                .or_insert(0);
+            *value = value.saturating_sub(amount).max(0);
+            if let Some(sprite) = health.stage_sprite(*value) {
+                self.sprite = sprite.clone();
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    // Fills in {timer} (whole seconds left) and {var:name} from the object's current
+    // state. Doubled braces give literal braces and unknown placeholders are kept as is
+    pub fn format_text(&self, text: &str) -> String {
+        let mut formatted = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    formatted.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    formatted.push('}');
+                }
+                '{' => {
+                    let mut placeholder = String::new();
+                    let mut is_closed = false;
+                    for c in chars.by_ref() {
+                        if c == '}' {
+                            is_closed = true;
+                            break;
+                        }
+                        placeholder.push(c);
+                    }
+                    match self.placeholder_value(&placeholder) {
+                        Some(value) if is_closed => formatted.push_str(&value),
+                        _ => {
+                            formatted.push('{');
+                            formatted.push_str(&placeholder);
+                            if is_closed {
+                                formatted.push('}');
+                            }
+                        }
+                    }
+                }
+                _ => formatted.push(c),
+            }
+        }
+        formatted
+    }
+
+    fn placeholder_value(&self, placeholder: &str) -> Option<String> {
+        if placeholder == "timer" {
+            let frames = self.timer.unwrap_or(0);
+            return Some(((frames as f32 / FPS).ceil() as u32).to_string());
+        }
+        placeholder
+            .strip_prefix("var:")
+            .map(|name| self.variables.get(name).copied().unwrap_or(0).to_string())
+    }
+
+    // Shifts the object so all of its bounding box is inside the projection
+    fn move_on_screen(&mut self) {
+        let bounding_box = self.bounding_box();
+        let shift = |min: f32, max: f32, limit: f32| {
+            if min < 0.0 {
+                -min
+            } else if max > limit {
+                limit - max
+            } else {
+                0.0
+            }
+        };
+        self.position.x += shift(bounding_box.min.x, bounding_box.max.x, PROJECTION_WIDTH);
+        self.position.y += shift(bounding_box.min.y, bounding_box.max.y, PROJECTION_HEIGHT);
+    }
+
+    fn check_property(&self, check: &PropertyCheck) -> bool {
+        match check {
+            PropertyCheck::Switch(switch_state) => self.switch == *switch_state,
+            PropertyCheck::Sprite(sprite) => self.sprite == *sprite,
+            PropertyCheck::FinishedAnimation => {
+                matches!(self.animation, AnimationStatus::Finished)
+            }
+            PropertyCheck::AnimationFrame(index) => self.animation.has_reached(*index),
+            PropertyCheck::AngleBetween { min, max } => is_between_angles(
+                self.angle.rem_euclid(360.0),
+                min.rem_euclid(360.0),
+                max.rem_euclid(360.0),
+            ),
+            PropertyCheck::Timer => match self.timer {
+                Some(alarm) => alarm == 0,
+                None => false,
+            },
+            PropertyCheck::TimerBelow(time) => match self.timer {
+                Some(alarm) => alarm < *time,
+                None => false,
+            },
+            PropertyCheck::TimerEquals(time) => match self.timer {
+                Some(alarm) => alarm == *time,
+                None => false,
+            },
+        }
+    }
+
     fn collision_aabb(&self) -> AABB {
         match &self.collision_area {
             Some(mut area) => {
@@ -1035,6 +2283,16 @@ impl Object {
     fn poly(&self) -> c2::Poly {
         let collision_aabb = self.collision_aabb();
         let origin = self.origin_in_world();
+        if let Some((cached_aabb, cached_origin, cached_angle, poly)) = &*self.poly_cache.0.borrow()
+        {
+            if *cached_aabb == collision_aabb
+                && *cached_origin == origin
+                && *cached_angle == self.angle
+            {
+                return *poly;
+            }
+        }
+
         let aabb = collision_aabb.move_position(-origin);
         let c2v = |x, y| c2::Vec2::new(x, y);
         let mut points = [
@@ -1053,7 +2311,95 @@ impl Object {
                 point.x() * s + point.y() * c + origin.y,
             );
         }
-        c2::Poly::from_slice(&points)
+        let poly = c2::Poly::from_slice(&points);
+        *self.poly_cache.0.borrow_mut() = Some((collision_aabb, origin, self.angle, poly));
+        poly
+    }
+
+    fn collision_centre(&self) -> Vec2 {
+        let aabb = self.collision_aabb();
+        let centre = Vec2::new(
+            (aabb.min.x + aabb.max.x) / 2.0,
+            (aabb.min.y + aabb.max.y) / 2.0,
+        );
+        let origin = self.origin_in_world();
+        let offset = centre - origin;
+        let angle = self.angle.to_radians();
+        let c = angle.cos();
+        let s = angle.sin();
+        Vec2::new(
+            offset.x * c - offset.y * s + origin.x,
+            offset.x * s + offset.y * c + origin.y,
+        )
+    }
+
+    pub fn collision_outline(&self) -> CollisionOutline {
+        match self.shape() {
+            ObjectShape::Poly(poly) | ObjectShape::MovedPoly((poly, _)) => {
+                let points = (0..poly.count())
+                    .map(|i| {
+                        let vert = poly.get_vert(i);
+                        Vec2::new(vert.x(), vert.y())
+                    })
+                    .collect();
+                CollisionOutline::Polygon(points)
+            }
+            ObjectShape::Circle(circle) => {
+                let position = circle.position();
+                CollisionOutline::Circle {
+                    centre: Vec2::new(position.x(), position.y()),
+                    radius: circle.radius(),
+                }
+            }
+        }
+    }
+
+    fn shape(&self) -> ObjectShape {
+        match self.collision_shape {
+            CollisionShape::Box => ObjectShape::Poly(self.poly()),
+            CollisionShape::Circle { radius } => {
+                let centre = self.collision_centre();
+                ObjectShape::Circle(c2::Circle::new(c2::Vec2::new(centre.x, centre.y), radius))
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> AABB {
+        if let CollisionShape::Circle { radius } = self.collision_shape {
+            let centre = self.collision_centre();
+            return AABB {
+                min: Vec2::new(centre.x - radius, centre.y - radius),
+                max: Vec2::new(centre.x + radius, centre.y + radius),
+            };
+        }
+        let collision_aabb = self.collision_aabb();
+        if self.angle == 0.0 {
+            return collision_aabb;
+        }
+        let origin = self.origin_in_world();
+        let aabb = collision_aabb.move_position(-origin);
+        let angle = self.angle.to_radians();
+        let c = angle.cos();
+        let s = angle.sin();
+        let corners = [
+            Vec2::new(aabb.min.x, aabb.min.y),
+            Vec2::new(aabb.max.x, aabb.min.y),
+            Vec2::new(aabb.max.x, aabb.max.y),
+            Vec2::new(aabb.min.x, aabb.max.y),
+        ];
+        let mut bounds: Option<AABB> = None;
+        for point in corners.iter() {
+            let point = Vec2::new(
+                point.x * c - point.y * s + origin.x,
+                point.x * s + point.y * c + origin.y,
+            );
+            let point_bounds = AABB {
+                min: point,
+                max: point,
+            };
+            bounds = Some(bounds.map_or(point_bounds, |bounds| bounds.union(point_bounds)));
+        }
+        bounds.unwrap_or(collision_aabb)
     }
 
     pub fn update_timer(&mut self) {
@@ -1069,20 +2415,68 @@ impl Object {
         };
     }
 
-    pub fn update_animation(&mut self) {
-        if let Some(sprite) = self.animation.update() {
-            self.sprite = sprite;
+    fn update_angle(&mut self) {
+        if !self.angle_set {
+            self.angle += self.angular_velocity;
         }
+        self.angle_set = false;
     }
 
-    pub fn update_switch(&mut self, old_switch: SwitchState) {
-        if self.switch == SwitchState::SwitchedOn
-            && (old_switch == SwitchState::SwitchedOn || old_switch == SwitchState::On)
-        {
-            self.switch = SwitchState::On;
-        } else if self.switch == SwitchState::SwitchedOff
-            && (old_switch == SwitchState::SwitchedOff || old_switch == SwitchState::Off)
-        {
+    fn update_queued_actions(&mut self) -> Vec<Action> {
+        for queued in self.queued_actions.iter_mut() {
+            queued.frames_left = queued.frames_left.saturating_sub(1);
+        }
+        let (ready, waiting) = self
+            .queued_actions
+            .drain(..)
+            .partition(|queued| queued.frames_left == 0);
+        self.queued_actions = waiting;
+        ready
+            .into_iter()
+            .map(|queued: QueuedAction| queued.action)
+            .collect()
+    }
+
+    fn is_off_screen(&self) -> bool {
+        let screen = AABB {
+            min: Vec2::zero(),
+            max: Vec2::new(PROJECTION_WIDTH, PROJECTION_HEIGHT),
+        };
+        !self.collision_aabb().overlaps(screen)
+    }
+
+    pub fn is_visible(&self) -> bool {
+        match self.blink {
+            Some(blink) => (blink.elapsed / blink.interval.max(1)) % 2 == 0,
+            None => true,
+        }
+    }
+
+    fn update_blink(&mut self) {
+        self.blink = match self.blink {
+            Some(blink) if blink.frames_left > 1 => Some(Blink {
+                frames_left: blink.frames_left - 1,
+                elapsed: blink.elapsed + 1,
+                ..blink
+            }),
+            _ => None,
+        };
+    }
+
+    pub fn update_animation(&mut self) {
+        if let Some(sprite) = self.animation.update() {
+            self.sprite = sprite;
+        }
+    }
+
+    pub fn update_switch(&mut self, old_switch: SwitchState) {
+        if self.switch == SwitchState::SwitchedOn
+            && (old_switch == SwitchState::SwitchedOn || old_switch == SwitchState::On)
+        {
+            self.switch = SwitchState::On;
+        } else if self.switch == SwitchState::SwitchedOff
+            && (old_switch == SwitchState::SwitchedOff || old_switch == SwitchState::Off)
+        {
             self.switch = SwitchState::Off;
         }
     }
@@ -1099,8 +2493,302 @@ impl GameData {
         let json_string =
             macroquad::file::load_string(&filename.as_ref().to_string_lossy()).await?;
 
+        GameData::from_json(&json_string)
+    }
+
+    pub fn from_json(text: &str) -> WeeResult<GameData> {
+        let value: serde_json::Value = json_from_str(text)?;
+        let version = value
+            .get("format_version")
+            .and_then(|version| version.as_str())
+            .unwrap_or(OLDEST_FORMAT_VERSION)
+            .to_string();
+        if parse_format_version(&version)? > parse_format_version(CURRENT_FORMAT_VERSION)? {
+            return Err(format!(
+                "Game format version {} is newer than the supported version {}",
+                version, CURRENT_FORMAT_VERSION
+            )
+            .into());
+        }
+        let value = migrate(value, &version);
+        match serde_json::from_value(value) {
+            Ok(data) => Ok(data),
+            Err(error) => Err(Box::new(error)),
+        }
+    }
+
+    pub fn validate(&self) -> WeeResult<()> {
+        fn check_sprite(sprite: &Sprite, asset_files: &AssetFiles, missing: &mut Vec<String>) {
+            if let Sprite::Image { name } | Sprite::ImageRegion { name, .. } = sprite {
+                if !asset_files.has_image(name) {
+                    missing.push(format!("image '{}'", name));
+                }
+            }
+        }
+
+        fn check_action(action: &Action, asset_files: &AssetFiles, missing: &mut Vec<String>) {
+            match action {
+                Action::PlaySound { name, .. } if !asset_files.audio.contains_key(name) => {
+                    missing.push(format!("sound '{}'", name));
+                }
+                Action::SetProperty(PropertySetter::Sprite(sprite)) => {
+                    check_sprite(sprite, asset_files, missing);
+                }
+                Action::Animate { sprites, .. } => {
+                    for sprite in sprites {
+                        check_sprite(sprite, asset_files, missing);
+                    }
+                }
+                Action::AnimateTimed { frames, .. } => {
+                    for (sprite, _) in frames {
+                        check_sprite(sprite, asset_files, missing);
+                    }
+                }
+                Action::AnimateSequence { name, .. }
+                    if !asset_files.sequences.contains_key(name) =>
+                {
+                    missing.push(format!("sequence '{}'", name));
+                }
+                Action::DrawText { font, .. } if !asset_files.fonts.contains_key(font) => {
+                    missing.push(format!("font '{}'", font));
+                }
+                Action::Random { random_actions } => {
+                    for action in random_actions {
+                        check_action(action, asset_files, missing);
+                    }
+                }
+                Action::If {
+                    then, otherwise, ..
+                } => {
+                    for action in then.iter().chain(otherwise) {
+                        check_action(action, asset_files, missing);
+                    }
+                }
+                Action::Sequence { steps } => {
+                    for (_, action) in steps {
+                        check_action(action, asset_files, missing);
+                    }
+                }
+                Action::Repeat { action, .. } => check_action(action, asset_files, missing),
+                Action::SetBackground { parts } => {
+                    for part in parts {
+                        check_sprite(&part.sprite, asset_files, missing);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut missing = Vec::new();
+        for part in &self.background {
+            check_sprite(&part.sprite, &self.asset_files, &mut missing);
+        }
+        for object in &self.objects {
+            let mut missing_from_object = Vec::new();
+            check_sprite(&object.sprite, &self.asset_files, &mut missing_from_object);
+            if let Some(health) = &object.health {
+                for (_, sprite) in &health.stages {
+                    check_sprite(sprite, &self.asset_files, &mut missing_from_object);
+                }
+            }
+            for instruction in &object.instructions {
+                for action in &instruction.actions {
+                    check_action(action, &self.asset_files, &mut missing_from_object);
+                }
+            }
+            missing.extend(
+                missing_from_object
+                    .into_iter()
+                    .map(|asset| format!("{} (used by {})", asset, object.name)),
+            );
+        }
+
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            missing.sort();
+            missing.dedup();
+            Err(format!("Missing assets: {}", missing.join(", ")).into())
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GameManifest {
+    pub games: Vec<String>,
+    #[serde(default)]
+    pub preload: Vec<String>,
+    // Zip files that replace the folder with the same name, e.g. "games/second.zip"
+    #[serde(default)]
+    pub archives: Vec<String>,
+}
+
+impl GameManifest {
+    pub async fn load(filename: impl AsRef<Path>) -> WeeResult<GameManifest> {
+        let json_string =
+            macroquad::file::load_string(&filename.as_ref().to_string_lossy()).await?;
+
+        json_from_str(&json_string)
+    }
+
+    // Games are keyed by filename for the lifetime of the program so leaking is fine
+    pub fn into_static(self) -> (Vec<&'static str>, Vec<&'static str>) {
+        let leak = |filename: String| -> &'static str { Box::leak(filename.into_boxed_str()) };
+        let mut games: Vec<&'static str> = self.games.into_iter().map(leak).collect();
+        let preload: Vec<&'static str> = self.preload.into_iter().map(leak).collect();
+        for filename in &preload {
+            if !games.contains(filename) {
+                games.push(filename);
+            }
+        }
+        (games, preload)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Settings {
+    #[serde(default)]
+    pub difficulty_curve: DifficultyCurve,
+    // Overrides keyed by game folder, e.g. "games/second"
+    #[serde(default)]
+    pub folders: HashMap<String, DifficultyCurve>,
+    #[serde(default)]
+    pub extra_time: ExtraTime,
+    // Draws every game flipped horizontally
+    #[serde(default)]
+    pub mirrored: bool,
+    // Lives at the start of a run, 1 for sudden death
+    #[serde(default)]
+    pub lives: Option<i32>,
+    // How many other games have to be played before a game can be chosen again
+    #[serde(default)]
+    pub game_cooldown: Option<usize>,
+    // Copies of the same sound that can start within a few frames of each other
+    #[serde(default)]
+    pub max_concurrent_sounds: Option<usize>,
+    // From 0.0 to 1.0, changed from the choose mode screen
+    #[serde(default)]
+    pub volume: Option<f32>,
+}
+
+impl Settings {
+    pub async fn load(filename: impl AsRef<Path>) -> WeeResult<Settings> {
+        let json_string =
+            macroquad::file::load_string(&filename.as_ref().to_string_lossy()).await?;
+
         json_from_str(&json_string)
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, filename: impl AsRef<Path>) -> WeeResult<()> {
+        let json_string = serde_json::to_string_pretty(self)?;
+        std::fs::write(filename, json_string)?;
+        Ok(())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self, _filename: impl AsRef<Path>) -> WeeResult<()> {
+        Err("Settings can't be saved in the browser yet".into())
+    }
+
+    pub fn difficulty_curve(&self, directory: &str) -> DifficultyCurve {
+        let directory = directory.trim_end_matches('/');
+        self.folders
+            .get(directory)
+            .copied()
+            .unwrap_or(self.difficulty_curve)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct DifficultyCurve {
+    pub up_to_difficulty_two: i32,
+    pub up_to_difficulty_three: i32,
+    pub increase_speed_after_games: i32,
+}
+
+impl Default for DifficultyCurve {
+    fn default() -> DifficultyCurve {
+        DifficultyCurve {
+            up_to_difficulty_two: 20,
+            up_to_difficulty_three: 40,
+            increase_speed_after_games: 5,
+        }
+    }
+}
+
+// Accessibility option that gives longer games and keeps them from speeding up too much
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ExtraTime {
+    pub enabled: bool,
+    pub length_multiplier: f32,
+    pub max_playback_rate: f32,
+}
+
+impl Default for ExtraTime {
+    fn default() -> ExtraTime {
+        ExtraTime {
+            enabled: false,
+            length_multiplier: 1.5,
+            max_playback_rate: 1.2,
+        }
+    }
+}
+
+impl ExtraTime {
+    pub fn length_multiplier(&self) -> f32 {
+        if self.enabled {
+            self.length_multiplier
+        } else {
+            1.0
+        }
+    }
+
+    pub fn cap_playback_rate(&self, playback_rate: f32) -> f32 {
+        if self.enabled {
+            playback_rate.min(self.max_playback_rate)
+        } else {
+            playback_rate
+        }
+    }
+}
+
+const CURRENT_FORMAT_VERSION: &str = "0.2";
+const OLDEST_FORMAT_VERSION: &str = "0.1";
+
+type Migration = fn(serde_json::Value) -> serde_json::Value;
+
+// Each migration upgrades a document from the first version to the second.
+// None are registered yet, documents without a migration are loaded as they are.
+const MIGRATIONS: &[(&str, &str, Migration)] = &[];
+
+fn parse_format_version(version: &str) -> WeeResult<(u32, u32)> {
+    let mut parts = version.split('.').map(str::parse::<u32>);
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor)), None) => Ok((major, minor)),
+        _ => Err(format!("Invalid game format version {}", version).into()),
+    }
+}
+
+fn migrate(value: serde_json::Value, from: &str) -> serde_json::Value {
+    run_migrations(value, from, MIGRATIONS)
+}
+
+fn run_migrations(
+    mut value: serde_json::Value,
+    from: &str,
+    migrations: &[(&str, &str, Migration)],
+) -> serde_json::Value {
+    let mut version = from.to_string();
+    for (migrate_from, migrate_to, migration) in migrations {
+        if version == *migrate_from {
+            value = migration(value);
+            version = migrate_to.to_string();
+        }
+    }
+    value
 }
 
 fn json_from_str<'a, T: Deserialize<'a>>(text: &'a str) -> WeeResult<T> {
@@ -1140,8 +2828,6 @@ pub struct FrameInfo {
     total: FrameCount,
     pub ran: u32,
     pub steps_taken: u32,
-    start_time: f64,
-    to_run: u32,
 }
 
 impl FrameInfo {
@@ -1151,7 +2837,7 @@ impl FrameInfo {
                 if frames < self.ran {
                     FrameCount::Frames(0)
                 } else {
-                    FrameCount::Frames((frames - self.ran).max(0))
+                    FrameCount::Frames(frames - self.ran)
                 }
             }
             FrameCount::Infinite => FrameCount::Infinite,
@@ -1161,6 +2847,16 @@ impl FrameInfo {
     fn is_final(self) -> bool {
         self.remaining() == FrameCount::Frames(1)
     }
+
+    fn reached_fraction(self, elapsed: f32) -> bool {
+        match self.total {
+            FrameCount::Frames(frames) if frames > 0 => {
+                let fraction = |ran: u32| ran as f32 / frames as f32;
+                fraction(self.ran) >= elapsed && (self.ran == 0 || fraction(self.ran - 1) < elapsed)
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -1169,12 +2865,220 @@ pub struct Mouse {
     pub state: ButtonState,
 }
 
+#[derive(Clone, Default)]
+pub struct Keyboard {
+    pub keys: HashMap<Key, ButtonState>,
+}
+
+impl Keyboard {
+    fn state(&self, key: Key) -> ButtonState {
+        self.keys.get(&key).copied().unwrap_or(ButtonState::Up)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Gamepad {
+    pub buttons: HashMap<GamepadButton, ButtonState>,
+}
+
+impl Gamepad {
+    pub fn state(&self, button: GamepadButton) -> ButtonState {
+        self.buttons
+            .get(&button)
+            .copied()
+            .unwrap_or(ButtonState::Up)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct PlayedSound {
+    pub name: String,
+    // -1.0 is the left edge of the screen, 0.0 the middle and 1.0 the right edge
+    pub pan: Option<f32>,
+}
+
 pub struct DrawnText {
     pub text: String,
     pub font: String,
     pub colour: Colour,
-    //resize: TextResize,
+    pub resize: TextResize,
     pub justify: JustifyText,
+    pub max_width: Option<f32>,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ScreenShake {
+    intensity: f32,
+    frames: u32,
+    pub remaining: u32,
+}
+
+impl ScreenShake {
+    fn none() -> ScreenShake {
+        ScreenShake {
+            intensity: 0.0,
+            frames: 0,
+            remaining: 0,
+        }
+    }
+
+    pub fn current_intensity(self) -> f32 {
+        if self.frames == 0 {
+            0.0
+        } else {
+            self.intensity * self.remaining as f32 / self.frames as f32
+        }
+    }
+}
+
+const GRID_CELL_SIZE: f32 = 200.0;
+const MAX_GRID_CELLS_PER_OBJECT: i64 = 256;
+
+type CellRange = ((i32, i32), (i32, i32));
+
+// Broadphase for collisions. Objects covering too many cells are kept in a
+// separate list and returned by every query
+#[derive(Debug, Default)]
+struct SpatialGrid {
+    cells: HashMap<(i32, i32), Vec<String>>,
+    ranges: HashMap<String, Option<CellRange>>,
+    large: Vec<String>,
+}
+
+impl SpatialGrid {
+    fn from_objects(objects: &Objects) -> SpatialGrid {
+        let mut grid = SpatialGrid::default();
+        for (name, object) in objects.iter() {
+            grid.insert(name, object.bounding_box());
+        }
+        grid
+    }
+
+    fn cell_range(area: AABB) -> Option<CellRange> {
+        let to_cell = |value: f32| (value / GRID_CELL_SIZE).floor();
+        let min = (to_cell(area.min.x), to_cell(area.min.y));
+        let max = (to_cell(area.max.x), to_cell(area.max.y));
+        let is_valid = [min.0, min.1, max.0, max.1]
+            .iter()
+            .all(|value| value.is_finite() && value.abs() < i32::MAX as f32);
+        if !is_valid {
+            return None;
+        }
+        let range = ((min.0 as i32, min.1 as i32), (max.0 as i32, max.1 as i32));
+        let cell_count = (i64::from((range.1).0) - i64::from((range.0).0) + 1)
+            * (i64::from((range.1).1) - i64::from((range.0).1) + 1);
+        if cell_count > MAX_GRID_CELLS_PER_OBJECT {
+            None
+        } else {
+            Some(range)
+        }
+    }
+
+    fn insert(&mut self, name: &str, area: AABB) {
+        let range = SpatialGrid::cell_range(area);
+        match range {
+            Some(((min_x, min_y), (max_x, max_y))) => {
+                for x in min_x..=max_x {
+                    for y in min_y..=max_y {
+                        self.cells.entry((x, y)).or_default().push(name.to_string());
+                    }
+                }
+            }
+            None => self.large.push(name.to_string()),
+        }
+        self.ranges.insert(name.to_string(), range);
+    }
+
+    fn remove(&mut self, name: &str) {
+        match self.ranges.remove(name) {
+            Some(Some(((min_x, min_y), (max_x, max_y)))) => {
+                for x in min_x..=max_x {
+                    for y in min_y..=max_y {
+                        if let Some(cell) = self.cells.get_mut(&(x, y)) {
+                            cell.retain(|other_name| other_name != name);
+                        }
+                    }
+                }
+            }
+            Some(None) => self.large.retain(|other_name| other_name != name),
+            None => {}
+        }
+    }
+
+    fn update(&mut self, name: &str, area: AABB) {
+        if self.ranges.get(name) != Some(&SpatialGrid::cell_range(area)) {
+            self.remove(name);
+            self.insert(name, area);
+        }
+    }
+
+    // Whether two objects share a cell, or either is too large to be placed
+    fn are_near(&self, name: &str, other_name: &str) -> bool {
+        match (self.ranges.get(name), self.ranges.get(other_name)) {
+            (Some(Some((min, max))), Some(Some((other_min, other_max)))) => {
+                min.0 <= other_max.0
+                    && other_min.0 <= max.0
+                    && min.1 <= other_max.1
+                    && other_min.1 <= max.1
+            }
+            _ => true,
+        }
+    }
+
+    fn query(&self, area: AABB) -> HashSet<&str> {
+        let mut found: HashSet<&str> = self.large.iter().map(|name| name.as_str()).collect();
+        match SpatialGrid::cell_range(area) {
+            Some(((min_x, min_y), (max_x, max_y))) => {
+                for x in min_x..=max_x {
+                    for y in min_y..=max_y {
+                        if let Some(cell) = self.cells.get(&(x, y)) {
+                            found.extend(cell.iter().map(|name| name.as_str()));
+                        }
+                    }
+                }
+            }
+            None => found.extend(self.ranges.keys().map(|name| name.as_str())),
+        }
+        found
+    }
+}
+
+// Deepest overlap with a nearby solid object, checked in object order
+fn closest_manifold(
+    objects: &Objects,
+    grid: &SpatialGrid,
+    name: &str,
+    shape: ObjectShape,
+    area: AABB,
+) -> (Option<c2::Manifold>, Vec2) {
+    let mut nearby: Vec<usize> = grid
+        .query(area)
+        .into_iter()
+        .filter(|other_name| *other_name != name)
+        .filter_map(|other_name| objects.get_index_of(other_name))
+        .collect();
+    nearby.sort_unstable();
+
+    let mut longest_depth = 0.0;
+    let mut closest_manifold = None;
+    let mut position = Vec2::zero();
+    for (_, other) in nearby
+        .into_iter()
+        .filter_map(|index| objects.get_index(index))
+    {
+        if other.solid {
+            let manifold = shape.manifold(&other.shape());
+            if manifold.count() > 0 {
+                let depth = manifold.depths()[0];
+                if depth > longest_depth || closest_manifold.is_none() {
+                    closest_manifold = Some(manifold);
+                    position = other.position;
+                    longest_depth = depth;
+                }
+            }
+        }
+    }
+    (closest_manifold, position)
 }
 
 pub struct Game {
@@ -1186,14 +3090,193 @@ pub struct Game {
     pub intro_text: String,
     pub drawn_text: HashMap<String, DrawnText>,
     pub difficulty: u32,
+    pub score: i32,
+    pub lives: i32,
+    pub lives_adjustment: i32,
+    pub score_adjustment: i32,
     pub has_music_finished: bool,
     pub end_early: bool,
+    pub shake: ScreenShake,
+    templates: Vec<SerialiseObject>,
+    spawn_count: u32,
+    destroyed: HashSet<String>,
+    grid: SpatialGrid,
+    // Objects moved or resized by an action since their grid cells were last updated
+    moved: HashSet<String>,
+    trace: Option<Vec<FrameTrace>>,
+    drag: Drag,
+    random_streams: Option<RandomStreams>,
+    delayed_outcomes: Vec<DelayedOutcome>,
+    slow_motion_progress: f32,
+    playing_sounds: HashMap<String, u32>,
+    finished_sounds: HashSet<String>,
+    // Number of frames in each image sequence
+    sequences: HashMap<String, u32>,
+}
+
+fn try_to_set_status(status: &mut GameStatus, opposite: WinStatus, next_frame: WinStatus) {
+    *status = match status.current {
+        WinStatus::NotYetWon | WinStatus::NotYetLost => {
+            if status.next_frame == opposite {
+                *status
+            } else {
+                GameStatus {
+                    current: status.current,
+                    next_frame,
+                }
+            }
+        }
+        _ => *status,
+    };
+}
+
+// A win or loss that resolves after a countdown, so an animation can finish first
+#[derive(Clone, Debug)]
+struct DelayedOutcome {
+    frames: u32,
+    won: bool,
+}
+
+// Per-object random number streams, so adding an object doesn't change what
+// another object rolls. The global generator is reseeded around each object's
+// update, which means anything random outside of an object's update (like the
+// times chosen when the game is loaded) still uses the shared stream.
+// The shared stream's state can't be read back, so a seed to resume it from is
+// drawn on entering and it carries on from there however many numbers the object used.
+#[derive(Clone, Debug)]
+struct RandomStreams {
+    seed: u64,
+    states: HashMap<String, u64>,
+    resume: u64,
+}
+
+fn next_seed() -> u64 {
+    ((rand::rand() as u64) << 32) | rand::rand() as u64
+}
+
+impl RandomStreams {
+    fn new(seed: u64) -> RandomStreams {
+        RandomStreams {
+            seed,
+            states: HashMap::new(),
+            resume: 0,
+        }
+    }
+
+    fn enter(&mut self, name: &str) {
+        self.resume = next_seed();
+        let seed = self.seed;
+        let state = *self
+            .states
+            .entry(name.to_string())
+            .or_insert_with(|| stream_seed(name, seed));
+        rand::srand(state);
+    }
+
+    fn leave(&mut self, name: &str) {
+        self.states.insert(name.to_string(), next_seed());
+        rand::srand(self.resume);
+    }
+}
+
+fn stream_seed(name: &str, seed: u64) -> u64 {
+    // FNV-1a of the name mixed with the seed using splitmix64
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in name.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    let mut z = (hash ^ seed).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[derive(Clone, Debug, Default)]
+struct Drag {
+    origin: Option<Vec2>,
+    // The displacement along the path that got furthest from the origin, so a drag
+    // that goes out and comes back is still a swipe in the direction it went
+    furthest: Option<Vec2>,
+    swipe: Option<Vec2>,
+}
+
+impl Drag {
+    fn update(&mut self, mouse: &Mouse) {
+        self.swipe = None;
+        match mouse.state {
+            ButtonState::Press => {
+                self.origin = Some(mouse.position);
+                self.furthest = None;
+            }
+            ButtonState::Down => {
+                if self.origin.is_none() {
+                    self.origin = Some(mouse.position);
+                }
+                self.track(mouse.position);
+            }
+            ButtonState::Release => {
+                self.track(mouse.position);
+                if self.origin.take().is_some() {
+                    self.swipe = self.furthest.take();
+                }
+            }
+            ButtonState::Up => {
+                self.origin = None;
+                self.furthest = None;
+            }
+        }
+    }
+
+    fn track(&mut self, position: Vec2) {
+        if let Some(origin) = self.origin {
+            let displacement = position - origin;
+            let is_further = match self.furthest {
+                Some(furthest) => displacement.magnitude() > furthest.magnitude(),
+                None => true,
+            };
+            if is_further {
+                self.furthest = Some(displacement);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FrameTrace {
+    pub frame: u32,
+    pub fired: Vec<FiredInstruction>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FiredInstruction {
+    pub object: String,
+    pub instruction: usize,
 }
 
 impl Game {
     pub fn from_data(game_data: GameData) -> Game {
+        let objects = Objects::from_serialised(game_data.objects.clone());
         Game {
-            objects: Objects::from_serialised(game_data.objects),
+            templates: game_data.objects.clone(),
+            spawn_count: 0,
+            destroyed: HashSet::new(),
+            grid: SpatialGrid::from_objects(&objects),
+            moved: HashSet::new(),
+            trace: None,
+            drag: Drag::default(),
+            random_streams: None,
+            delayed_outcomes: Vec::new(),
+            slow_motion_progress: 0.0,
+            playing_sounds: HashMap::new(),
+            finished_sounds: HashSet::new(),
+            sequences: game_data
+                .asset_files
+                .sequences
+                .iter()
+                .map(|(name, sequence)| (name.clone(), sequence.count))
+                .collect(),
+            objects,
             background: game_data.background,
             frames: FrameInfo {
                 total: match game_data.length {
@@ -1202,8 +3285,6 @@ impl Game {
                 },
                 ran: 0,
                 steps_taken: 0,
-                start_time: macroquad::time::get_time(),
-                to_run: 0,
             },
             status: GameStatus {
                 current: WinStatus::NotYetWon,
@@ -1213,38 +3294,232 @@ impl Game {
             intro_text: game_data.intro_text.as_deref().unwrap_or("").to_string(),
             drawn_text: HashMap::new(),
             difficulty: 1,
+            score: 0,
+            lives: 0,
+            lives_adjustment: 0,
+            score_adjustment: 0,
             has_music_finished: false,
             end_early: false,
+            shake: ScreenShake::none(),
         }
     }
 
-    pub fn update(&mut self, mouse: &Mouse) -> WeeResult<Vec<String>> {
-        let mut played_sounds = Vec::new();
+    // When::End still fires on the last frame since that's worked out from the new total
+    pub fn extend_length(&mut self, multiplier: f32) {
+        if let FrameCount::Frames(frames) = self.frames.total {
+            self.frames.total = FrameCount::Frames((frames as f32 * multiplier).round() as u32);
+        }
+    }
+
+    pub fn isolate_random(&mut self, seed: u64) {
+        self.random_streams = Some(RandomStreams::new(seed));
+    }
+
+    // Objects in the order they should be drawn: higher layers first, then
+    // objects on the same layer in the order they were added
+    pub fn draw_order(&self) -> Vec<(&String, &Object)> {
+        let mut objects: Vec<(&String, &Object)> = self.objects.iter().collect();
+        objects.sort_by_key(|(_, object)| std::cmp::Reverse(object.layer));
+        objects
+    }
+
+    // The topmost object under the point decides the cursor. Objects that can't be
+    // seen are skipped since games often use transparent objects to track state
+    pub fn cursor_at(&self, point: Vec2) -> CursorStyle {
+        let is_seen = |object: &Object| {
+            let is_transparent = match object.sprite {
+                Sprite::Colour(colour) => colour.a == 0.0,
+                _ => false,
+            };
+            object.is_visible() && object.alpha > 0.0 && !is_transparent
+        };
+        self.draw_order()
+            .into_iter()
+            .rev()
+            .find(|(_, object)| is_seen(object) && object.shape().contains_point(point))
+            .map(|(_, object)| object.cursor)
+            .unwrap_or(CursorStyle::Default)
+    }
+
+    // Records which instructions fire each frame, for working out why a game can't be won
+    pub fn enable_trace(&mut self) {
+        self.trace = Some(Vec::new());
+    }
+
+    pub fn take_trace(&mut self) -> Vec<FrameTrace> {
+        self.trace.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    pub fn advance_frame(&mut self) {
+        self.status.current = self.status.next_frame;
+        self.status.next_frame = match self.status.next_frame {
+            WinStatus::HasBeenWon => WinStatus::Won,
+            WinStatus::HasBeenLost => WinStatus::Lost,
+            _ => self.status.next_frame,
+        };
+        self.frames.ran += 1;
+    }
+
+    // Steps the game without drawing or playing audio, returning the final status
+    // and the names of any sounds that would have played
+    pub fn run_headless(
+        &mut self,
+        mut inputs: impl Iterator<Item = Mouse>,
+        max_frames: u32,
+    ) -> WeeResult<(WinStatus, Vec<PlayedSound>)> {
+        let mut played_sounds = Vec::new();
+        let mut mouse = Mouse {
+            position: Vec2::zero(),
+            state: ButtonState::Up,
+        };
+        for _ in 0..max_frames {
+            if self.frames.remaining() == FrameCount::Frames(0) || self.end_early {
+                break;
+            }
+            mouse = inputs.next().unwrap_or(Mouse {
+                position: mouse.position,
+                state: ButtonState::Up,
+            });
+            let mut new_sounds = self.update(&mouse, &Keyboard::default(), &Gamepad::default())?;
+            played_sounds.append(&mut new_sounds);
+            self.advance_frame();
+        }
+        Ok((self.status.next_frame, played_sounds))
+    }
+
+    fn resolve_delayed_outcomes(&mut self) {
+        for outcome in self.delayed_outcomes.iter_mut() {
+            outcome.frames = outcome.frames.saturating_sub(1);
+        }
+        let status = &mut self.status;
+        self.delayed_outcomes.retain(|outcome| {
+            if outcome.frames > 0 {
+                return true;
+            }
+            if outcome.won {
+                try_to_set_status(status, WinStatus::HasBeenLost, WinStatus::HasBeenWon);
+            } else {
+                try_to_set_status(status, WinStatus::HasBeenWon, WinStatus::HasBeenLost);
+            }
+            false
+        });
+    }
+
+    // Sounds are played outside of the engine so it's told how long each one lasts.
+    // Playing a sound again restarts its countdown.
+    pub fn track_sound(&mut self, name: &str, length_in_seconds: f32) {
+        let frames = (length_in_seconds * FPS).ceil() as u32;
+        self.playing_sounds.insert(name.to_string(), frames.max(1));
+    }
+
+    fn update_playing_sounds(&mut self) {
+        self.finished_sounds.clear();
+        let finished_sounds = &mut self.finished_sounds;
+        self.playing_sounds.retain(|name, frames| {
+            *frames -= 1;
+            if *frames == 0 {
+                finished_sounds.insert(name.clone());
+            }
+            *frames > 0
+        });
+    }
+
+    // Triggers still run every frame during slow motion so input stays responsive,
+    // it's only movement and animation that skip frames
+    fn advance_slow_motion(&mut self) -> bool {
+        match self.effect {
+            Effect::SlowMotion { factor } => {
+                self.slow_motion_progress += factor.clamp(0.0, 1.0);
+                if self.slow_motion_progress >= 1.0 {
+                    self.slow_motion_progress -= 1.0;
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => {
+                self.slow_motion_progress = 0.0;
+                true
+            }
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        mouse: &Mouse,
+        keyboard: &Keyboard,
+        gamepad: &Gamepad,
+    ) -> WeeResult<Vec<PlayedSound>> {
+        let mut played_sounds = Vec::new();
         let keys: Vec<String> = self.objects.keys().cloned().collect();
+        if self.shake.remaining > 0 {
+            self.shake.remaining -= 1;
+        }
+        self.drag.update(mouse);
+        self.resolve_delayed_outcomes();
+        self.update_playing_sounds();
         match self.effect {
-            Effect::None => {
+            Effect::None | Effect::SlowMotion { .. } => {
+                let is_moving = self.advance_slow_motion();
                 for name in keys.iter() {
+                    if self.destroyed.contains(name) {
+                        continue;
+                    }
+
+                    if let Some(streams) = &mut self.random_streams {
+                        streams.enter(name);
+                    }
+
                     let old_switch = self.objects[name].switch;
+                    let is_out_of_health = self.objects[name].health() == Some(0);
 
                     self.objects[name].update_timer();
 
-                    let actions = self.check_triggers(name, &mouse)?;
+                    self.objects[name].update_blink();
 
-                    let mut new_sounds = self.apply_actions(name, &actions, &mouse)?;
+                    let mut actions = self.objects[name].update_queued_actions();
+                    self.refresh_grid();
+                    actions.extend(self.check_triggers(name, mouse, keyboard, gamepad)?);
+
+                    let mut new_sounds =
+                        self.apply_actions(name, &actions, mouse, keyboard, gamepad)?;
                     played_sounds.append(&mut new_sounds);
 
-                    self.objects[name].update_animation();
+                    if is_moving {
+                        self.objects[name].update_animation();
+
+                        self.move_object(name, mouse)?;
+
+                        self.objects[name].update_angle();
+                    }
 
-                    self.move_object(name, &mouse)?;
+                    let bounding_box = self.objects[name].bounding_box();
+                    self.grid.update(name, bounding_box);
 
                     self.objects[name].update_switch(old_switch);
+                    self.objects[name].was_out_of_health = is_out_of_health;
+
+                    if let Some(streams) = &mut self.random_streams {
+                        streams.leave(name);
+                    }
                 }
             }
             Effect::Freeze => {
                 for name in keys.iter() {
+                    if self.destroyed.contains(name) {
+                        continue;
+                    }
+
                     self.objects[name].update_timer();
 
-                    let actions = self.check_triggers(name, &mouse)?;
+                    if let Some(streams) = &mut self.random_streams {
+                        streams.enter(name);
+                    }
+                    self.refresh_grid();
+                    let actions = self.check_triggers(name, mouse, keyboard, gamepad)?;
+                    if let Some(streams) = &mut self.random_streams {
+                        streams.leave(name);
+                    }
 
                     for action in actions {
                         if action == Action::EndEarly {
@@ -1255,10 +3530,39 @@ impl Game {
             }
         }
 
+        for name in self.destroyed.drain() {
+            self.grid.remove(&name);
+            self.objects.shift_remove(&name);
+            self.drawn_text.remove(&name);
+        }
+
         Ok(played_sounds)
     }
 
-    fn is_triggered(&self, name: &str, trigger: &Trigger, mouse: &Mouse) -> WeeResult<bool> {
+    fn refresh_grid(&mut self) {
+        for name in self.moved.drain() {
+            if let Some(object) = self.objects.get(&name) {
+                self.grid.update(&name, object.bounding_box());
+            }
+        }
+    }
+
+    fn is_triggered(
+        &self,
+        name: &str,
+        trigger: &Trigger,
+        mouse: &Mouse,
+        keyboard: &Keyboard,
+        gamepad: &Gamepad,
+        depth: u32,
+    ) -> WeeResult<bool> {
+        if depth > MAX_TRIGGER_DEPTH {
+            return Err(format!(
+                "Triggers for {} are nested more than {} levels deep",
+                name, MAX_TRIGGER_DEPTH
+            )
+            .into());
+        }
         let is_point_in_area = |pos: Vec2, area: AABB| {
             pos.x >= area.min.x && pos.y >= area.min.y && pos.x < area.max.x && pos.y < area.max.y
         };
@@ -1269,52 +3573,50 @@ impl Game {
             Trigger::Time(When::End) => self.frames.is_final(),
             Trigger::Time(When::Exact { time }) => self.frames.ran == *time,
             Trigger::Time(When::Random { .. }) => false,
+            Trigger::Time(When::Fraction { elapsed }) => self.frames.reached_fraction(*elapsed),
             Trigger::Collision(CollisionWith::Object { name: other_name }) => {
                 let other_obj = self.objects.get_obj(other_name)?;
 
-                self.objects[name].poly().collides_with(&other_obj.poly())
+                self.grid.are_near(name, other_name)
+                    && self.objects[name]
+                        .bounding_box()
+                        .overlaps(other_obj.bounding_box())
+                    && self.objects[name].shape().collides_with(&other_obj.shape())
             }
             Trigger::Collision(CollisionWith::Area(area)) => {
                 let area = c2::AABB::new(c2v(area.min), c2v(area.max));
 
-                self.objects[name].poly().collides_with(&area)
+                self.objects[name].shape().collides_with_shape(&area)
             }
             Trigger::WinStatus(win_status) => match win_status {
-                WinStatus::Won => match self.status.current {
-                    WinStatus::Won | WinStatus::HasBeenWon => true,
-                    _ => false,
-                },
-                WinStatus::Lost => match self.status.current {
-                    WinStatus::Lost | WinStatus::HasBeenLost => true,
-                    _ => false,
-                },
-                WinStatus::NotYetLost => match self.status.current {
+                WinStatus::Won => {
+                    matches!(self.status.current, WinStatus::Won | WinStatus::HasBeenWon)
+                }
+                WinStatus::Lost => matches!(
+                    self.status.current,
+                    WinStatus::Lost | WinStatus::HasBeenLost
+                ),
+                WinStatus::NotYetLost => matches!(
+                    self.status.current,
                     WinStatus::NotYetLost
-                    | WinStatus::NotYetWon
-                    | WinStatus::HasBeenWon
-                    | WinStatus::Won => true,
-                    _ => false,
-                },
-                WinStatus::NotYetWon => match self.status.current {
+                        | WinStatus::NotYetWon
+                        | WinStatus::HasBeenWon
+                        | WinStatus::Won
+                ),
+                WinStatus::NotYetWon => matches!(
+                    self.status.current,
                     WinStatus::NotYetWon
-                    | WinStatus::NotYetLost
-                    | WinStatus::HasBeenLost
-                    | WinStatus::Lost => true,
-                    _ => false,
-                },
+                        | WinStatus::NotYetLost
+                        | WinStatus::HasBeenLost
+                        | WinStatus::Lost
+                ),
                 _ => self.status.current == *win_status,
             },
             Trigger::Input(Input::Mouse { over, interaction }) => {
                 let is_over = match over {
                     MouseOver::Object { name: other_name } => {
                         let other_obj = self.objects.get_obj(other_name)?;
-                        other_obj
-                            .poly()
-                            .gjk(&c2::Circle::new(c2v(mouse.position), 1.0))
-                            .use_radius(false)
-                            .run()
-                            .distance()
-                            == 0.0
+                        other_obj.shape().contains_point(mouse.position)
                     }
                     MouseOver::Area(area) => is_mouse_in_area(mouse, *area),
                     MouseOver::Anywhere => true,
@@ -1325,42 +3627,142 @@ impl Game {
                         MouseInteraction::Hover => true,
                     }
             }
+            Trigger::Input(Input::Keyboard { key, state }) => keyboard.state(*key) == *state,
+            Trigger::Input(Input::Gamepad { button, state }) => gamepad.state(*button) == *state,
             Trigger::CheckProperty {
                 name: object_name,
                 check,
-            } => {
-                let obj = self.objects.get_obj(object_name)?;
-                match check {
-                    PropertyCheck::Switch(switch_state) => obj.switch == *switch_state,
-                    PropertyCheck::Sprite(sprite) => obj.sprite == *sprite,
-                    PropertyCheck::FinishedAnimation => match obj.animation {
-                        AnimationStatus::Finished => true,
-                        _ => false,
-                    },
-                    PropertyCheck::Timer => match obj.timer {
-                        Some(alarm) => alarm == 0,
-                        None => false,
-                    },
+            } => self.objects.get_obj(object_name)?.check_property(check),
+            Trigger::AllMatch { pattern, check } => {
+                let mut matching = self
+                    .objects
+                    .iter()
+                    .filter(|(other_name, _)| other_name.starts_with(pattern.as_str()))
+                    .peekable();
+                if matching.peek().is_none() {
+                    return Err(format!("No objects with names starting with {}", pattern).into());
                 }
+                matching.all(|(_, obj)| obj.check_property(check))
             }
             Trigger::Random { chance } => {
                 let roll = rand::gen_range::<f32>(0.0, 1.0);
                 roll < *chance
             }
             Trigger::DifficultyLevel { level } => self.difficulty == *level,
+            Trigger::ScoreAtLeast { score } => self.score >= *score,
+            Trigger::Lives { comparison, value } => {
+                comparison.compare(self.lives as f32, *value as f32)
+            }
+            Trigger::Proximity {
+                name: other_name,
+                distance,
+            } => {
+                let other_obj = self.objects.get_obj(other_name)?;
+                let difference = other_obj.origin_in_world() - self.objects[name].origin_in_world();
+
+                difference.magnitude() <= *distance
+            }
+            Trigger::CheckVariable {
+                object,
+                name: variable,
+                comparison,
+                value,
+            } => {
+                let obj = self.objects.get_obj(object)?;
+                let current = obj.variables.get(variable).copied().unwrap_or(0);
+                comparison.compare(current as f32, *value as f32)
+            }
+            Trigger::SoundFinished { name: sound_name } => {
+                self.finished_sounds.contains(sound_name)
+            }
+            Trigger::Destroyed => {
+                self.objects[name].health() == Some(0) && !self.objects[name].was_out_of_health
+            }
+            Trigger::OffScreen => self.objects[name].is_off_screen(),
+            Trigger::OnScreen => !self.objects[name].is_off_screen(),
+            Trigger::Swipe {
+                direction,
+                min_distance,
+                tolerance,
+            } => match self.drag.swipe {
+                Some(swipe) if swipe.magnitude() >= *min_distance => {
+                    let angle = swipe.x.atan2(-swipe.y).to_degrees();
+                    let difference = (angle - direction.angle()).rem_euclid(360.0);
+                    difference.min(360.0 - difference) <= *tolerance
+                }
+                _ => false,
+            },
+            Trigger::Speed { comparison, value } => {
+                let speed = self.objects[name].active_motion.velocity().magnitude();
+                comparison.compare(speed, *value)
+            }
+            Trigger::AnyOf { triggers } => {
+                let mut triggered = false;
+                for trigger in triggers {
+                    triggered = triggered
+                        || self.is_triggered(name, trigger, mouse, keyboard, gamepad, depth + 1)?;
+                }
+                triggered
+            }
+            // Random times are replaced with exact times when the object is created,
+            // so negating one is true on every frame except the chosen one
+            Trigger::Not { trigger } => {
+                !self.is_triggered(name, trigger, mouse, keyboard, gamepad, depth + 1)?
+            }
         };
         Ok(triggered)
     }
 
-    fn check_triggers(&self, name: &str, mouse: &Mouse) -> WeeResult<Vec<Action>> {
+    fn check_triggers(
+        &mut self,
+        name: &str,
+        mouse: &Mouse,
+        keyboard: &Keyboard,
+        gamepad: &Gamepad,
+    ) -> WeeResult<Vec<Action>> {
         let mut actions = Vec::new();
-        for instruction in self.objects[name].instructions.iter() {
+        let mut fired = Vec::new();
+        let frame = self.frames.ran;
+        for (index, instruction) in self.objects[name].instructions.iter().enumerate() {
+            let is_cooling_down = match self.objects[name].last_fired.get(&index) {
+                Some(last_fired) => frame - last_fired < instruction.cooldown,
+                None => false,
+            };
+            if is_cooling_down {
+                continue;
+            }
             let mut triggered = true;
             for trigger in &instruction.triggers {
-                triggered = triggered && self.is_triggered(name, trigger, mouse)?;
+                triggered =
+                    triggered && self.is_triggered(name, trigger, mouse, keyboard, gamepad, 0)?;
             }
             if triggered {
                 actions.extend(instruction.actions.clone());
+                fired.push(index);
+            }
+        }
+        for &index in &fired {
+            let object = &mut self.objects[name];
+            if object.instructions[index].cooldown > 0 {
+                object.last_fired.insert(index, frame);
+            }
+        }
+        if let Some(trace) = &mut self.trace {
+            if !fired.is_empty() {
+                if trace.last().map(|last| last.frame) != Some(frame) {
+                    trace.push(FrameTrace {
+                        frame,
+                        fired: Vec::new(),
+                    });
+                }
+                if let Some(frame_trace) = trace.last_mut() {
+                    for instruction in fired {
+                        frame_trace.fired.push(FiredInstruction {
+                            object: name.to_string(),
+                            instruction,
+                        });
+                    }
+                }
             }
         }
         Ok(actions)
@@ -1371,10 +3773,12 @@ impl Game {
         name: &str,
         actions: &[Action],
         mouse: &Mouse,
-    ) -> WeeResult<Vec<String>> {
+        keyboard: &Keyboard,
+        gamepad: &Gamepad,
+    ) -> WeeResult<Vec<PlayedSound>> {
         let mut played_sounds = Vec::new();
         for action in actions {
-            self.apply_action(name, action, mouse, &mut played_sounds)?;
+            self.apply_action(name, action, mouse, keyboard, gamepad, &mut played_sounds)?;
         }
         Ok(played_sounds)
     }
@@ -1384,29 +3788,24 @@ impl Game {
         name: &str,
         action: &Action,
         mouse: &Mouse,
-        played_sounds: &mut Vec<String>,
+        keyboard: &Keyboard,
+        gamepad: &Gamepad,
+        played_sounds: &mut Vec<PlayedSound>,
     ) -> WeeResult<()> {
-        let try_to_set_status = |status: &mut GameStatus, opposite, next_frame| {
-            *status = match status.current {
-                WinStatus::NotYetWon | WinStatus::NotYetLost => {
-                    if status.next_frame == opposite {
-                        *status
-                    } else {
-                        GameStatus {
-                            current: status.current,
-                            next_frame,
-                        }
-                    }
-                }
-                _ => *status,
-            };
-        };
         let try_to_win = |status| {
             try_to_set_status(status, WinStatus::HasBeenLost, WinStatus::HasBeenWon);
         };
         let try_to_lose = |status| {
             try_to_set_status(status, WinStatus::HasBeenWon, WinStatus::HasBeenLost);
         };
+        if let Action::SetProperty(
+            PropertySetter::Angle(_)
+            | PropertySetter::Size(_)
+            | PropertySetter::CopyPosition { .. },
+        ) = action
+        {
+            self.moved.insert(name.to_string());
+        }
         match action {
             Action::Motion(motion) => {
                 self.objects[name].queued_motion.push(motion.clone());
@@ -1417,11 +3816,47 @@ impl Game {
             Action::Lose => {
                 try_to_lose(&mut self.status);
             }
+            Action::WinAfter { frames } => {
+                self.delayed_outcomes.push(DelayedOutcome {
+                    frames: *frames,
+                    won: true,
+                });
+            }
+            Action::LoseAfter { frames } => {
+                self.delayed_outcomes.push(DelayedOutcome {
+                    frames: *frames,
+                    won: false,
+                });
+            }
             Action::Effect(new_effect) => {
                 self.effect = *new_effect;
             }
-            Action::PlaySound { name: sound_name } => {
-                played_sounds.push(sound_name.clone());
+            Action::SetBackground { parts } => {
+                self.background = parts.clone();
+            }
+            Action::SetBackgroundColour(colour) => {
+                self.background = vec![BackgroundPart {
+                    sprite: Sprite::Colour(*colour),
+                    area: AABB {
+                        min: Vec2::zero(),
+                        max: Vec2::new(PROJECTION_WIDTH, PROJECTION_HEIGHT),
+                    },
+                }];
+            }
+            Action::PlaySound {
+                name: sound_name,
+                attenuate,
+            } => {
+                let pan = if *attenuate {
+                    let x = self.objects[name].position.x / PROJECTION_WIDTH;
+                    Some((x * 2.0 - 1.0).clamp(-1.0, 1.0))
+                } else {
+                    None
+                };
+                played_sounds.push(PlayedSound {
+                    name: sound_name.clone(),
+                    pan,
+                });
             }
             Action::StopMusic => {
                 // TODO:
@@ -1432,11 +3867,53 @@ impl Game {
                 animation_type,
                 sprites,
                 speed,
+                reverse,
+            } => {
+                let durations = vec![speed.to_animation_time(); sprites.len()];
+                self.objects[name].animation =
+                    AnimationStatus::start(*animation_type, sprites.clone(), durations, *reverse);
+
+                if let Some(sprite) = self.objects[name].animation.current_sprite() {
+                    self.objects[name].sprite = sprite;
+                }
+            }
+            Action::AnimateSequence {
+                name: sequence_name,
+                animation_type,
+                speed,
+                reverse,
+            } => {
+                let count = match self.sequences.get(sequence_name) {
+                    Some(count) => *count,
+                    None => return Err(format!("Unknown image sequence {}", sequence_name).into()),
+                };
+                let sprites = (0..count)
+                    .map(|index| Sprite::Image {
+                        name: ImageSequence::frame_name(sequence_name, index),
+                    })
+                    .collect();
+                let action = Action::Animate {
+                    animation_type: *animation_type,
+                    sprites,
+                    speed: *speed,
+                    reverse: *reverse,
+                };
+                return self.apply_action(name, &action, mouse, keyboard, gamepad, played_sounds);
+            }
+            Action::AnimateTimed {
+                frames,
+                animation_type,
             } => {
+                let sprites = frames.iter().map(|(sprite, _)| sprite.clone()).collect();
+                // The sprite changes on the update after the countdown reaches zero
+                let durations = frames
+                    .iter()
+                    .map(|(_, duration)| duration.saturating_sub(1))
+                    .collect();
                 self.objects[name].animation =
-                    AnimationStatus::start(*animation_type, sprites, *speed);
+                    AnimationStatus::start(*animation_type, sprites, durations, false);
 
-                if let Some(sprite) = sprites.get(0).cloned() {
+                if let Some(sprite) = self.objects[name].animation.current_sprite() {
                     self.objects[name].sprite = sprite;
                 }
             }
@@ -1444,8 +3921,9 @@ impl Game {
                 text,
                 font,
                 colour,
-                resize: _resize,
+                resize,
                 justify,
+                max_width,
             } => {
                 self.drawn_text.insert(
                     name.to_string(),
@@ -1453,12 +3931,17 @@ impl Game {
                         text: text.to_string(),
                         font: font.to_string(),
                         colour: *colour,
-                        //resize: *resize,
+                        resize: *resize,
                         justify: *justify,
+                        max_width: *max_width,
                     },
                 );
             }
             Action::SetProperty(PropertySetter::Angle(angle_setter)) => {
+                match angle_setter {
+                    AngleSetter::Increase(_) | AngleSetter::Decrease(_) => {}
+                    _ => self.objects[name].angle_set = true,
+                }
                 self.objects[name].angle = match angle_setter {
                     AngleSetter::Value(value) => *value,
                     AngleSetter::Increase(value) => self.objects[name].angle + value,
@@ -1473,14 +3956,6 @@ impl Game {
                         }
 
                         fn clamp_degrees(angle: f32, min: f32, max: f32) -> f32 {
-                            fn is_between_angles(angle: f32, min: f32, max: f32) -> bool {
-                                if min < max {
-                                    angle >= min && angle <= max
-                                } else {
-                                    angle >= min && angle <= (max + 360.0)
-                                        || angle >= (min - 360.0) && angle <= max
-                                }
-                            }
                             fn distance_between_angles(a: f32, b: f32) -> f32 {
                                 let dist1 = (a - b).abs();
                                 let dist2 = ((a + 360.0) - b).abs();
@@ -1508,6 +3983,14 @@ impl Game {
                             .to_degrees()
                             + 90.0
                     }
+                    AngleSetter::RotateToVelocity => {
+                        let velocity = self.objects[name].active_motion.velocity();
+                        if velocity == Vec2::zero() {
+                            self.objects[name].angle
+                        } else {
+                            velocity.y.atan2(velocity.x).to_degrees() + 90.0
+                        }
+                    }
                     AngleSetter::RotateToMouse => {
                         let centre = self.objects[name].origin_in_world();
                         let error = 0.00001;
@@ -1528,6 +4011,13 @@ impl Game {
                 self.objects[name].sprite = sprite.clone();
                 self.objects[name].animation = AnimationStatus::None;
             }
+            Action::SwapSprite { name: other_name } => {
+                let other_sprite = self.objects.get_obj(other_name)?.sprite.clone();
+                let sprite = std::mem::replace(&mut self.objects[name].sprite, other_sprite);
+                self.objects[other_name].sprite = sprite;
+                self.objects[name].animation = AnimationStatus::None;
+                self.objects[other_name].animation = AnimationStatus::None;
+            }
             Action::SetProperty(PropertySetter::Size(size_setter)) => {
                 let old_size = self.objects[name].size;
                 self.objects[name].size = match size_setter {
@@ -1568,20 +4058,17 @@ impl Game {
                 let size = self.objects[name].size;
                 let difference =
                     Size::new(size.width / old_size.width, size.height / old_size.height);
-                match &mut self.objects[name].collision_area {
-                    Some(area) => {
-                        *area = AABB {
-                            min: Vec2::new(
-                                area.min.x * difference.width,
-                                area.min.y * difference.height,
-                            ),
-                            max: Vec2::new(
-                                area.max.x * difference.width,
-                                area.max.y * difference.width,
-                            ),
-                        };
-                    }
-                    None => {}
+                if let Some(area) = &mut self.objects[name].collision_area {
+                    *area = AABB {
+                        min: Vec2::new(
+                            area.min.x * difference.width,
+                            area.min.y * difference.height,
+                        ),
+                        max: Vec2::new(
+                            area.max.x * difference.width,
+                            area.max.y * difference.height,
+                        ),
+                    };
                 }
             }
             Action::SetProperty(PropertySetter::Switch(switch)) => {
@@ -1611,7 +4098,7 @@ impl Game {
                     LayerSetter::Value(value) => *value,
                     LayerSetter::Increase => {
                         self.objects[name].layer
-                            + if self.objects[name].layer < std::u8::MAX - 1 {
+                            + if self.objects[name].layer < u8::MAX - 1 {
                                 1
                             } else {
                                 0
@@ -1622,20 +4109,280 @@ impl Game {
                     }
                 };
             }
+            Action::SetProperty(PropertySetter::Opacity(alpha)) => {
+                self.objects[name].alpha = alpha.clamp(0.0, 1.0);
+            }
+            Action::SetProperty(PropertySetter::Tint(colour)) => {
+                self.objects[name].tint = *colour;
+            }
+            Action::AdjustLives(lives) => {
+                self.lives_adjustment += lives;
+            }
+            Action::AdjustScore(score) => {
+                self.score_adjustment += score;
+            }
+            Action::Damage(amount) => {
+                // A mistake in the game's content shouldn't end the whole run
+                if !self.objects[name].damage(*amount) {
+                    macroquad::logging::warn!("Can't damage {} as it has no health", name);
+                }
+            }
+            Action::Blink { frames, interval } => {
+                self.objects[name].blink = if *frames == 0 {
+                    None
+                } else {
+                    Some(Blink {
+                        frames_left: *frames,
+                        interval: *interval,
+                        elapsed: 0,
+                    })
+                };
+            }
+            Action::SetProperty(PropertySetter::AngularVelocity(angular_velocity)) => {
+                self.objects[name].angular_velocity = *angular_velocity;
+            }
+            Action::SetProperty(PropertySetter::CopySize { name: other_name }) => {
+                let size = self.objects.get_obj(other_name)?.size;
+                let action = Action::SetProperty(PropertySetter::Size(SizeSetter::Value(size)));
+                return self.apply_action(name, &action, mouse, keyboard, gamepad, played_sounds);
+            }
+            Action::SetProperty(PropertySetter::CopySprite { name: other_name }) => {
+                let sprite = self.objects.get_obj(other_name)?.sprite.clone();
+                let action = Action::SetProperty(PropertySetter::Sprite(sprite));
+                return self.apply_action(name, &action, mouse, keyboard, gamepad, played_sounds);
+            }
+            Action::SetProperty(PropertySetter::CopyPosition {
+                name: other_name,
+                offset,
+            }) => {
+                self.objects[name].position = self.objects.get_obj(other_name)?.position + *offset;
+            }
+            Action::SetProperty(PropertySetter::Variable { name: variable, op }) => {
+                let value = self.objects[name]
+                    .variables
+                    .entry(variable.clone())
+                    .or_insert(0);
+                match op {
+                    VarOp::Set(new_value) => *value = *new_value,
+                    VarOp::Add(amount) => *value = value.saturating_add(*amount),
+                }
+            }
             Action::Random { random_actions } => {
                 let action = random_actions.choose();
                 if let Some(action) = action {
-                    return self.apply_action(name, &action, mouse, played_sounds);
+                    return self.apply_action(
+                        name,
+                        action,
+                        mouse,
+                        keyboard,
+                        gamepad,
+                        played_sounds,
+                    );
+                }
+            }
+            Action::If {
+                check,
+                then,
+                otherwise,
+            } => {
+                self.refresh_grid();
+                let branch = if self.is_triggered(name, check, mouse, keyboard, gamepad, 0)? {
+                    then
+                } else {
+                    otherwise
+                };
+                for action in branch {
+                    self.apply_action(name, action, mouse, keyboard, gamepad, played_sounds)?;
+                }
+            }
+            Action::Sequence { steps } => {
+                for (frames, action) in steps {
+                    if *frames == 0 {
+                        self.apply_action(name, action, mouse, keyboard, gamepad, played_sounds)?;
+                    } else {
+                        self.objects[name].queued_actions.push(QueuedAction {
+                            frames_left: *frames,
+                            action: action.clone(),
+                        });
+                    }
+                }
+            }
+            Action::Repeat {
+                count,
+                every,
+                action,
+            } => {
+                for i in 0..*count {
+                    let frames = i * every;
+                    if frames == 0 {
+                        self.apply_action(name, action, mouse, keyboard, gamepad, played_sounds)?;
+                    } else {
+                        self.objects[name].queued_actions.push(QueuedAction {
+                            frames_left: frames,
+                            action: *action.clone(),
+                        });
+                    }
                 }
             }
             Action::EndEarly => {
                 self.end_early = true;
             }
+            Action::Spawn {
+                template,
+                position,
+                name_prefix,
+            } => {
+                let object = self.templates.get_obj(template)?.clone().into_object();
+                let mut spawned_name = format!("{}{}", name_prefix, self.spawn_count);
+                while self.objects.contains_key(&spawned_name) {
+                    self.spawn_count += 1;
+                    spawned_name = format!("{}{}", name_prefix, self.spawn_count);
+                }
+                self.spawn_count += 1;
+                self.objects.insert(spawned_name.clone(), object);
+                self.jump_to(&spawned_name, position, mouse)?;
+                let bounding_box = self.objects[&spawned_name].bounding_box();
+                self.grid.insert(&spawned_name, bounding_box);
+            }
+            Action::ScreenShake { intensity, frames } => {
+                self.shake = ScreenShake {
+                    intensity: *intensity,
+                    frames: *frames,
+                    remaining: *frames,
+                };
+            }
+            Action::Destroy => {
+                self.destroyed.insert(name.to_string());
+            }
+            Action::DestroyNamed { name: other_name } => {
+                self.objects.get_obj(other_name)?;
+                self.destroyed.insert(other_name.clone());
+            }
         };
 
         Ok(())
     }
 
+    fn jump_to(
+        &mut self,
+        name: &str,
+        jump_location: &JumpLocation,
+        mouse: &Mouse,
+    ) -> WeeResult<()> {
+        fn gen_in_area(area: AABB) -> Vec2 {
+            Vec2::new(
+                gen_in_range(area.min.x, area.max.x),
+                gen_in_range(area.min.y, area.max.y),
+            )
+        }
+        match jump_location {
+            JumpLocation::Point(point) => {
+                self.objects[name].position = *point;
+            }
+            JumpLocation::Relative { to, distance } => match to {
+                RelativeTo::CurrentPosition => {
+                    self.objects[name].position += *distance;
+                }
+                RelativeTo::CurrentAngle => {
+                    let angle = self.objects[name].trig_angle();
+                    self.objects[name].position.x +=
+                        -distance.y * angle.cos() - distance.x * angle.sin();
+                    self.objects[name].position.y +=
+                        -distance.y * angle.sin() + distance.x * angle.cos();
+                }
+            },
+            JumpLocation::Area(area) => {
+                self.objects[name].position = gen_in_area(*area);
+            }
+            JumpLocation::RandomInAreaAvoiding {
+                area,
+                avoid,
+                min_gap,
+            } => {
+                const MAX_TRIES: u32 = 20;
+                let mut obstacles = Vec::new();
+                for other_name in avoid {
+                    obstacles.push(self.objects.get_obj(other_name)?.position);
+                }
+                let mut position = gen_in_area(*area);
+                for _ in 1..MAX_TRIES {
+                    let is_clear = obstacles
+                        .iter()
+                        .all(|obstacle| (position - *obstacle).magnitude() >= *min_gap);
+                    if is_clear {
+                        break;
+                    }
+                    position = gen_in_area(*area);
+                }
+                self.objects[name].position = position;
+            }
+            JumpLocation::ClampPosition { .. } => {
+                //clamp_position(&mut self.objects[name].position, *area);
+            }
+            JumpLocation::Object { name: other_name } => {
+                self.objects[name].position = self.objects.get_obj(other_name)?.position;
+            }
+            JumpLocation::ObjectAnchor {
+                name: other_name,
+                anchor,
+                offset,
+            } => {
+                let other_obj = self.objects.get_obj(other_name)?;
+                let (top_left, bottom_right) = (other_obj.top_left(), other_obj.bottom_right());
+                let centre = other_obj.position;
+                let half_width = self.objects[name].half_width();
+                let half_height = self.objects[name].half_height();
+                let left = top_left.x - half_width;
+                let right = bottom_right.x + half_width;
+                let top = top_left.y - half_height;
+                let bottom = bottom_right.y + half_height;
+                let position = match anchor {
+                    Anchor::Top => Vec2::new(centre.x, top),
+                    Anchor::Bottom => Vec2::new(centre.x, bottom),
+                    Anchor::Left => Vec2::new(left, centre.y),
+                    Anchor::Right => Vec2::new(right, centre.y),
+                    Anchor::TopLeft => Vec2::new(left, top),
+                    Anchor::TopRight => Vec2::new(right, top),
+                    Anchor::BottomLeft => Vec2::new(left, bottom),
+                    Anchor::BottomRight => Vec2::new(right, bottom),
+                };
+                self.objects[name].position = position + *offset;
+            }
+            JumpLocation::Mouse => {
+                self.objects[name].position = mouse.position;
+            }
+            JumpLocation::Grid { origin, cell } => {
+                let position = self.objects[name].position;
+                let snap = |value: f32, origin: f32, size: f32| {
+                    if size > 0.0 {
+                        origin + ((value - origin) / size).round() * size
+                    } else {
+                        value
+                    }
+                };
+                self.objects[name].position = Vec2::new(
+                    snap(position.x, origin.x, cell.width),
+                    snap(position.y, origin.y, cell.height),
+                );
+            }
+            JumpLocation::SnapToNearest { points } => {
+                let position = self.objects[name].position;
+                let mut closest: Option<(Vec2, f32)> = None;
+                for point in points {
+                    let distance = (*point - position).magnitude();
+                    if closest.map(|(_, d)| distance < d).unwrap_or(true) {
+                        closest = Some((*point, distance));
+                    }
+                }
+                if let Some((point, _)) = closest {
+                    self.objects[name].position = point;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn move_object(&mut self, name: &str, mouse: &Mouse) -> WeeResult<()> {
         let mut clamps = Vec::new();
         for mut motion in self.objects[name].queued_motion.clone().into_iter() {
@@ -1652,42 +4399,7 @@ impl Game {
                     ActiveMotion::GoStraight { velocity }
                 }
                 Motion::JumpTo(jump_location) => {
-                    match jump_location {
-                        JumpLocation::Point(point) => {
-                            self.objects[name].position = *point;
-                        }
-                        JumpLocation::Relative { to, distance } => match to {
-                            RelativeTo::CurrentPosition => {
-                                self.objects[name].position += *distance;
-                            }
-                            RelativeTo::CurrentAngle => {
-                                let angle = self.objects[name].trig_angle();
-                                self.objects[name].position.x +=
-                                    -distance.y * angle.cos() - distance.x * angle.sin();
-                                self.objects[name].position.y +=
-                                    -distance.y * angle.sin() + distance.x * angle.cos();
-                            }
-                        },
-                        JumpLocation::Area(area) => {
-                            fn gen_in_area(area: AABB) -> Vec2 {
-                                Vec2::new(
-                                    gen_in_range(area.min.x, area.max.x),
-                                    gen_in_range(area.min.y, area.max.y),
-                                )
-                            }
-                            self.objects[name].position = gen_in_area(*area);
-                        }
-                        JumpLocation::ClampPosition { .. } => {
-                            //clamp_position(&mut self.objects[name].position, *area);
-                        }
-                        JumpLocation::Object { name: other_name } => {
-                            self.objects[name].position =
-                                self.objects.get_obj(&other_name)?.position;
-                        }
-                        JumpLocation::Mouse => {
-                            self.objects[name].position = mouse.position;
-                        }
-                    }
+                    self.jump_to(name, jump_location, mouse)?;
                     if let Motion::JumpTo(JumpLocation::ClampPosition { area }) = motion {
                         clamps.push(area);
                         self.objects[name].active_motion.clone()
@@ -1697,9 +4409,10 @@ impl Game {
                 }
                 Motion::Roam {
                     movement_type,
-                    area,
+                    area: roam_area,
                     speed,
                 } => {
+                    let area = &roam_area.bounds();
                     let active_roam = match movement_type {
                         MovementType::Wiggle => ActiveRoam::Wiggle,
                         MovementType::Reflect {
@@ -1783,16 +4496,18 @@ impl Game {
                     };
                     ActiveMotion::Roam {
                         movement_type: active_roam,
-                        area: *area,
+                        area: roam_area.clone(),
                         speed: *speed,
                     }
                 }
                 Motion::Swap { name: other_name } => {
                     let other_name = &*other_name;
-                    self.objects.get_obj(&other_name)?;
+                    self.objects.get_obj(other_name)?;
                     let temp = self.objects[other_name].position;
                     self.objects[other_name].position = self.objects[name].position;
                     self.objects[name].position = temp;
+                    let bounding_box = self.objects[other_name].bounding_box();
+                    self.grid.update(other_name, bounding_box);
                     ActiveMotion::Stop
                 }
                 Motion::Target {
@@ -1800,47 +4515,26 @@ impl Game {
                     target_type,
                     offset,
                     speed,
+                    easing,
                 } => ActiveMotion::Target {
                     target: target.clone(),
                     target_type: *target_type,
                     offset: *offset,
                     speed: *speed,
+                    easing: *easing,
+                    travelled: 0.0,
                 },
                 Motion::Accelerate(Acceleration::Continuous { direction, speed }) => {
                     let speed = Speed::Value(speed.as_value() / 40.0);
                     let acceleration = direction.to_vector(&self.objects[name], speed);
-                    let velocity = match &self.objects[name].active_motion {
-                        ActiveMotion::Accelerate { velocity, .. } => *velocity,
-                        ActiveMotion::GoStraight { velocity } => *velocity,
-                        ActiveMotion::Roam { movement_type, .. } => match movement_type {
-                            ActiveRoam::Insect { velocity } => *velocity,
-                            ActiveRoam::Bounce { velocity, .. } => *velocity,
-                            ActiveRoam::Reflect { velocity, .. } => *velocity,
-                            _ => Vec2::zero(),
-                        },
-                        ActiveMotion::Target { .. } => Vec2::zero(),
-                        ActiveMotion::SlowDown { velocity, .. } => *velocity,
-                        ActiveMotion::Stop => Vec2::zero(),
-                    };
+                    let velocity = self.objects[name].active_motion.velocity();
                     ActiveMotion::Accelerate {
                         velocity,
                         acceleration,
                     }
                 }
                 Motion::Accelerate(Acceleration::SlowDown { speed }) => {
-                    let velocity = match &self.objects[name].active_motion {
-                        ActiveMotion::Accelerate { velocity, .. } => *velocity,
-                        ActiveMotion::GoStraight { velocity } => *velocity,
-                        ActiveMotion::Roam { movement_type, .. } => match movement_type {
-                            ActiveRoam::Insect { velocity } => *velocity,
-                            ActiveRoam::Bounce { velocity, .. } => *velocity,
-                            ActiveRoam::Reflect { velocity, .. } => *velocity,
-                            _ => Vec2::zero(),
-                        },
-                        ActiveMotion::Target { .. } => Vec2::zero(),
-                        ActiveMotion::SlowDown { velocity, .. } => *velocity,
-                        ActiveMotion::Stop => Vec2::zero(),
-                    };
+                    let velocity = self.objects[name].active_motion.velocity();
                     if velocity.x == 0.0 && velocity.y == 0.0 {
                         ActiveMotion::Stop
                     } else {
@@ -1851,19 +4545,98 @@ impl Game {
                         }
                     }
                 }
-                Motion::Stop => ActiveMotion::Stop,
-            };
-        }
-        self.objects[name].queued_motion = Vec::new();
-
-        self.objects[name].active_motion = self.update_active_motion(name, mouse)?;
-
-        for area in clamps {
-            clamp_position(&mut self.objects[name].position, area);
-            self.objects[name].active_motion = ActiveMotion::Stop;
-        }
-
-        Ok(())
+                Motion::Orbit {
+                    center,
+                    radius,
+                    speed,
+                    clockwise,
+                } => {
+                    let centre = match center {
+                        Target::Object { name: other_name } => {
+                            self.objects.get_obj(other_name)?.position
+                        }
+                        Target::Mouse => mouse.position,
+                    };
+                    let offset = self.objects[name].position - centre;
+                    let angle = if offset.magnitude() == 0.0 {
+                        0.0
+                    } else {
+                        offset.y.atan2(offset.x).to_degrees()
+                    };
+                    ActiveMotion::Orbit {
+                        center: center.clone(),
+                        radius: *radius,
+                        speed: *speed,
+                        clockwise: *clockwise,
+                        angle,
+                    }
+                }
+                Motion::ApplyGravity {
+                    strength,
+                    terminal_velocity,
+                } => ActiveMotion::Gravity {
+                    velocity: self.objects[name].active_motion.velocity(),
+                    strength: *strength,
+                    terminal_velocity: *terminal_velocity,
+                },
+                Motion::FollowPath {
+                    points,
+                    speed,
+                    loop_path,
+                } => {
+                    if points.is_empty() {
+                        self.objects[name].active_motion.clone()
+                    } else {
+                        ActiveMotion::FollowPath {
+                            points: points.clone(),
+                            speed: *speed,
+                            loop_path: *loop_path,
+                            index: 0,
+                        }
+                    }
+                }
+                Motion::Wave {
+                    axis,
+                    amplitude,
+                    period_frames,
+                    speed,
+                } => ActiveMotion::Wave {
+                    axis: *axis,
+                    amplitude: *amplitude,
+                    period_frames: *period_frames,
+                    speed: *speed,
+                    frame: 0,
+                },
+                Motion::Attract {
+                    target,
+                    strength,
+                    falloff,
+                } => ActiveMotion::Attract {
+                    target: target.clone(),
+                    strength: *strength,
+                    falloff: *falloff,
+                    velocity: self.objects[name].active_motion.velocity(),
+                },
+                Motion::SetVelocity(velocity) => ActiveMotion::GoStraight {
+                    velocity: *velocity,
+                },
+                Motion::Stop => ActiveMotion::Stop,
+            };
+        }
+        self.objects[name].queued_motion = Vec::new();
+
+        self.objects[name].active_motion = self.update_active_motion(name, mouse)?;
+
+        for area in clamps {
+            clamp_position(&mut self.objects[name].position, area);
+            self.objects[name].active_motion = ActiveMotion::Stop;
+        }
+
+        if self.objects[name].keep_on_screen {
+            self.objects[name].move_on_screen();
+        }
+
+        Ok(())
     }
 
     fn update_active_motion(&mut self, name: &str, mouse: &Mouse) -> WeeResult<ActiveMotion> {
@@ -1874,10 +4647,12 @@ impl Game {
             }
             ActiveMotion::Roam {
                 movement_type,
-                area,
+                area: roam_area,
                 speed,
             } => {
-                let movement_type = match movement_type {
+                let previous_position = self.objects[name].position;
+                let area = roam_area.bounds();
+                let mut movement_type = match movement_type {
                     ActiveRoam::Wiggle => {
                         self.objects[name].position += random_velocity(speed);
                         clamp_position(&mut self.objects[name].position, area);
@@ -1900,33 +4675,13 @@ impl Game {
                         movement_handling,
                     } => {
                         if let MovementHandling::TryNotToOverlap = movement_handling {
-                            fn calculate_closest_manifold<T: BasicShape>(
-                                objects: &Objects,
-                                name: &str,
-                                poly: T,
-                            ) -> (Option<c2::Manifold>, Vec2) {
-                                let mut longest_depth = 0.0;
-                                let mut closest_manifold = None;
-                                let mut position = Vec2::zero();
-                                for other_name in objects.keys() {
-                                    if other_name != name {
-                                        let manifold = poly.manifold(&objects[other_name].poly());
-                                        if manifold.count() > 0 {
-                                            let depth = manifold.depths()[0];
-                                            if depth > longest_depth || closest_manifold.is_none() {
-                                                closest_manifold = Some(manifold);
-                                                position = objects[other_name].position;
-                                                longest_depth = depth;
-                                            }
-                                        }
-                                    }
-                                }
-                                (closest_manifold, position)
-                            }
-                            let (original_manifold, other_position) = calculate_closest_manifold(
+                            let bounding_box = self.objects[name].bounding_box();
+                            let (original_manifold, other_position) = closest_manifold(
                                 &self.objects,
+                                &self.grid,
                                 name,
-                                self.objects[name].poly(),
+                                self.objects[name].shape(),
+                                bounding_box,
                             );
                             let move_away = |manifold: Option<c2::Manifold>| {
                                 if let Some(manifold) = manifold {
@@ -1940,16 +4695,18 @@ impl Game {
                             self.objects[name].position -= move_away(original_manifold);
 
                             let closest_manifold = {
-                                let moved_poly = {
-                                    let transformation = c2::Transformation::new(
-                                        [velocity.x, velocity.y],
-                                        c2::Rotation::zero(),
-                                    );
-                                    (self.objects[name].poly(), transformation)
-                                };
-
-                                let (new_manifold, _) =
-                                    calculate_closest_manifold(&self.objects, name, moved_poly);
+                                let moved_shape = self.objects[name].shape().moved_by(velocity);
+                                let bounding_box = self.objects[name].bounding_box();
+                                let moved_area =
+                                    bounding_box.union(bounding_box.move_position(velocity));
+
+                                let (new_manifold, _) = closest_manifold(
+                                    &self.objects,
+                                    &self.grid,
+                                    name,
+                                    moved_shape,
+                                    moved_area,
+                                );
 
                                 let is_moving_towards = {
                                     let to_point = other_position - self.objects[name].position;
@@ -1967,7 +4724,12 @@ impl Game {
                                 let len = (normal.x().powf(2.0) + normal.y().powf(2.0)).sqrt();
                                 if len != 0.0 {
                                     let normal = Vec2::new(normal.x() / len, normal.y() / len);
-                                    velocity = velocity - 2.0 * (velocity * normal) * normal;
+                                    // Only bounce when heading into the surface, otherwise
+                                    // a ball that's already leaving would turn back around
+                                    let dot = velocity.x * normal.x + velocity.y * normal.y;
+                                    if dot > 0.0 {
+                                        velocity -= normal * (2.0 * dot);
+                                    }
                                 }
                             }
                         }
@@ -2031,9 +4793,22 @@ impl Game {
                         }
                     }
                 };
+                if let RoamArea::Polygon(polygon) = &roam_area {
+                    let position = self.objects[name].position;
+                    if is_point_in_polygon(previous_position, polygon)
+                        && !is_point_in_polygon(position, polygon)
+                    {
+                        self.objects[name].position = previous_position;
+                        if let Some(normal) =
+                            crossed_edge_normal(previous_position, position, polygon)
+                        {
+                            movement_type.reflect(normal);
+                        }
+                    }
+                }
                 ActiveMotion::Roam {
                     movement_type,
-                    area,
+                    area: roam_area,
                     speed,
                 }
             }
@@ -2042,7 +4817,10 @@ impl Game {
                 target_type,
                 offset,
                 speed,
+                easing,
+                mut travelled,
             } => {
+                let previous_position = self.objects[name].position;
                 self.objects[name].position = {
                     let other = match &target {
                         Target::Object { name: other_name } => {
@@ -2051,8 +4829,8 @@ impl Game {
                         Target::Mouse => mouse.position,
                     };
                     let target_vector = other + offset - self.objects[name].position;
-                    let target_vector = target_vector
-                        / (target_vector.x.powf(2.0) + target_vector.y.powf(2.0)).sqrt();
+                    let remaining = (target_vector.x.powf(2.0) + target_vector.y.powf(2.0)).sqrt();
+                    let target_vector = target_vector / remaining;
                     let move_to = |x: f32, other: f32, velocity: f32| {
                         if (x - other).abs() > velocity.abs() {
                             x + velocity
@@ -2060,7 +4838,8 @@ impl Game {
                             other
                         }
                     };
-                    let velocity: Vec2 = target_vector * speed.as_value();
+                    let velocity: Vec2 =
+                        target_vector * easing.step(speed.as_value(), travelled, remaining);
 
                     Vec2::new(
                         move_to(
@@ -2075,6 +4854,7 @@ impl Game {
                         ),
                     )
                 };
+                travelled += (self.objects[name].position - previous_position).magnitude();
 
                 if let TargetType::StopWhenReached = target_type {
                     let other = match &target {
@@ -2095,6 +4875,8 @@ impl Game {
                             target_type,
                             offset,
                             speed,
+                            easing,
+                            travelled,
                         }
                     }
                 } else {
@@ -2103,6 +4885,8 @@ impl Game {
                         target_type,
                         offset,
                         speed,
+                        easing,
+                        travelled,
                     }
                 }
             }
@@ -2132,9 +4916,2874 @@ impl Game {
                     }
                 }
             }
+            ActiveMotion::Orbit {
+                center,
+                radius,
+                speed,
+                clockwise,
+                mut angle,
+            } => {
+                let centre = match &center {
+                    Target::Object { name: other_name } => {
+                        self.objects.get_obj(other_name)?.position
+                    }
+                    Target::Mouse => mouse.position,
+                };
+                if clockwise {
+                    angle += speed.as_value();
+                } else {
+                    angle -= speed.as_value();
+                }
+                let radians = angle.to_radians();
+                self.objects[name].position =
+                    centre + Vec2::new(radius * radians.cos(), radius * radians.sin());
+                ActiveMotion::Orbit {
+                    center,
+                    radius,
+                    speed,
+                    clockwise,
+                    angle,
+                }
+            }
+            ActiveMotion::Gravity {
+                mut velocity,
+                strength,
+                terminal_velocity,
+            } => {
+                velocity.y += strength;
+                if let Some(terminal_velocity) = terminal_velocity {
+                    velocity.y = velocity.y.min(terminal_velocity);
+                }
+                self.objects[name].position += velocity;
+                ActiveMotion::Gravity {
+                    velocity,
+                    strength,
+                    terminal_velocity,
+                }
+            }
+            ActiveMotion::FollowPath {
+                points,
+                speed,
+                loop_path,
+                mut index,
+            } => {
+                let to_point = points[index] - self.objects[name].position;
+                if to_point.magnitude() <= speed.as_value() {
+                    self.objects[name].position = points[index];
+                    index += 1;
+                    if index < points.len() || loop_path {
+                        ActiveMotion::FollowPath {
+                            index: index % points.len(),
+                            points,
+                            speed,
+                            loop_path,
+                        }
+                    } else {
+                        ActiveMotion::Stop
+                    }
+                } else {
+                    self.objects[name].position += to_point.unit() * speed.as_value();
+                    ActiveMotion::FollowPath {
+                        points,
+                        speed,
+                        loop_path,
+                        index,
+                    }
+                }
+            }
+            ActiveMotion::Wave {
+                axis,
+                amplitude,
+                period_frames,
+                speed,
+                frame,
+            } => {
+                let offset = wave_offset(amplitude, period_frames, frame + 1)
+                    - wave_offset(amplitude, period_frames, frame);
+                let perpendicular = match axis {
+                    Axis::Horizontal => Vec2::new(0.0, offset),
+                    Axis::Vertical => Vec2::new(offset, 0.0),
+                };
+                let velocity = self.objects[name].active_motion.velocity();
+                self.objects[name].position += velocity + perpendicular;
+                ActiveMotion::Wave {
+                    axis,
+                    amplitude,
+                    period_frames,
+                    speed,
+                    frame: (frame + 1) % period_frames.max(1),
+                }
+            }
+            ActiveMotion::Attract {
+                target,
+                strength,
+                falloff,
+                mut velocity,
+            } => {
+                let other = match &target {
+                    Target::Object { name: other_name } => {
+                        self.objects.get_obj(other_name)?.position
+                    }
+                    Target::Mouse => mouse.position,
+                };
+                let to_target = other - self.objects[name].position;
+                let distance = to_target.magnitude();
+                if distance > 0.0 {
+                    velocity += to_target / distance * falloff.pull(strength, distance);
+                }
+                self.objects[name].position += velocity;
+                ActiveMotion::Attract {
+                    target,
+                    strength,
+                    falloff,
+                    velocity,
+                }
+            }
             ActiveMotion::Stop => ActiveMotion::Stop,
         };
 
         Ok(active_motion)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press_at(x: f32, y: f32) -> Mouse {
+        Mouse {
+            position: Vec2::new(x, y),
+            state: ButtonState::Press,
+        }
+    }
+
+    fn has_won(status: WinStatus) -> bool {
+        matches!(status, WinStatus::Won | WinStatus::HasBeenWon)
+    }
+
+    const CLICK_TO_WIN: &str = r#"{
+        "format_version": "0.2",
+        "published": true,
+        "game_type": "Minigame",
+        "objects": [
+            {
+                "name": "Button",
+                "sprite": { "Colour": { "r": 1.0, "g": 0.0, "b": 0.0, "a": 1.0 } },
+                "position": { "x": 800.0, "y": 450.0 },
+                "size": { "width": 100.0, "height": 100.0 },
+                "angle": 0.0,
+                "origin": null,
+                "collision_area": null,
+                "flip": { "horizontal": false, "vertical": false },
+                "layer": 0,
+                "switch": "Off",
+                "instructions": [
+                    {
+                        "triggers": [
+                            {
+                                "Input": {
+                                    "Mouse": {
+                                        "over": { "Object": { "name": "Button" } },
+                                        "interaction": { "Button": { "state": "Press" } }
+                                    }
+                                }
+                            }
+                        ],
+                        "actions": ["Win"]
+                    }
+                ]
+            }
+        ],
+        "background": [],
+        "asset_files": { "images": {}, "audio": {}, "fonts": {} },
+        "length": { "Seconds": 4.0 },
+        "intro_text": "Click!",
+        "attribution": ""
+    }"#;
+
+    #[test]
+    fn json_game_is_won_by_clicking() -> WeeResult<()> {
+        let game_data = GameData::from_json(CLICK_TO_WIN)?;
+        let mut game = Game::from_data(game_data);
+
+        let inputs = vec![press_at(0.0, 0.0), press_at(800.0, 450.0)];
+        let (status, _) = game.run_headless(inputs.into_iter(), 240)?;
+
+        assert!(has_won(status));
+        Ok(())
+    }
+
+    #[test]
+    fn json_game_is_not_won_by_clicking_elsewhere() -> WeeResult<()> {
+        let game_data = GameData::from_json(CLICK_TO_WIN)?;
+        let mut game = Game::from_data(game_data);
+
+        let inputs = vec![press_at(100.0, 100.0)];
+        let (status, _) = game.run_headless(inputs.into_iter(), 240)?;
+
+        assert!(!has_won(status));
+        Ok(())
+    }
+
+    fn click_to_win_button() -> SerialiseObject {
+        SerialiseObject::builder()
+            .name("Button")
+            .position(Vec2::new(800.0, 450.0))
+            .size(Size::new(100.0, 100.0))
+            .instruction(Instruction {
+                triggers: vec![Trigger::Input(Input::Mouse {
+                    over: MouseOver::Object {
+                        name: "Button".to_string(),
+                    },
+                    interaction: MouseInteraction::Button {
+                        state: ButtonState::Press,
+                    },
+                })],
+                actions: vec![Action::Win],
+                cooldown: 0,
+            })
+            .build()
+    }
+
+    #[test]
+    fn builder_game_is_winnable_headlessly() -> WeeResult<()> {
+        let game_data = GameData::builder()
+            .object(click_to_win_button())
+            .length(Length::Seconds(4.0))
+            .intro_text("Click!")
+            .build();
+        let mut game = Game::from_data(game_data);
+
+        let inputs = vec![press_at(800.0, 450.0)];
+        let (status, _) = game.run_headless(inputs.into_iter(), 240)?;
+
+        assert!(has_won(status));
+        Ok(())
+    }
+
+    #[test]
+    fn builder_game_runs_out_of_time_without_input() -> WeeResult<()> {
+        let game_data = GameData::builder()
+            .object(click_to_win_button())
+            .length(Length::Seconds(1.0))
+            .build();
+        let mut game = Game::from_data(game_data);
+
+        let (status, _) = game.run_headless(std::iter::empty(), 240)?;
+
+        assert!(!has_won(status));
+        assert_eq!(game.frames.remaining(), FrameCount::Frames(0));
+        Ok(())
+    }
+
+    fn click_to_win_with_version(version: &str) -> String {
+        CLICK_TO_WIN.replace(
+            r#""format_version": "0.2""#,
+            &format!(r#""format_version": "{}""#, version),
+        )
+    }
+
+    // A made up change where 0.1 called the intro text "intro"
+    fn rename_intro(mut value: serde_json::Value) -> serde_json::Value {
+        if let Some(object) = value.as_object_mut() {
+            if let Some(intro) = object.remove("intro") {
+                object.insert("intro_text".to_string(), intro);
+            }
+            object.insert("format_version".to_string(), "0.2".into());
+        }
+        value
+    }
+
+    #[test]
+    fn migrations_upgrade_a_0_1_document_to_0_2() -> WeeResult<()> {
+        let value: serde_json::Value =
+            json_from_str(r#"{ "format_version": "0.1", "intro": "Click!" }"#)?;
+
+        let migrated = run_migrations(value, "0.1", &[("0.1", "0.2", rename_intro)]);
+
+        let expected: serde_json::Value =
+            json_from_str(r#"{ "format_version": "0.2", "intro_text": "Click!" }"#)?;
+        assert_eq!(migrated, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn documents_without_a_migration_load_unchanged() -> WeeResult<()> {
+        let game_data = GameData::from_json(&click_to_win_with_version("0.1"))?;
+
+        assert_eq!(game_data.format_version, "0.1");
+        assert_eq!(
+            game_data.objects,
+            GameData::from_json(CLICK_TO_WIN)?.objects
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_newer_format_version() {
+        let error = GameData::from_json(&click_to_win_with_version("9.0")).unwrap_err();
+
+        assert!(error
+            .to_string()
+            .contains("newer than the supported version"));
+    }
+
+    #[test]
+    fn rejects_invalid_format_version() {
+        let error = GameData::from_json(&click_to_win_with_version("latest")).unwrap_err();
+
+        assert!(error.to_string().contains("Invalid game format version"));
+    }
+
+    #[test]
+    fn destroyed_objects_do_not_act_later_in_the_frame() -> WeeResult<()> {
+        let destroyer = SerialiseObject::builder()
+            .name("Destroyer")
+            .instruction(Instruction {
+                triggers: vec![Trigger::Time(When::Start)],
+                actions: vec![Action::DestroyNamed {
+                    name: "Winner".to_string(),
+                }],
+                cooldown: 0,
+            })
+            .build();
+        let winner = SerialiseObject::builder()
+            .name("Winner")
+            .instruction(Instruction {
+                triggers: vec![Trigger::Time(When::Start)],
+                actions: vec![Action::Win],
+                cooldown: 0,
+            })
+            .build();
+        let game_data = GameData::builder()
+            .object(destroyer)
+            .object(winner)
+            .length(Length::Seconds(1.0))
+            .build();
+        let mut game = Game::from_data(game_data);
+
+        let (status, _) = game.run_headless(std::iter::empty(), 240)?;
+
+        assert!(!has_won(status));
+        assert!(!game.objects.contains_key("Winner"));
+        Ok(())
+    }
+
+    fn scattered_objects(count: usize) -> Objects {
+        let mut seed: u32 = 12345;
+        let mut next = move |max: f32| {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 8) as f32 / (1 << 24) as f32 * max
+        };
+        let objects = (0..count)
+            .map(|index| {
+                SerialiseObject::builder()
+                    .name(format!("Object {}", index))
+                    .position(Vec2::new(next(1600.0), next(900.0)))
+                    .size(Size::new(10.0 + next(150.0), 10.0 + next(150.0)))
+                    .solid(true)
+                    .build()
+            })
+            .collect();
+        Objects::from_serialised(objects)
+    }
+
+    fn every_object(objects: &Objects) -> SpatialGrid {
+        SpatialGrid {
+            large: objects.keys().cloned().collect(),
+            ..SpatialGrid::default()
+        }
+    }
+
+    #[test]
+    fn grid_finds_the_same_overlaps_as_brute_force() {
+        let mut objects = scattered_objects(200);
+        let mut grid = SpatialGrid::from_objects(&objects);
+        for (index, object) in objects.values_mut().enumerate().filter(|(i, _)| i % 3 == 0) {
+            object.position += Vec2::new(index as f32 * 7.0 % 400.0 - 200.0, 150.0);
+        }
+        for (name, object) in objects.iter() {
+            grid.update(name, object.bounding_box());
+        }
+        let brute_force = every_object(&objects);
+
+        for (name, object) in objects.iter() {
+            let area = object.bounding_box();
+            let overlapping: HashSet<&str> = objects
+                .iter()
+                .filter(|(_, other)| other.bounding_box().overlaps(area))
+                .map(|(other_name, _)| other_name.as_str())
+                .collect();
+            assert!(grid.query(area).is_superset(&overlapping));
+
+            let from_grid = closest_manifold(&objects, &grid, name, object.shape(), area);
+            let expected = closest_manifold(&objects, &brute_force, name, object.shape(), area);
+            assert_eq!(from_grid.1, expected.1);
+            assert_eq!(
+                from_grid.0.map(|manifold| manifold.depths()[0]),
+                expected.0.map(|manifold| manifold.depths()[0])
+            );
+        }
+    }
+
+    #[test]
+    fn destroyed_fires_once_when_health_runs_out() -> WeeResult<()> {
+        let always = || Trigger::Not {
+            trigger: Box::new(Trigger::Time(When::End)),
+        };
+        let target = SerialiseObject::builder()
+            .name("Target")
+            .health(Health {
+                amount: 3,
+                stages: Vec::new(),
+            })
+            .instruction(Instruction {
+                triggers: vec![always()],
+                actions: vec![Action::Damage(1)],
+                cooldown: 0,
+            })
+            .instruction(Instruction {
+                triggers: vec![Trigger::Destroyed],
+                actions: vec![Action::AdjustScore(1)],
+                cooldown: 0,
+            })
+            .build();
+        let game_data = GameData::builder()
+            .object(target)
+            .length(Length::Seconds(1.0))
+            .build();
+        let mut game = Game::from_data(game_data);
+
+        game.run_headless(std::iter::empty(), 240)?;
+
+        assert_eq!(game.objects["Target"].health(), Some(0));
+        assert_eq!(game.score_adjustment, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn attenuated_sounds_are_panned_by_position() -> WeeResult<()> {
+        let speaker = |name: &str, x: f32| {
+            SerialiseObject::builder()
+                .name(name)
+                .position(Vec2::new(x, 450.0))
+                .instruction(Instruction {
+                    triggers: vec![Trigger::Time(When::Start)],
+                    actions: vec![Action::PlaySound {
+                        name: "beep".to_string(),
+                        attenuate: true,
+                    }],
+                    cooldown: 0,
+                })
+                .build()
+        };
+        let game_data = GameData::builder()
+            .object(speaker("Left", 0.0))
+            .object(speaker("Middle", PROJECTION_WIDTH / 2.0))
+            .object(speaker("Right", PROJECTION_WIDTH))
+            .length(Length::Seconds(1.0))
+            .build();
+        let mut game = Game::from_data(game_data);
+
+        let (_, sounds) = game.run_headless(std::iter::empty(), 1)?;
+
+        let pans: Vec<_> = sounds.iter().map(|sound| sound.pan).collect();
+        assert_eq!(pans, vec![Some(-1.0), Some(0.0), Some(1.0)]);
+        Ok(())
+    }
+
+    fn instruction(triggers: Vec<Trigger>, actions: Vec<Action>) -> Instruction {
+        Instruction {
+            triggers,
+            actions,
+            cooldown: 0,
+        }
+    }
+
+    fn game_of(objects: Vec<SerialiseObject>) -> Game {
+        let game_data = objects
+            .into_iter()
+            .fold(GameData::builder(), |builder, object| {
+                builder.object(object)
+            })
+            .length(Length::Seconds(4.0))
+            .build();
+        Game::from_data(game_data)
+    }
+
+    fn run_frames(game: &mut Game, frames: u32) -> WeeResult<WinStatus> {
+        Ok(game.run_headless(std::iter::empty(), frames)?.0)
+    }
+
+    fn wins_when_near(distance: f32) -> WeeResult<bool> {
+        let player = SerialiseObject::builder()
+            .name("Player")
+            .position(Vec2::new(400.0, 400.0))
+            .instruction(instruction(
+                vec![Trigger::Proximity {
+                    name: "Bomb".to_string(),
+                    distance,
+                }],
+                vec![Action::Win],
+            ))
+            .build();
+        let bomb = SerialiseObject::builder()
+            .name("Bomb")
+            .position(Vec2::new(500.0, 400.0))
+            .build();
+        let mut game = game_of(vec![player, bomb]);
+
+        Ok(has_won(run_frames(&mut game, 1)?))
+    }
+
+    #[test]
+    fn proximity_fires_within_distance() -> WeeResult<()> {
+        assert!(wins_when_near(150.0)?);
+        assert!(!wins_when_near(50.0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn proximity_to_missing_object_is_an_error() {
+        let player = SerialiseObject::builder()
+            .name("Player")
+            .instruction(instruction(
+                vec![Trigger::Proximity {
+                    name: "Nothing".to_string(),
+                    distance: 10.0,
+                }],
+                vec![Action::Win],
+            ))
+            .build();
+        let mut game = game_of(vec![player]);
+
+        assert!(run_frames(&mut game, 1).is_err());
+    }
+
+    fn clickable(name: &str, triggers: Vec<Trigger>) -> SerialiseObject {
+        SerialiseObject::builder()
+            .name(name)
+            .position(Vec2::new(800.0, 450.0))
+            .size(Size::new(100.0, 100.0))
+            .instruction(instruction(triggers, vec![Action::Win]))
+            .build()
+    }
+
+    fn clicked_or_timed_out() -> Trigger {
+        Trigger::AnyOf {
+            triggers: vec![
+                Trigger::Time(When::Exact { time: 100 }),
+                Trigger::Input(Input::Mouse {
+                    over: MouseOver::Object {
+                        name: "Button".to_string(),
+                    },
+                    interaction: MouseInteraction::Button {
+                        state: ButtonState::Press,
+                    },
+                }),
+            ],
+        }
+    }
+
+    #[test]
+    fn any_of_fires_on_either_trigger() -> WeeResult<()> {
+        let mut clicked = game_of(vec![clickable("Button", vec![clicked_or_timed_out()])]);
+        let (status, _) = clicked.run_headless(vec![press_at(800.0, 450.0)].into_iter(), 1)?;
+        assert!(has_won(status));
+
+        let mut timed_out = game_of(vec![clickable("Button", vec![clicked_or_timed_out()])]);
+        assert!(!has_won(run_frames(&mut timed_out, 100)?));
+        assert!(has_won(run_frames(&mut timed_out, 1)?));
+        Ok(())
+    }
+
+    #[test]
+    fn deeply_nested_any_of_is_an_error() {
+        let mut trigger = Trigger::Time(When::Start);
+        for _ in 0..=MAX_TRIGGER_DEPTH + 1 {
+            trigger = Trigger::AnyOf {
+                triggers: vec![trigger],
+            };
+        }
+        let mut game = game_of(vec![clickable("Button", vec![trigger])]);
+
+        assert!(run_frames(&mut game, 1).is_err());
+    }
+
+    fn wins_unless_lamp_on(lamp: Switch) -> WeeResult<bool> {
+        let lamp = SerialiseObject::builder().name("Lamp").switch(lamp).build();
+        let watcher = SerialiseObject::builder()
+            .name("Watcher")
+            .instruction(instruction(
+                vec![Trigger::Not {
+                    trigger: Box::new(Trigger::CheckProperty {
+                        name: "Lamp".to_string(),
+                        check: PropertyCheck::Switch(SwitchState::On),
+                    }),
+                }],
+                vec![Action::Win],
+            ))
+            .build();
+        let mut game = game_of(vec![lamp, watcher]);
+
+        Ok(has_won(run_frames(&mut game, 1)?))
+    }
+
+    #[test]
+    fn not_negates_switch_check() -> WeeResult<()> {
+        assert!(wins_unless_lamp_on(Switch::Off)?);
+        assert!(!wins_unless_lamp_on(Switch::On)?);
+        Ok(())
+    }
+
+    #[test]
+    fn not_random_time_fires_on_every_other_frame() -> WeeResult<()> {
+        let counter = SerialiseObject::builder()
+            .name("Counter")
+            .instruction(instruction(
+                vec![Trigger::Not {
+                    trigger: Box::new(Trigger::Time(When::Random { start: 10, end: 10 })),
+                }],
+                vec![Action::AdjustScore(1)],
+            ))
+            .build();
+        let mut game = game_of(vec![counter]);
+
+        run_frames(&mut game, 30)?;
+
+        assert_eq!(game.score_adjustment, 29);
+        Ok(())
+    }
+
+    #[test]
+    fn resizing_scales_collision_area_per_axis() -> WeeResult<()> {
+        let object = SerialiseObject::builder()
+            .name("Box")
+            .size(Size::new(100.0, 100.0))
+            .collision_area(AABB {
+                min: Vec2::new(10.0, 20.0),
+                max: Vec2::new(50.0, 80.0),
+            })
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::SetProperty(PropertySetter::Size(SizeSetter::Grow(
+                    SizeDifference::Percent(Size::new(100.0, 0.0)),
+                )))],
+            ))
+            .build();
+        let mut game = game_of(vec![object]);
+
+        run_frames(&mut game, 1)?;
+
+        let area = game.objects["Box"].collision_area.expect("collision area");
+        assert_eq!(area.min, Vec2::new(20.0, 20.0));
+        assert_eq!(area.max, Vec2::new(100.0, 80.0));
+        Ok(())
+    }
+
+    #[test]
+    fn tint_survives_a_round_trip() -> WeeResult<()> {
+        let object = SerialiseObject::builder()
+            .name("Sprite")
+            .tint(Colour::rgb(1.0, 0.0, 0.0))
+            .build();
+
+        let saved = serde_json::to_string(&object)?;
+        let loaded: SerialiseObject = json_from_str(&saved)?;
+
+        assert_eq!(loaded.tint, Colour::rgb(1.0, 0.0, 0.0));
+        Ok(())
+    }
+
+    #[test]
+    fn tint_setter_changes_object_tint() -> WeeResult<()> {
+        let object = SerialiseObject::builder()
+            .name("Sprite")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::SetProperty(PropertySetter::Tint(Colour::rgb(
+                    1.0, 0.0, 0.0,
+                )))],
+            ))
+            .build();
+        let mut game = game_of(vec![object]);
+        assert_eq!(game.objects["Sprite"].tint, Colour::white());
+
+        run_frames(&mut game, 1)?;
+
+        assert_eq!(game.objects["Sprite"].tint, Colour::rgb(1.0, 0.0, 0.0));
+        Ok(())
+    }
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!(
+            (actual - expected).magnitude() < 0.01,
+            "{:?} is not near {:?}",
+            actual,
+            expected
+        );
+    }
+
+    fn moved_at_start(name: &str, position: Vec2, motion: Motion) -> SerialiseObject {
+        SerialiseObject::builder()
+            .name(name)
+            .position(position)
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::Motion(motion)],
+            ))
+            .build()
+    }
+
+    fn orbit_centre(radius: f32) -> Motion {
+        Motion::Orbit {
+            center: Target::Object {
+                name: "Centre".to_string(),
+            },
+            radius,
+            speed: Speed::Value(1.0),
+            clockwise: true,
+        }
+    }
+
+    #[test]
+    fn orbit_goes_a_quarter_turn_in_90_frames() -> WeeResult<()> {
+        let centre = SerialiseObject::builder()
+            .name("Centre")
+            .position(Vec2::new(800.0, 450.0))
+            .build();
+        let moon = moved_at_start("Moon", Vec2::new(900.0, 450.0), orbit_centre(100.0));
+        let mut game = game_of(vec![centre, moon]);
+
+        run_frames(&mut game, 90)?;
+
+        assert_near(game.objects["Moon"].position, Vec2::new(800.0, 550.0));
+        Ok(())
+    }
+
+    #[test]
+    fn orbit_with_no_radius_sits_on_centre() -> WeeResult<()> {
+        let centre = SerialiseObject::builder()
+            .name("Centre")
+            .position(Vec2::new(800.0, 450.0))
+            .build();
+        let moon = moved_at_start("Moon", Vec2::new(900.0, 450.0), orbit_centre(0.0));
+        let mut game = game_of(vec![centre, moon]);
+
+        run_frames(&mut game, 10)?;
+
+        assert_near(game.objects["Moon"].position, Vec2::new(800.0, 450.0));
+        Ok(())
+    }
+
+    #[test]
+    fn orbit_around_missing_object_is_an_error() {
+        let moon = moved_at_start("Moon", Vec2::new(900.0, 450.0), orbit_centre(100.0));
+        let mut game = game_of(vec![moon]);
+
+        assert!(run_frames(&mut game, 1).is_err());
+    }
+
+    fn gravity(strength: f32, terminal_velocity: Option<f32>) -> Motion {
+        Motion::ApplyGravity {
+            strength,
+            terminal_velocity,
+        }
+    }
+
+    #[test]
+    fn gravity_falls_with_constant_acceleration() -> WeeResult<()> {
+        let strength = 0.5;
+        let frames = 60;
+        let ball = moved_at_start("Ball", Vec2::zero(), gravity(strength, None));
+        let mut game = game_of(vec![ball]);
+
+        run_frames(&mut game, frames)?;
+
+        // 0.5 * g * t^2 plus the half step from adding velocity before moving
+        let t = frames as f32;
+        let expected = 0.5 * strength * t * t + 0.5 * strength * t;
+        assert!((game.objects["Ball"].position.y - expected).abs() < 0.01);
+        assert_eq!(game.objects["Ball"].position.x, 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn gravity_stops_accelerating_at_terminal_velocity() -> WeeResult<()> {
+        let ball = moved_at_start("Ball", Vec2::zero(), gravity(1.0, Some(5.0)));
+        let mut game = game_of(vec![ball]);
+
+        run_frames(&mut game, 20)?;
+        let before = game.objects["Ball"].position.y;
+        run_frames(&mut game, 1)?;
+
+        assert!((game.objects["Ball"].position.y - before - 5.0).abs() < 0.01);
+        Ok(())
+    }
+
+    fn square_path(loop_path: bool) -> Motion {
+        Motion::FollowPath {
+            points: vec![
+                Vec2::new(100.0, 0.0),
+                Vec2::new(100.0, 100.0),
+                Vec2::new(0.0, 100.0),
+            ],
+            speed: Speed::Value(10.0),
+            loop_path,
+        }
+    }
+
+    #[test]
+    fn path_walks_each_point_and_stops_at_the_end() -> WeeResult<()> {
+        let walker = moved_at_start("Walker", Vec2::zero(), square_path(false));
+        let mut game = game_of(vec![walker]);
+
+        run_frames(&mut game, 10)?;
+        assert_near(game.objects["Walker"].position, Vec2::new(100.0, 0.0));
+        run_frames(&mut game, 10)?;
+        assert_near(game.objects["Walker"].position, Vec2::new(100.0, 100.0));
+        run_frames(&mut game, 30)?;
+
+        assert_near(game.objects["Walker"].position, Vec2::new(0.0, 100.0));
+        assert!(matches!(
+            game.objects["Walker"].active_motion,
+            ActiveMotion::Stop
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn looped_path_returns_to_the_first_point() -> WeeResult<()> {
+        let walker = moved_at_start("Walker", Vec2::zero(), square_path(true));
+        let mut game = game_of(vec![walker]);
+
+        // Each side is 10 frames and the diagonal back to the start is 15 more
+        run_frames(&mut game, 45)?;
+
+        assert_near(game.objects["Walker"].position, Vec2::new(100.0, 0.0));
+        Ok(())
+    }
+
+    #[test]
+    fn empty_path_does_nothing() -> WeeResult<()> {
+        let path = Motion::FollowPath {
+            points: Vec::new(),
+            speed: Speed::Value(10.0),
+            loop_path: false,
+        };
+        let walker = moved_at_start("Walker", Vec2::new(50.0, 50.0), path);
+        let mut game = game_of(vec![walker]);
+
+        run_frames(&mut game, 10)?;
+
+        assert_near(game.objects["Walker"].position, Vec2::new(50.0, 50.0));
+        Ok(())
+    }
+
+    #[test]
+    fn spawn_adds_one_object_with_the_template_instructions() -> WeeResult<()> {
+        let bullet = SerialiseObject::builder()
+            .name("Bullet")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Exact { time: 5 })],
+                vec![Action::AdjustScore(1)],
+            ))
+            .build();
+        let gun = SerialiseObject::builder()
+            .name("Gun")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::Spawn {
+                    template: "Bullet".to_string(),
+                    position: JumpLocation::Point(Vec2::new(100.0, 200.0)),
+                    name_prefix: "Bullet ".to_string(),
+                }],
+            ))
+            .build();
+        let mut game = game_of(vec![bullet, gun]);
+
+        run_frames(&mut game, 1)?;
+        assert_eq!(game.objects.len(), 3);
+        assert_near(game.objects["Bullet 0"].position, Vec2::new(100.0, 200.0));
+
+        run_frames(&mut game, 5)?;
+        assert_eq!(game.score_adjustment, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn spawning_from_missing_template_is_an_error() {
+        let gun = SerialiseObject::builder()
+            .name("Gun")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::Spawn {
+                    template: "Nothing".to_string(),
+                    position: JumpLocation::Point(Vec2::zero()),
+                    name_prefix: "Bullet ".to_string(),
+                }],
+            ))
+            .build();
+        let mut game = game_of(vec![gun]);
+
+        assert!(run_frames(&mut game, 1).is_err());
+    }
+
+    #[test]
+    fn destroyed_object_is_removed_after_the_frame() -> WeeResult<()> {
+        let object = SerialiseObject::builder()
+            .name("Balloon")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Exact { time: 3 })],
+                vec![Action::Destroy],
+            ))
+            .build();
+        let mut game = game_of(vec![object]);
+
+        run_frames(&mut game, 3)?;
+        assert!(game.objects.contains_key("Balloon"));
+        run_frames(&mut game, 1)?;
+
+        assert!(!game.objects.contains_key("Balloon"));
+        Ok(())
+    }
+
+    #[test]
+    fn destroying_missing_object_is_an_error() {
+        let object = SerialiseObject::builder()
+            .name("Pin")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::DestroyNamed {
+                    name: "Nothing".to_string(),
+                }],
+            ))
+            .build();
+        let mut game = game_of(vec![object]);
+
+        assert!(run_frames(&mut game, 1).is_err());
+    }
+
+    #[test]
+    fn screen_shake_wears_off_after_its_frames() -> WeeResult<()> {
+        let bomb = SerialiseObject::builder()
+            .name("Bomb")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::ScreenShake {
+                    intensity: 10.0,
+                    frames: 5,
+                }],
+            ))
+            .build();
+        let mut game = game_of(vec![bomb]);
+
+        run_frames(&mut game, 1)?;
+        assert_eq!(game.shake.remaining, 5);
+        assert_eq!(game.shake.current_intensity(), 10.0);
+
+        run_frames(&mut game, 4)?;
+        assert_eq!(game.shake.remaining, 1);
+        assert_eq!(game.shake.current_intensity(), 2.0);
+
+        run_frames(&mut game, 1)?;
+        assert_eq!(game.shake.remaining, 0);
+        assert_eq!(game.shake.current_intensity(), 0.0);
+        Ok(())
+    }
+
+    fn validated(actions: Vec<Action>) -> WeeResult<()> {
+        let mut asset_files = AssetFiles::default();
+        asset_files.audio.insert(
+            "pop".to_string(),
+            SoundLoadInfo {
+                filename: "pop.ogg".to_string(),
+                volume: 1.0,
+            },
+        );
+        asset_files.fonts.insert(
+            "Fonty".to_string(),
+            FontLoadInfo {
+                filename: "Lato-Bold.ttf".to_string(),
+                size: 48.0,
+            },
+        );
+        let object = SerialiseObject::builder()
+            .name("Object")
+            .instruction(instruction(vec![Trigger::Time(When::Start)], actions))
+            .build();
+        GameData::builder()
+            .object(object)
+            .asset_files(asset_files)
+            .build()
+            .validate()
+    }
+
+    fn draw_text_in(font: &str) -> Action {
+        Action::DrawText {
+            text: "Hello".to_string(),
+            font: font.to_string(),
+            colour: Colour::white(),
+            resize: TextResize::MatchObject,
+            justify: JustifyText::Centre,
+            max_width: None,
+        }
+    }
+
+    fn play(sound: &str) -> Action {
+        Action::PlaySound {
+            name: sound.to_string(),
+            attenuate: false,
+        }
+    }
+
+    #[test]
+    fn validation_passes_with_known_assets() {
+        assert!(validated(vec![play("pop"), draw_text_in("Fonty")]).is_ok());
+    }
+
+    #[test]
+    fn validation_reports_missing_sound() {
+        let error = validated(vec![play("bang")]).unwrap_err().to_string();
+
+        assert!(error.contains("sound 'bang'"), "{}", error);
+    }
+
+    #[test]
+    fn validation_reports_missing_font() {
+        let error = validated(vec![draw_text_in("Comic Sans")])
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("font 'Comic Sans'"), "{}", error);
+    }
+
+    fn halfway_counter(length: Length) -> Game {
+        let counter = SerialiseObject::builder()
+            .name("Counter")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Fraction { elapsed: 0.5 })],
+                vec![Action::AdjustScore(1)],
+            ))
+            .build();
+        Game::from_data(GameData::builder().object(counter).length(length).build())
+    }
+
+    #[test]
+    fn fraction_fires_once_halfway_through() -> WeeResult<()> {
+        let mut game = halfway_counter(Length::Seconds(4.0));
+        assert_eq!(game.frames.total, FrameCount::Frames(240));
+
+        run_frames(&mut game, 120)?;
+        assert_eq!(game.score_adjustment, 0);
+        run_frames(&mut game, 1)?;
+        assert_eq!(game.score_adjustment, 1);
+        run_frames(&mut game, 119)?;
+
+        assert_eq!(game.score_adjustment, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn fraction_never_fires_in_infinite_games() -> WeeResult<()> {
+        let mut game = halfway_counter(Length::Infinite);
+
+        run_frames(&mut game, 600)?;
+
+        assert_eq!(game.score_adjustment, 0);
+        Ok(())
+    }
+
+    fn draw(text: &str) -> Action {
+        Action::DrawText {
+            text: text.to_string(),
+            font: "Fonty".to_string(),
+            colour: Colour::white(),
+            resize: TextResize::MatchObject,
+            justify: JustifyText::Centre,
+            max_width: None,
+        }
+    }
+
+    fn text_when_lamp(lamp: Switch) -> WeeResult<String> {
+        let lamp = SerialiseObject::builder().name("Lamp").switch(lamp).build();
+        let sign = SerialiseObject::builder()
+            .name("Sign")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::If {
+                    check: Trigger::CheckProperty {
+                        name: "Lamp".to_string(),
+                        check: PropertyCheck::Switch(SwitchState::On),
+                    },
+                    then: vec![draw("Lit")],
+                    otherwise: vec![draw("Dark")],
+                }],
+            ))
+            .build();
+        let mut game = game_of(vec![lamp, sign]);
+
+        run_frames(&mut game, 1)?;
+
+        Ok(game.drawn_text["Sign"].text.clone())
+    }
+
+    #[test]
+    fn if_runs_the_matching_branch() -> WeeResult<()> {
+        assert_eq!(text_when_lamp(Switch::On)?, "Lit");
+        assert_eq!(text_when_lamp(Switch::Off)?, "Dark");
+        Ok(())
+    }
+
+    fn scores_at_start(action: Action) -> Game {
+        let object = SerialiseObject::builder()
+            .name("Scorer")
+            .instruction(instruction(vec![Trigger::Time(When::Start)], vec![action]))
+            .build();
+        game_of(vec![object])
+    }
+
+    // The score after each frame up to the given count
+    fn score_by_frame(game: &mut Game, frames: u32) -> WeeResult<Vec<i32>> {
+        let mut scores = Vec::new();
+        for _ in 0..frames {
+            run_frames(game, 1)?;
+            scores.push(game.score_adjustment);
+        }
+        Ok(scores)
+    }
+
+    fn frames_scored_on(scores: &[i32]) -> Vec<usize> {
+        let mut previous = 0;
+        let mut frames = Vec::new();
+        for (frame, score) in scores.iter().enumerate() {
+            if *score != previous {
+                frames.push(frame);
+            }
+            previous = *score;
+        }
+        frames
+    }
+
+    #[test]
+    fn sequence_steps_fire_after_their_delays() -> WeeResult<()> {
+        let mut game = scores_at_start(Action::Sequence {
+            steps: vec![
+                (0, Action::AdjustScore(1)),
+                (10, Action::AdjustScore(1)),
+                (20, Action::AdjustScore(1)),
+            ],
+        });
+
+        let scores = score_by_frame(&mut game, 30)?;
+
+        assert_eq!(frames_scored_on(&scores), vec![0, 10, 20]);
+        assert_eq!(game.score_adjustment, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn sequence_is_dropped_when_its_object_is_destroyed() -> WeeResult<()> {
+        let mut game = scores_at_start(Action::Sequence {
+            steps: vec![
+                (0, Action::AdjustScore(1)),
+                (5, Action::Destroy),
+                (10, Action::AdjustScore(1)),
+            ],
+        });
+
+        run_frames(&mut game, 30)?;
+
+        assert!(!game.objects.contains_key("Scorer"));
+        assert_eq!(game.score_adjustment, 1);
+        Ok(())
+    }
+
+    fn repeat_score(count: u32, every: u32) -> Action {
+        Action::Repeat {
+            count,
+            every,
+            action: Box::new(Action::AdjustScore(1)),
+        }
+    }
+
+    #[test]
+    fn repeat_fires_count_times_spaced_apart() -> WeeResult<()> {
+        let mut game = scores_at_start(repeat_score(3, 5));
+
+        let scores = score_by_frame(&mut game, 15)?;
+
+        assert_eq!(frames_scored_on(&scores), vec![0, 5, 10]);
+        assert_eq!(game.score_adjustment, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn repeat_with_no_spacing_fires_all_at_once() -> WeeResult<()> {
+        let mut game = scores_at_start(repeat_score(3, 0));
+
+        run_frames(&mut game, 1)?;
+
+        assert_eq!(game.score_adjustment, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn repeat_zero_times_does_nothing() -> WeeResult<()> {
+        let mut game = scores_at_start(repeat_score(0, 5));
+
+        run_frames(&mut game, 15)?;
+
+        assert_eq!(game.score_adjustment, 0);
+        Ok(())
+    }
+
+    fn vertices(poly: c2::Poly) -> Vec<(f32, f32)> {
+        (0..poly.count())
+            .map(|index| {
+                let vertex = poly.get_vert(index);
+                (vertex.x(), vertex.y())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn cached_poly_matches_fresh_poly_after_moving() {
+        let mut object = SerialiseObject::builder()
+            .name("Box")
+            .position(Vec2::new(100.0, 100.0))
+            .size(Size::new(50.0, 80.0))
+            .angle(30.0)
+            .build()
+            .into_object();
+        let before = vertices(object.poly());
+
+        object.position += Vec2::new(25.0, -10.0);
+        object.angle = 45.0;
+        object.size = Size::new(60.0, 80.0);
+        let cached = vertices(object.poly());
+        let fresh = Object {
+            poly_cache: PolyCache::default(),
+            ..object.clone()
+        };
+
+        assert_ne!(cached, before);
+        assert_eq!(cached, vertices(fresh.poly()));
+        assert_eq!(cached, vertices(object.poly()));
+    }
+
+    fn spinner(instructions: Vec<Instruction>) -> Game {
+        let spinner = instructions
+            .into_iter()
+            .fold(
+                SerialiseObject::builder().name("Spinner"),
+                |builder, instruction| builder.instruction(instruction),
+            )
+            .build();
+        game_of(vec![spinner])
+    }
+
+    fn spin_at_start(degrees_per_frame: f32) -> Instruction {
+        instruction(
+            vec![Trigger::Time(When::Start)],
+            vec![Action::SetProperty(PropertySetter::AngularVelocity(
+                degrees_per_frame,
+            ))],
+        )
+    }
+
+    #[test]
+    fn angular_velocity_keeps_spinning() -> WeeResult<()> {
+        let mut game = spinner(vec![spin_at_start(9.0)]);
+
+        run_frames(&mut game, 10)?;
+
+        assert!((game.objects["Spinner"].angle - 90.0).abs() < 0.001);
+        Ok(())
+    }
+
+    #[test]
+    fn setting_the_angle_overrides_spinning_for_that_frame() -> WeeResult<()> {
+        let mut game = spinner(vec![
+            spin_at_start(9.0),
+            instruction(
+                vec![Trigger::Time(When::Exact { time: 5 })],
+                vec![Action::SetProperty(PropertySetter::Angle(
+                    AngleSetter::Value(0.0),
+                ))],
+            ),
+        ]);
+
+        run_frames(&mut game, 6)?;
+        assert_eq!(game.objects["Spinner"].angle, 0.0);
+        run_frames(&mut game, 1)?;
+
+        assert!((game.objects["Spinner"].angle - 9.0).abs() < 0.001);
+        Ok(())
+    }
+
+    #[test]
+    fn image_region_survives_a_round_trip() -> WeeResult<()> {
+        let sprite = Sprite::ImageRegion {
+            name: "atlas".to_string(),
+            source: AABB {
+                min: Vec2::new(32.0, 0.0),
+                max: Vec2::new(64.0, 48.0),
+            },
+        };
+
+        let saved = serde_json::to_string(&sprite)?;
+        let loaded: Sprite = json_from_str(&saved)?;
+
+        assert_eq!(loaded, sprite);
+        Ok(())
+    }
+
+    fn attract_to_centre(falloff: Falloff) -> Motion {
+        Motion::Attract {
+            target: Target::Object {
+                name: "Centre".to_string(),
+            },
+            strength: 100.0,
+            falloff,
+        }
+    }
+
+    #[test]
+    fn inverse_square_attraction_pulls_harder_when_closer() -> WeeResult<()> {
+        let centre = SerialiseObject::builder()
+            .name("Centre")
+            .position(Vec2::new(800.0, 450.0))
+            .build();
+        let near = moved_at_start(
+            "Near",
+            Vec2::new(810.0, 450.0),
+            attract_to_centre(Falloff::InverseSquare),
+        );
+        let far = moved_at_start(
+            "Far",
+            Vec2::new(900.0, 450.0),
+            attract_to_centre(Falloff::InverseSquare),
+        );
+        let mut game = game_of(vec![centre, near, far]);
+
+        run_frames(&mut game, 1)?;
+
+        let near_pull = 810.0 - game.objects["Near"].position.x;
+        let far_pull = 900.0 - game.objects["Far"].position.x;
+        assert!(far_pull > 0.0);
+        assert!(near_pull > far_pull * 50.0);
+        Ok(())
+    }
+
+    #[test]
+    fn attraction_at_the_target_does_not_move() -> WeeResult<()> {
+        let centre = SerialiseObject::builder()
+            .name("Centre")
+            .position(Vec2::new(800.0, 450.0))
+            .build();
+        let magnet = moved_at_start(
+            "Magnet",
+            Vec2::new(800.0, 450.0),
+            attract_to_centre(Falloff::InverseSquare),
+        );
+        let mut game = game_of(vec![centre, magnet]);
+
+        run_frames(&mut game, 5)?;
+
+        assert_eq!(game.objects["Magnet"].position, Vec2::new(800.0, 450.0));
+        Ok(())
+    }
+
+    #[test]
+    fn blink_toggles_visibility_every_interval_then_clears() -> WeeResult<()> {
+        let blinker = SerialiseObject::builder()
+            .name("Blinker")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::Blink {
+                    frames: 12,
+                    interval: 3,
+                }],
+            ))
+            .build();
+        let mut game = game_of(vec![blinker]);
+
+        let mut visibility = Vec::new();
+        for _ in 0..20 {
+            run_frames(&mut game, 1)?;
+            visibility.push(game.objects["Blinker"].is_visible());
+        }
+
+        let toggles = visibility.windows(2).filter(|w| w[0] != w[1]).count();
+        assert_eq!(toggles, 4);
+        assert!(game.objects["Blinker"].blink.is_none());
+        assert!(game.objects["Blinker"].is_visible());
+        Ok(())
+    }
+
+    fn wins_at_speed(motion: Motion, comparison: Comparison, value: f32) -> WeeResult<bool> {
+        let car = SerialiseObject::builder()
+            .name("Car")
+            .position(Vec2::new(400.0, 450.0))
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::Motion(motion)],
+            ))
+            .instruction(instruction(
+                vec![
+                    Trigger::Time(When::Exact { time: 1 }),
+                    Trigger::Speed { comparison, value },
+                ],
+                vec![Action::Win],
+            ))
+            .build();
+        let mut game = game_of(vec![car]);
+
+        // Speed is checked on the frame after the motion starts
+        Ok(has_won(run_frames(&mut game, 2)?))
+    }
+
+    #[test]
+    fn go_straight_reports_its_speed() -> WeeResult<()> {
+        let go_straight = || Motion::GoStraight {
+            direction: MovementDirection::Angle(Angle::Degrees(90.0)),
+            speed: Speed::Value(6.0),
+        };
+
+        assert!(wins_at_speed(go_straight(), Comparison::Equal, 6.0)?);
+        assert!(wins_at_speed(go_straight(), Comparison::Greater, 5.0)?);
+        assert!(!wins_at_speed(go_straight(), Comparison::Less, 6.0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn stopped_object_reports_zero_speed() -> WeeResult<()> {
+        assert!(wins_at_speed(Motion::Stop, Comparison::Equal, 0.0)?);
+        assert!(!wins_at_speed(Motion::Stop, Comparison::Greater, 0.0)?);
+        Ok(())
+    }
+
+    fn wins_at(position: Vec2, trigger: Trigger) -> WeeResult<bool> {
+        let bullet = SerialiseObject::builder()
+            .name("Bullet")
+            .position(position)
+            .instruction(instruction(vec![trigger], vec![Action::Win]))
+            .build();
+        let mut game = game_of(vec![bullet]);
+
+        Ok(has_won(run_frames(&mut game, 1)?))
+    }
+
+    #[test]
+    fn off_screen_fires_outside_the_projection() -> WeeResult<()> {
+        assert!(wins_at(Vec2::new(2000.0, 450.0), Trigger::OffScreen)?);
+        assert!(!wins_at(Vec2::new(800.0, 450.0), Trigger::OffScreen)?);
+        Ok(())
+    }
+
+    #[test]
+    fn on_screen_fires_inside_the_projection() -> WeeResult<()> {
+        assert!(wins_at(Vec2::new(800.0, 450.0), Trigger::OnScreen)?);
+        assert!(!wins_at(Vec2::new(2000.0, 450.0), Trigger::OnScreen)?);
+        Ok(())
+    }
+
+    #[test]
+    fn score_at_least_fires_from_the_current_score() -> WeeResult<()> {
+        let boss = SerialiseObject::builder()
+            .name("Boss")
+            .instruction(instruction(
+                vec![Trigger::ScoreAtLeast { score: 20 }],
+                vec![Action::Win],
+            ))
+            .build();
+        let mut game = game_of(vec![boss.clone()]);
+        game.score = 25;
+        assert!(has_won(run_frames(&mut game, 1)?));
+
+        let mut game = game_of(vec![boss]);
+        game.score = 15;
+        assert!(!has_won(run_frames(&mut game, 1)?));
+        Ok(())
+    }
+
+    #[test]
+    fn lives_trigger_compares_the_current_lives() -> WeeResult<()> {
+        let boss = SerialiseObject::builder()
+            .name("Boss")
+            .instruction(instruction(
+                vec![Trigger::Lives {
+                    comparison: Comparison::Equal,
+                    value: 1,
+                }],
+                vec![Action::Win],
+            ))
+            .build();
+        let mut game = game_of(vec![boss]);
+        game.lives = 1;
+
+        assert!(has_won(run_frames(&mut game, 1)?));
+        Ok(())
+    }
+
+    #[test]
+    fn adjust_lives_accumulates_for_the_progress() -> WeeResult<()> {
+        let mut game = scores_at_start(Action::AdjustLives(1));
+
+        run_frames(&mut game, 1)?;
+
+        assert_eq!(game.lives_adjustment, 1);
+        Ok(())
+    }
+
+    fn shell(name: &str, image: &str, actions: Vec<Action>) -> SerialiseObject {
+        SerialiseObject::builder()
+            .name(name)
+            .sprite(Sprite::Image {
+                name: image.to_string(),
+            })
+            .instruction(instruction(vec![Trigger::Time(When::Start)], actions))
+            .build()
+    }
+
+    #[test]
+    fn swap_sprite_exchanges_both_sprites() -> WeeResult<()> {
+        let left = shell(
+            "Left",
+            "Pea",
+            vec![Action::SwapSprite {
+                name: "Right".to_string(),
+            }],
+        );
+        let right = shell("Right", "Empty", Vec::new());
+        let mut game = game_of(vec![left, right]);
+
+        run_frames(&mut game, 1)?;
+
+        let image = |name: &str| Sprite::Image {
+            name: name.to_string(),
+        };
+        assert_eq!(game.objects["Left"].sprite, image("Empty"));
+        assert_eq!(game.objects["Right"].sprite, image("Pea"));
+        Ok(())
+    }
+
+    #[test]
+    fn swap_sprite_with_missing_object_is_an_error() {
+        let left = shell(
+            "Left",
+            "Pea",
+            vec![Action::SwapSprite {
+                name: "Missing".to_string(),
+            }],
+        );
+        let mut game = game_of(vec![left]);
+
+        assert!(run_frames(&mut game, 1).is_err());
+    }
+
+    fn ball_touches_box_at(position: Vec2) -> WeeResult<bool> {
+        let ball = SerialiseObject::builder()
+            .name("Ball")
+            .position(Vec2::new(500.0, 500.0))
+            .size(Size::new(100.0, 100.0))
+            .collision_shape(CollisionShape::Circle { radius: 50.0 })
+            .instruction(instruction(
+                vec![Trigger::Collision(CollisionWith::Object {
+                    name: "Box".to_string(),
+                })],
+                vec![Action::Win],
+            ))
+            .build();
+        let block = SerialiseObject::builder()
+            .name("Box")
+            .position(position)
+            .size(Size::new(10.0, 10.0))
+            .build();
+        let mut game = game_of(vec![ball, block]);
+
+        Ok(has_won(run_frames(&mut game, 1)?))
+    }
+
+    #[test]
+    fn circle_collides_only_when_truly_overlapping() -> WeeResult<()> {
+        // Inside the bounding box's corner but outside the circle
+        assert!(!ball_touches_box_at(Vec2::new(545.0, 545.0))?);
+        assert!(ball_touches_box_at(Vec2::new(545.0, 500.0))?);
+        Ok(())
+    }
+
+    #[test]
+    fn circles_collide_with_each_other() -> WeeResult<()> {
+        let circle = |name: &str, x: f32| {
+            SerialiseObject::builder()
+                .name(name)
+                .position(Vec2::new(x, 500.0))
+                .size(Size::new(100.0, 100.0))
+                .collision_shape(CollisionShape::Circle { radius: 50.0 })
+        };
+        let planet = circle("Planet", 500.0)
+            .instruction(instruction(
+                vec![Trigger::Collision(CollisionWith::Object {
+                    name: "Moon".to_string(),
+                })],
+                vec![Action::Win],
+            ))
+            .build();
+        let mut game = game_of(vec![planet.clone(), circle("Moon", 590.0).build()]);
+        assert!(has_won(run_frames(&mut game, 1)?));
+
+        let mut game = game_of(vec![planet, circle("Moon", 610.0).build()]);
+        assert!(!has_won(run_frames(&mut game, 1)?));
+        Ok(())
+    }
+
+    #[test]
+    fn timer_below_fires_once_the_timer_drops_under_it() -> WeeResult<()> {
+        let countdown = SerialiseObject::builder()
+            .name("Countdown")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::SetProperty(PropertySetter::Timer { time: 60 })],
+            ))
+            .instruction(instruction(
+                vec![Trigger::CheckProperty {
+                    name: "Countdown".to_string(),
+                    check: PropertyCheck::TimerBelow(30),
+                }],
+                vec![Action::Win],
+            ))
+            .build();
+        let mut game = game_of(vec![countdown]);
+
+        for _ in 0..60 {
+            let timer = game.objects["Countdown"].timer;
+            let has_won = has_won(run_frames(&mut game, 1)?);
+            if has_won {
+                // Won on the frame the timer went from 30 to 29
+                assert_eq!(timer, Some(30));
+                return Ok(());
+            }
+        }
+        panic!("TimerBelow never fired");
+    }
+
+    fn jumped_to(location: JumpLocation) -> WeeResult<Vec2> {
+        let piece = moved_at_start("Piece", Vec2::new(137.0, 212.0), Motion::JumpTo(location));
+        let mut game = game_of(vec![piece]);
+
+        run_frames(&mut game, 1)?;
+
+        Ok(game.objects["Piece"].position)
+    }
+
+    #[test]
+    fn grid_snaps_to_the_nearest_cell() -> WeeResult<()> {
+        let position = jumped_to(JumpLocation::Grid {
+            origin: Vec2::zero(),
+            cell: Size::new(50.0, 50.0),
+        })?;
+
+        assert_eq!(position, Vec2::new(150.0, 200.0));
+        Ok(())
+    }
+
+    #[test]
+    fn snap_to_nearest_picks_the_closest_point() -> WeeResult<()> {
+        let position = jumped_to(JumpLocation::SnapToNearest {
+            points: vec![
+                Vec2::new(0.0, 0.0),
+                Vec2::new(100.0, 250.0),
+                Vec2::new(400.0, 200.0),
+            ],
+        })?;
+
+        assert_eq!(position, Vec2::new(100.0, 250.0));
+        Ok(())
+    }
+
+    fn mouse_at(x: f32, y: f32, state: ButtonState) -> Mouse {
+        Mouse {
+            position: Vec2::new(x, y),
+            state,
+        }
+    }
+
+    fn wins_by_swiping(direction: CompassDirection, path: &[Vec2], end: Vec2) -> WeeResult<bool> {
+        let jumper = SerialiseObject::builder()
+            .name("Jumper")
+            .instruction(instruction(
+                vec![Trigger::Swipe {
+                    direction,
+                    min_distance: 200.0,
+                    tolerance: 30.0,
+                }],
+                vec![Action::Win],
+            ))
+            .build();
+        let mut game = game_of(vec![jumper]);
+        let mut inputs = vec![press_at(100.0, 100.0)];
+        inputs.extend(
+            path.iter()
+                .map(|point| mouse_at(point.x, point.y, ButtonState::Down)),
+        );
+        inputs.push(mouse_at(end.x, end.y, ButtonState::Release));
+
+        let frames = inputs.len() as u32;
+        let (status, _) = game.run_headless(inputs.into_iter(), frames)?;
+        Ok(has_won(status))
+    }
+
+    #[test]
+    fn dragging_down_registers_a_down_swipe() -> WeeResult<()> {
+        let path = [Vec2::new(100.0, 250.0)];
+        let end = Vec2::new(100.0, 400.0);
+
+        assert!(wins_by_swiping(CompassDirection::Down, &path, end)?);
+        assert!(!wins_by_swiping(CompassDirection::Up, &path, end)?);
+        assert!(!wins_by_swiping(CompassDirection::Right, &path, end)?);
+        Ok(())
+    }
+
+    #[test]
+    fn short_drags_are_not_swipes() -> WeeResult<()> {
+        let path = [Vec2::new(100.0, 150.0)];
+
+        assert!(!wins_by_swiping(
+            CompassDirection::Down,
+            &path,
+            Vec2::new(100.0, 200.0)
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn a_drag_that_goes_out_and_comes_back_still_swipes() -> WeeResult<()> {
+        let path = [Vec2::new(100.0, 250.0), Vec2::new(100.0, 400.0)];
+        let end = Vec2::new(100.0, 120.0);
+
+        assert!(wins_by_swiping(CompassDirection::Down, &path, end)?);
+        assert!(!wins_by_swiping(CompassDirection::Up, &path, end)?);
+        Ok(())
+    }
+
+    #[test]
+    fn draw_order_is_by_layer_then_insertion() {
+        let on_layer =
+            |name: &str, layer: u8| SerialiseObject::builder().name(name).layer(layer).build();
+        let game = game_of(vec![
+            on_layer("Front A", 0),
+            on_layer("Back", 2),
+            on_layer("Middle", 1),
+            on_layer("Front B", 0),
+        ]);
+
+        let names: Vec<&str> = game
+            .draw_order()
+            .into_iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+
+        assert_eq!(names, vec!["Back", "Middle", "Front A", "Front B"]);
+    }
+
+    #[test]
+    fn roaming_stays_inside_a_triangle() -> WeeResult<()> {
+        let triangle = vec![
+            Vec2::new(100.0, 100.0),
+            Vec2::new(700.0, 100.0),
+            Vec2::new(100.0, 700.0),
+        ];
+        let roamer = moved_at_start(
+            "Roamer",
+            Vec2::new(200.0, 200.0),
+            Motion::Roam {
+                movement_type: MovementType::Reflect {
+                    initial_direction: MovementDirection::Angle(Angle::Degrees(100.0)),
+                    movement_handling: MovementHandling::Anywhere,
+                },
+                area: RoamArea::Polygon(triangle.clone()),
+                speed: Speed::Value(9.0),
+            },
+        );
+        let mut game = game_of(vec![roamer]);
+
+        let mut positions = HashSet::new();
+        for _ in 0..200 {
+            run_frames(&mut game, 1)?;
+            let position = game.objects["Roamer"].position;
+            assert!(is_point_in_polygon(position, &triangle), "{:?}", position);
+            positions.insert((position.x as i32, position.y as i32));
+        }
+
+        // Still moving rather than stuck against an edge
+        assert!(positions.len() > 100);
+        Ok(())
+    }
+
+    fn rolls_with_isolated_random(extra_object: bool) -> WeeResult<Vec<i32>> {
+        let coin_flip = |name: &str, action: Action| {
+            SerialiseObject::builder()
+                .name(name)
+                .instruction(instruction(
+                    vec![Trigger::Random { chance: 0.5 }],
+                    vec![action],
+                ))
+                .build()
+        };
+        let mut objects = Vec::new();
+        if extra_object {
+            objects.push(coin_flip(
+                "Extra",
+                Action::SetProperty(PropertySetter::Switch(Switch::On)),
+            ));
+        }
+        objects.push(coin_flip("Roller", Action::AdjustScore(1)));
+        let mut game = game_of(objects);
+        game.isolate_random(7);
+
+        score_by_frame(&mut game, 60)
+    }
+
+    #[test]
+    fn isolated_random_rolls_ignore_other_objects() -> WeeResult<()> {
+        let alone = rolls_with_isolated_random(false)?;
+        let with_extra = rolls_with_isolated_random(true)?;
+
+        assert_eq!(alone, with_extra);
+        let scored = frames_scored_on(&alone).len();
+        assert!(scored > 0 && scored < 60);
+        Ok(())
+    }
+
+    fn shared_rolls_around_a_stream(rolls_inside: usize) -> Vec<u32> {
+        rand::srand(99);
+        let before = rand::rand();
+        let mut streams = RandomStreams::new(7);
+        streams.enter("Roller");
+        for _ in 0..rolls_inside {
+            rand::rand();
+        }
+        streams.leave("Roller");
+        vec![before, rand::rand(), rand::rand()]
+    }
+
+    #[test]
+    fn shared_random_stream_resumes_after_an_object_stream() {
+        assert_eq!(
+            shared_rolls_around_a_stream(0),
+            shared_rolls_around_a_stream(5)
+        );
+    }
+
+    fn steps_towards_goal(easing: Easing) -> WeeResult<(Vec<f32>, Game)> {
+        let goal = SerialiseObject::builder()
+            .name("Goal")
+            .position(Vec2::new(700.0, 450.0))
+            .build();
+        let follower = moved_at_start(
+            "Follower",
+            Vec2::new(100.0, 450.0),
+            Motion::Target {
+                target: Target::Object {
+                    name: "Goal".to_string(),
+                },
+                target_type: TargetType::StopWhenReached,
+                offset: Vec2::zero(),
+                speed: Speed::Value(20.0),
+                easing,
+            },
+        );
+        let mut game = game_of(vec![goal, follower]);
+
+        let mut steps = Vec::new();
+        for _ in 0..200 {
+            let before = game.objects["Follower"].position;
+            run_frames(&mut game, 1)?;
+            steps.push((game.objects["Follower"].position - before).magnitude());
+        }
+        Ok((steps, game))
+    }
+
+    #[test]
+    fn ease_out_slows_down_near_the_target() -> WeeResult<()> {
+        let (steps, game) = steps_towards_goal(Easing::EaseOut)?;
+
+        let moving: Vec<f32> = steps.into_iter().filter(|step| *step > 0.0).collect();
+        assert!(moving.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert!(moving.last().unwrap() < &(moving[0] / 4.0));
+        assert_near(game.objects["Follower"].position, Vec2::new(700.0, 450.0));
+        assert!(matches!(
+            game.objects["Follower"].active_motion,
+            ActiveMotion::Stop
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn linear_target_moves_at_a_constant_speed() -> WeeResult<()> {
+        let (steps, _) = steps_towards_goal(Easing::Linear)?;
+
+        assert!(steps[1..30].iter().all(|step| (step - 20.0).abs() < 0.01));
+        Ok(())
+    }
+
+    #[test]
+    fn random_spot_keeps_away_from_obstacles() -> WeeResult<()> {
+        let obstacle_position = Vec2::new(200.0, 200.0);
+        let min_gap = 150.0;
+        let mut kept_gap = 0;
+        for _ in 0..100 {
+            let obstacle = SerialiseObject::builder()
+                .name("Obstacle")
+                .position(obstacle_position)
+                .build();
+            let coin = moved_at_start(
+                "Coin",
+                Vec2::zero(),
+                Motion::JumpTo(JumpLocation::RandomInAreaAvoiding {
+                    area: AABB {
+                        min: Vec2::zero(),
+                        max: Vec2::new(400.0, 400.0),
+                    },
+                    avoid: vec!["Obstacle".to_string()],
+                    min_gap,
+                }),
+            );
+            let mut game = game_of(vec![obstacle, coin]);
+
+            run_frames(&mut game, 1)?;
+
+            if (game.objects["Coin"].position - obstacle_position).magnitude() >= min_gap {
+                kept_gap += 1;
+            }
+        }
+
+        // Gives up after a few tries, so an unlucky sample can still land close
+        assert!(kept_gap >= 95);
+        Ok(())
+    }
+
+    fn frame_image(index: usize) -> Sprite {
+        Sprite::Image {
+            name: format!("Frame {}", index),
+        }
+    }
+
+    #[test]
+    fn animation_frame_fires_each_time_the_frame_is_reached() -> WeeResult<()> {
+        let walker = SerialiseObject::builder()
+            .name("Walker")
+            .sprite(frame_image(0))
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::Animate {
+                    animation_type: AnimationType::Loop,
+                    sprites: (0..4).map(frame_image).collect(),
+                    speed: Speed::Fast,
+                    reverse: false,
+                }],
+            ))
+            .instruction(instruction(
+                vec![Trigger::CheckProperty {
+                    name: "Walker".to_string(),
+                    check: PropertyCheck::AnimationFrame(2),
+                }],
+                vec![Action::AdjustScore(1)],
+            ))
+            .build();
+        let mut game = game_of(vec![walker]);
+
+        let mut sprites_when_fired = Vec::new();
+        let mut previous = 0;
+        for _ in 0..60 {
+            run_frames(&mut game, 1)?;
+            if game.score_adjustment > previous {
+                sprites_when_fired.push(game.objects["Walker"].sprite.clone());
+            }
+            previous = game.score_adjustment;
+        }
+
+        // Once per loop rather than for every frame the sprite is showing
+        assert_eq!(sprites_when_fired.len(), 3);
+        assert!(sprites_when_fired
+            .iter()
+            .all(|sprite| *sprite == frame_image(2)));
+        Ok(())
+    }
+
+    #[test]
+    fn win_after_waits_before_winning() -> WeeResult<()> {
+        let mut game = scores_at_start(Action::WinAfter { frames: 30 });
+
+        let mut first_won = None;
+        for frame in 1..=40 {
+            if has_won(run_frames(&mut game, 1)?) && first_won.is_none() {
+                first_won = Some(frame);
+            }
+        }
+
+        // Scheduled on the first frame, so it resolves 30 frames later
+        assert_eq!(first_won, Some(31));
+        Ok(())
+    }
+
+    #[test]
+    fn losing_before_a_delayed_win_resolves_keeps_the_loss() -> WeeResult<()> {
+        let object = SerialiseObject::builder()
+            .name("Loser")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::WinAfter { frames: 30 }],
+            ))
+            .instruction(instruction(
+                vec![Trigger::Time(When::Exact { time: 10 })],
+                vec![Action::Lose],
+            ))
+            .build();
+        let mut game = game_of(vec![object]);
+
+        let status = run_frames(&mut game, 40)?;
+
+        assert!(matches!(status, WinStatus::Lost | WinStatus::HasBeenLost));
+        Ok(())
+    }
+
+    fn click_counter(clicks: usize) -> WeeResult<bool> {
+        let counter = SerialiseObject::builder()
+            .name("Counter")
+            .position(Vec2::new(800.0, 450.0))
+            .size(Size::new(100.0, 100.0))
+            .instruction(instruction(
+                vec![Trigger::Input(Input::Mouse {
+                    over: MouseOver::Object {
+                        name: "Counter".to_string(),
+                    },
+                    interaction: MouseInteraction::Button {
+                        state: ButtonState::Press,
+                    },
+                })],
+                vec![Action::SetProperty(PropertySetter::Variable {
+                    name: "clicks".to_string(),
+                    op: VarOp::Add(1),
+                })],
+            ))
+            .instruction(instruction(
+                vec![Trigger::CheckVariable {
+                    object: "Counter".to_string(),
+                    name: "clicks".to_string(),
+                    comparison: Comparison::Equal,
+                    value: 5,
+                }],
+                vec![Action::Win],
+            ))
+            .build();
+        let mut game = game_of(vec![counter]);
+        let mut inputs = Vec::new();
+        for _ in 0..clicks {
+            inputs.push(press_at(800.0, 450.0));
+            inputs.push(mouse_at(800.0, 450.0, ButtonState::Release));
+        }
+
+        let (status, _) = game.run_headless(inputs.into_iter(), 20)?;
+        Ok(has_won(status))
+    }
+
+    #[test]
+    fn click_counter_wins_on_the_fifth_click() -> WeeResult<()> {
+        assert!(!click_counter(4)?);
+        assert!(click_counter(5)?);
+        Ok(())
+    }
+
+    fn ball_thrown_at_wall(solid: bool) -> WeeResult<Game> {
+        let ball = moved_at_start(
+            "Ball",
+            Vec2::new(400.0, 450.0),
+            Motion::Roam {
+                movement_type: MovementType::Reflect {
+                    initial_direction: MovementDirection::Angle(Angle::Degrees(90.0)),
+                    movement_handling: MovementHandling::TryNotToOverlap,
+                },
+                area: RoamArea::Rectangle(AABB {
+                    min: Vec2::zero(),
+                    max: Vec2::new(1600.0, 900.0),
+                }),
+                speed: Speed::Value(10.0),
+            },
+        );
+        let ball = SerialiseObject {
+            size: Size::new(20.0, 20.0),
+            solid: false,
+            ..ball
+        };
+        let wall = SerialiseObject::builder()
+            .name("Wall")
+            .position(Vec2::new(600.0, 450.0))
+            .size(Size::new(40.0, 400.0))
+            .solid(solid)
+            .build();
+        let mut game = game_of(vec![ball, wall]);
+
+        run_frames(&mut game, 40)?;
+        Ok(game)
+    }
+
+    #[test]
+    fn ball_bounces_back_off_a_solid_wall() -> WeeResult<()> {
+        let game = ball_thrown_at_wall(true)?;
+
+        let velocity = game.objects["Ball"].active_motion.velocity();
+        assert!(velocity.x < 0.0);
+        assert!(velocity.y.abs() < 0.01);
+        assert!(game.objects["Ball"].position.x < 580.0);
+        Ok(())
+    }
+
+    #[test]
+    fn ball_passes_through_objects_that_are_not_solid() -> WeeResult<()> {
+        let game = ball_thrown_at_wall(false)?;
+
+        assert!(game.objects["Ball"].active_motion.velocity().x > 0.0);
+        assert!(game.objects["Ball"].position.x > 620.0);
+        Ok(())
+    }
+
+    fn dial_lands_between(angle: f32, min: f32, max: f32) -> WeeResult<bool> {
+        let dial = SerialiseObject::builder()
+            .name("Dial")
+            .angle(angle)
+            .instruction(instruction(
+                vec![Trigger::CheckProperty {
+                    name: "Dial".to_string(),
+                    check: PropertyCheck::AngleBetween { min, max },
+                }],
+                vec![Action::Win],
+            ))
+            .build();
+        let mut game = game_of(vec![dial]);
+
+        Ok(has_won(run_frames(&mut game, 1)?))
+    }
+
+    #[test]
+    fn angle_between_wraps_through_zero() -> WeeResult<()> {
+        assert!(dial_lands_between(350.0, 340.0, 10.0)?);
+        assert!(dial_lands_between(5.0, 340.0, 10.0)?);
+        assert!(!dial_lands_between(180.0, 340.0, 10.0)?);
+        Ok(())
+    }
+
+    #[test]
+    fn angle_between_normalises_negative_angles() -> WeeResult<()> {
+        assert!(dial_lands_between(-10.0, 340.0, 10.0)?);
+        assert!(dial_lands_between(350.0, -20.0, 10.0)?);
+        Ok(())
+    }
+
+    fn anchored_to_crate(anchor: Anchor, offset: Vec2) -> WeeResult<Vec2> {
+        let stand = SerialiseObject::builder()
+            .name("Crate")
+            .position(Vec2::new(800.0, 600.0))
+            .size(Size::new(200.0, 100.0))
+            .build();
+        let lid = moved_at_start(
+            "Lid",
+            Vec2::zero(),
+            Motion::JumpTo(JumpLocation::ObjectAnchor {
+                name: "Crate".to_string(),
+                anchor,
+                offset,
+            }),
+        );
+        let lid = SerialiseObject {
+            size: Size::new(50.0, 20.0),
+            ..lid
+        };
+        let mut game = game_of(vec![stand, lid]);
+
+        run_frames(&mut game, 1)?;
+        Ok(game.objects["Lid"].position)
+    }
+
+    #[test]
+    fn anchoring_to_the_top_sits_on_the_edge() -> WeeResult<()> {
+        // The crate's top edge is at 550 and the lid is 20 high
+        assert_eq!(
+            anchored_to_crate(Anchor::Top, Vec2::zero())?,
+            Vec2::new(800.0, 540.0)
+        );
+        assert_eq!(
+            anchored_to_crate(Anchor::BottomRight, Vec2::new(5.0, 0.0))?,
+            Vec2::new(930.0, 660.0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn anchoring_to_a_missing_object_is_an_error() {
+        let lid = moved_at_start(
+            "Lid",
+            Vec2::zero(),
+            Motion::JumpTo(JumpLocation::ObjectAnchor {
+                name: "Missing".to_string(),
+                anchor: Anchor::Top,
+                offset: Vec2::zero(),
+            }),
+        );
+        let mut game = game_of(vec![lid]);
+
+        assert!(run_frames(&mut game, 1).is_err());
+    }
+
+    fn distance_in_60_frames(effect: Effect) -> WeeResult<f32> {
+        let mover = moved_at_start(
+            "Mover",
+            Vec2::new(100.0, 450.0),
+            Motion::GoStraight {
+                direction: MovementDirection::Angle(Angle::Degrees(90.0)),
+                speed: Speed::Value(4.0),
+            },
+        );
+        let director = SerialiseObject::builder()
+            .name("Director")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::Effect(effect)],
+            ))
+            .build();
+        let mut game = game_of(vec![mover, director]);
+
+        run_frames(&mut game, 1)?;
+        let start = game.objects["Mover"].position;
+        run_frames(&mut game, 60)?;
+        Ok((game.objects["Mover"].position - start).magnitude())
+    }
+
+    #[test]
+    fn slow_motion_halves_the_distance_travelled() -> WeeResult<()> {
+        let normal = distance_in_60_frames(Effect::None)?;
+        let slow = distance_in_60_frames(Effect::SlowMotion { factor: 0.5 })?;
+
+        assert!((normal - 240.0).abs() < 0.01);
+        assert!((slow - normal / 2.0).abs() < 0.01);
+        Ok(())
+    }
+
+    fn wins_with_locks(locks: &[Switch]) -> WeeResult<bool> {
+        let mut objects: Vec<SerialiseObject> = locks
+            .iter()
+            .enumerate()
+            .map(|(index, switch)| {
+                SerialiseObject::builder()
+                    .name(format!("lock{}", index + 1))
+                    .switch(*switch)
+                    .build()
+            })
+            .collect();
+        objects.push(
+            SerialiseObject::builder()
+                .name("Door")
+                .instruction(instruction(
+                    vec![Trigger::AllMatch {
+                        pattern: "lock".to_string(),
+                        check: PropertyCheck::Switch(SwitchState::On),
+                    }],
+                    vec![Action::Win],
+                ))
+                .build(),
+        );
+        let mut game = game_of(objects);
+
+        Ok(has_won(run_frames(&mut game, 1)?))
+    }
+
+    #[test]
+    fn all_match_only_fires_once_every_lock_is_on() -> WeeResult<()> {
+        assert!(!wins_with_locks(&[Switch::On, Switch::Off, Switch::On])?);
+        assert!(wins_with_locks(&[Switch::On, Switch::On, Switch::On])?);
+        Ok(())
+    }
+
+    #[test]
+    fn all_match_without_matching_objects_is_an_error() {
+        assert!(wins_with_locks(&[]).is_err());
+    }
+
+    #[test]
+    fn set_background_colour_replaces_the_background() -> WeeResult<()> {
+        let night = Colour::rgb(0.0, 0.0, 0.2);
+        let mut game = scores_at_start(Action::SetBackgroundColour(night));
+        assert!(game.background.is_empty());
+
+        run_frames(&mut game, 1)?;
+
+        assert_eq!(game.background.len(), 1);
+        assert_eq!(game.background[0].sprite, Sprite::Colour(night));
+        assert_eq!(
+            game.background[0].area,
+            AABB {
+                min: Vec2::zero(),
+                max: Vec2::new(PROJECTION_WIDTH, PROJECTION_HEIGHT),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn set_background_replaces_every_part() -> WeeResult<()> {
+        let part = |colour: Colour| BackgroundPart {
+            sprite: Sprite::Colour(colour),
+            area: AABB {
+                min: Vec2::zero(),
+                max: Vec2::new(800.0, 900.0),
+            },
+        };
+        let parts = vec![part(Colour::black()), part(Colour::white())];
+        let mut game = scores_at_start(Action::SetBackground {
+            parts: parts.clone(),
+        });
+        game.background = vec![part(Colour::rgb(1.0, 0.0, 0.0))];
+
+        run_frames(&mut game, 1)?;
+
+        assert_eq!(game.background, parts);
+        Ok(())
+    }
+
+    #[test]
+    fn timer_placeholder_counts_down_as_frames_pass() -> WeeResult<()> {
+        let countdown = SerialiseObject::builder()
+            .name("Countdown")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![
+                    Action::SetProperty(PropertySetter::Timer { time: 120 }),
+                    draw("{timer}s left {{timer}}"),
+                ],
+            ))
+            .build();
+        let mut game = game_of(vec![countdown]);
+        let shown =
+            |game: &Game| game.objects["Countdown"].format_text(&game.drawn_text["Countdown"].text);
+
+        run_frames(&mut game, 1)?;
+        assert_eq!(shown(&game), "2s left {timer}");
+        run_frames(&mut game, 60)?;
+        assert_eq!(shown(&game), "1s left {timer}");
+        run_frames(&mut game, 60)?;
+        assert_eq!(shown(&game), "0s left {timer}");
+        Ok(())
+    }
+
+    #[test]
+    fn variable_placeholders_show_the_current_value() -> WeeResult<()> {
+        let mut game = scores_at_start(Action::SetProperty(PropertySetter::Variable {
+            name: "coins".to_string(),
+            op: VarOp::Set(7),
+        }));
+
+        run_frames(&mut game, 1)?;
+
+        let scorer = &game.objects["Scorer"];
+        assert_eq!(scorer.format_text("{var:coins} coins"), "7 coins");
+        assert_eq!(scorer.format_text("{var:gems} {unknown}"), "0 {unknown}");
+        Ok(())
+    }
+
+    fn button_under_cover(cover_sprite: Sprite) -> Game {
+        let button = SerialiseObject::builder()
+            .name("Button")
+            .position(Vec2::new(800.0, 450.0))
+            .layer(1)
+            .cursor(CursorStyle::Pointer)
+            .build();
+        let cover = SerialiseObject::builder()
+            .name("Cover")
+            .sprite(cover_sprite)
+            .position(Vec2::new(850.0, 450.0))
+            .layer(0)
+            .build();
+        game_of(vec![cover, button])
+    }
+
+    #[test]
+    fn topmost_object_decides_the_cursor() {
+        let game = button_under_cover(Sprite::Colour(Colour::white()));
+
+        assert_eq!(
+            game.cursor_at(Vec2::new(760.0, 450.0)),
+            CursorStyle::Pointer
+        );
+        assert_eq!(
+            game.cursor_at(Vec2::new(820.0, 450.0)),
+            CursorStyle::Default
+        );
+        assert_eq!(
+            game.cursor_at(Vec2::new(100.0, 100.0)),
+            CursorStyle::Default
+        );
+    }
+
+    #[test]
+    fn transparent_objects_do_not_hide_the_cursor() {
+        let invisible = Colour {
+            a: 0.0,
+            ..Colour::white()
+        };
+        let game = button_under_cover(Sprite::Colour(invisible));
+
+        assert_eq!(
+            game.cursor_at(Vec2::new(820.0, 450.0)),
+            CursorStyle::Pointer
+        );
+    }
+
+    #[test]
+    fn set_velocity_moves_by_exactly_that_much_each_frame() -> WeeResult<()> {
+        let rocket = moved_at_start(
+            "Rocket",
+            Vec2::new(100.0, 400.0),
+            Motion::SetVelocity(Vec2::new(5.0, -3.0)),
+        );
+        let mut game = game_of(vec![rocket]);
+
+        run_frames(&mut game, 1)?;
+        let start = game.objects["Rocket"].position;
+        run_frames(&mut game, 1)?;
+        assert_eq!(
+            game.objects["Rocket"].position - start,
+            Vec2::new(5.0, -3.0)
+        );
+        run_frames(&mut game, 9)?;
+        assert_eq!(
+            game.objects["Rocket"].position - start,
+            Vec2::new(50.0, -30.0)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn keep_on_screen_stops_fast_objects_leaving() -> WeeResult<()> {
+        let car = moved_at_start(
+            "Car",
+            Vec2::new(800.0, 450.0),
+            Motion::GoStraight {
+                direction: MovementDirection::Angle(Angle::Degrees(60.0)),
+                speed: Speed::Value(50.0),
+            },
+        );
+        let car = SerialiseObject {
+            size: Size::new(120.0, 80.0),
+            keep_on_screen: true,
+            ..car
+        };
+        let mut game = game_of(vec![car]);
+
+        for _ in 0..60 {
+            run_frames(&mut game, 1)?;
+            let area = game.objects["Car"].bounding_box();
+            assert!(area.min.x >= 0.0 && area.min.y >= 0.0);
+            assert!(area.max.x <= PROJECTION_WIDTH && area.max.y <= PROJECTION_HEIGHT);
+        }
+        assert_near(game.objects["Car"].position, Vec2::new(1540.0, 40.0));
+        Ok(())
+    }
+
+    fn frames_shown(animate: Action, frames: u32) -> WeeResult<Vec<Sprite>> {
+        let animated = SerialiseObject::builder()
+            .name("Animated")
+            .sprite(frame_image(0))
+            .instruction(instruction(vec![Trigger::Time(When::Start)], vec![animate]))
+            .build();
+        let mut game = game_of(vec![animated]);
+
+        let mut shown = Vec::new();
+        for _ in 0..frames {
+            run_frames(&mut game, 1)?;
+            shown.push(game.objects["Animated"].sprite.clone());
+        }
+        Ok(shown)
+    }
+
+    fn sprite_changes(shown: Vec<Sprite>) -> Vec<Sprite> {
+        let mut changes = shown;
+        changes.dedup();
+        changes
+    }
+
+    #[test]
+    fn ping_pong_counts_up_then_back_down() -> WeeResult<()> {
+        let shown = frames_shown(
+            Action::Animate {
+                animation_type: AnimationType::PingPong,
+                sprites: (0..3).map(frame_image).collect(),
+                speed: Speed::Fast,
+                reverse: false,
+            },
+            35,
+        )?;
+
+        let expected: Vec<Sprite> = [0, 1, 2, 1, 0, 1, 2]
+            .iter()
+            .map(|i| frame_image(*i))
+            .collect();
+        assert_eq!(sprite_changes(shown)[..7], expected[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn reversed_loop_counts_down() -> WeeResult<()> {
+        let shown = frames_shown(
+            Action::Animate {
+                animation_type: AnimationType::Loop,
+                sprites: (0..3).map(frame_image).collect(),
+                speed: Speed::Fast,
+                reverse: true,
+            },
+            35,
+        )?;
+
+        let expected: Vec<Sprite> = [2, 1, 0, 2, 1, 0, 2]
+            .iter()
+            .map(|i| frame_image(*i))
+            .collect();
+        assert_eq!(sprite_changes(shown)[..7], expected[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn timed_frames_are_held_for_their_own_duration() -> WeeResult<()> {
+        let shown = frames_shown(
+            Action::AnimateTimed {
+                frames: vec![
+                    (frame_image(0), 5),
+                    (frame_image(1), 30),
+                    (frame_image(2), 5),
+                ],
+                animation_type: AnimationType::PlayOnce,
+            },
+            80,
+        )?;
+
+        let held_for = |sprite: Sprite| shown.iter().filter(|shown| **shown == sprite).count();
+        assert_eq!(held_for(frame_image(1)), 30);
+        Ok(())
+    }
+
+    fn hover_scores(cooldown: u32) -> WeeResult<Vec<usize>> {
+        let button = SerialiseObject::builder()
+            .name("Button")
+            .instruction(Instruction {
+                triggers: vec![Trigger::Input(Input::Mouse {
+                    over: MouseOver::Object {
+                        name: "Button".to_string(),
+                    },
+                    interaction: MouseInteraction::Hover,
+                })],
+                actions: vec![Action::AdjustScore(1)],
+                cooldown,
+            })
+            .build();
+        let mut game = game_of(vec![button]);
+
+        let mut scores = Vec::new();
+        for _ in 0..90 {
+            let hovering = vec![mouse_at(800.0, 450.0, ButtonState::Up)];
+            game.run_headless(hovering.into_iter(), 1)?;
+            scores.push(game.score_adjustment);
+        }
+        Ok(frames_scored_on(&scores))
+    }
+
+    #[test]
+    fn hover_with_cooldown_fires_at_most_once_per_cooldown() -> WeeResult<()> {
+        assert_eq!(hover_scores(30)?, vec![0, 30, 60]);
+        Ok(())
+    }
+
+    #[test]
+    fn hover_without_cooldown_fires_every_frame() -> WeeResult<()> {
+        assert_eq!(hover_scores(0)?.len(), 90);
+        Ok(())
+    }
+
+    fn heading_after_moving(motion: Motion) -> WeeResult<f32> {
+        let fish = SerialiseObject::builder()
+            .name("Fish")
+            .angle(30.0)
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::Motion(motion)],
+            ))
+            .instruction(instruction(
+                vec![Trigger::Time(When::Exact { time: 1 })],
+                vec![Action::SetProperty(PropertySetter::Angle(
+                    AngleSetter::RotateToVelocity,
+                ))],
+            ))
+            .build();
+        let mut game = game_of(vec![fish]);
+
+        run_frames(&mut game, 2)?;
+        Ok(game.objects["Fish"].angle)
+    }
+
+    #[test]
+    fn rotate_to_velocity_points_up_right() -> WeeResult<()> {
+        let angle = heading_after_moving(Motion::GoStraight {
+            direction: MovementDirection::Angle(Angle::Degrees(45.0)),
+            speed: Speed::Value(6.0),
+        })?;
+
+        assert!((angle - 45.0).abs() < 0.01);
+        Ok(())
+    }
+
+    #[test]
+    fn rotate_to_velocity_keeps_the_angle_when_still() -> WeeResult<()> {
+        assert_eq!(heading_after_moving(Motion::Stop)?, 30.0);
+        Ok(())
+    }
+
+    fn every_frame(action: Action) -> Instruction {
+        instruction(Vec::new(), vec![action])
+    }
+
+    #[test]
+    fn copy_size_tracks_the_leader_every_frame() -> WeeResult<()> {
+        let leader = SerialiseObject::builder()
+            .name("Leader")
+            .instruction(every_frame(Action::SetProperty(PropertySetter::Size(
+                SizeSetter::Grow(SizeDifference::Value(Size::new(2.0, 1.0))),
+            ))))
+            .build();
+        let shadow = SerialiseObject::builder()
+            .name("Shadow")
+            .size(Size::new(10.0, 10.0))
+            .instruction(every_frame(Action::SetProperty(PropertySetter::CopySize {
+                name: "Leader".to_string(),
+            })))
+            .build();
+        let mut game = game_of(vec![leader, shadow]);
+
+        for _ in 0..10 {
+            run_frames(&mut game, 1)?;
+            assert_eq!(game.objects["Shadow"].size, game.objects["Leader"].size);
+        }
+        assert_eq!(game.objects["Shadow"].size, Size::new(120.0, 110.0));
+        Ok(())
+    }
+
+    #[test]
+    fn copying_from_a_missing_object_is_an_error() {
+        let mut game = scores_at_start(Action::SetProperty(PropertySetter::CopySize {
+            name: "Missing".to_string(),
+        }));
+
+        assert!(run_frames(&mut game, 1).is_err());
+    }
+
+    fn walk_sequence() -> AssetFiles {
+        let mut asset_files = AssetFiles::default();
+        asset_files
+            .images
+            .insert("Crate".to_string(), "crate.png".to_string());
+        asset_files.sequences.insert(
+            "walk".to_string(),
+            ImageSequence {
+                pattern: "walk_{}.png".to_string(),
+                count: 3,
+                first: 1,
+            },
+        );
+        asset_files
+    }
+
+    #[test]
+    fn sequence_loads_one_image_per_frame_under_its_name() {
+        let files = walk_sequence().image_files();
+
+        assert_eq!(files.len(), 4);
+        assert_eq!(files["Crate"], "crate.png");
+        assert_eq!(files["walk#0"], "walk_1.png");
+        assert_eq!(files["walk#1"], "walk_2.png");
+        assert_eq!(files["walk#2"], "walk_3.png");
+    }
+
+    #[test]
+    fn animate_sequence_cycles_through_its_frames() -> WeeResult<()> {
+        let walker = SerialiseObject::builder()
+            .name("Walker")
+            .sprite(Sprite::Image {
+                name: "Crate".to_string(),
+            })
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::AnimateSequence {
+                    name: "walk".to_string(),
+                    animation_type: AnimationType::Loop,
+                    speed: Speed::Fast,
+                    reverse: false,
+                }],
+            ))
+            .build();
+        let game_data = GameData::builder()
+            .object(walker)
+            .asset_files(walk_sequence())
+            .length(Length::Seconds(4.0))
+            .build();
+        let mut game = Game::from_data(game_data);
+
+        let mut shown = Vec::new();
+        for _ in 0..30 {
+            run_frames(&mut game, 1)?;
+            shown.push(game.objects["Walker"].sprite.clone());
+        }
+
+        let walk_frame = |index| Sprite::Image {
+            name: ImageSequence::frame_name("walk", index),
+        };
+        let expected = [walk_frame(0), walk_frame(1), walk_frame(2), walk_frame(0)];
+        assert_eq!(sprite_changes(shown)[..4], expected[..]);
+        Ok(())
+    }
+
+    #[test]
+    fn sound_finished_fires_once_the_sound_has_played_through() -> WeeResult<()> {
+        let listener = SerialiseObject::builder()
+            .name("Listener")
+            .instruction(instruction(
+                vec![Trigger::SoundFinished {
+                    name: "Ding".to_string(),
+                }],
+                vec![Action::Win],
+            ))
+            .build();
+        let mut game = game_of(vec![listener]);
+        game.track_sound("Ding", 0.5);
+
+        for _ in 0..29 {
+            assert!(!has_won(run_frames(&mut game, 1)?));
+        }
+        assert!(has_won(run_frames(&mut game, 1)?));
+        Ok(())
+    }
+
+    #[test]
+    fn fired_instructions_show_up_in_the_trace_once() -> WeeResult<()> {
+        let lamp = SerialiseObject::builder()
+            .name("Lamp")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Exact { time: 1000 })],
+                vec![],
+            ))
+            .instruction(instruction(
+                vec![Trigger::Time(When::Exact { time: 3 })],
+                vec![],
+            ))
+            .build();
+        let mut game = game_of(vec![lamp]);
+        game.enable_trace();
+
+        run_frames(&mut game, 10)?;
+
+        let trace = game.take_trace();
+        assert_eq!(trace.len(), 1);
+        assert_eq!(trace[0].frame, 3);
+        assert_eq!(trace[0].fired.len(), 1);
+        assert_eq!(trace[0].fired[0].object, "Lamp");
+        assert_eq!(trace[0].fired[0].instruction, 1);
+        assert!(game.take_trace().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn wave_offset_peaks_at_a_quarter_period() {
+        let offset = |frame| wave_offset(30.0, 60, frame);
+        assert!(offset(0).abs() < 0.01);
+        assert!((offset(15) - 30.0).abs() < 0.01);
+        assert!(offset(30).abs() < 0.01);
+        assert!((offset(45) + 30.0).abs() < 0.01);
+        assert!((offset(75) - 30.0).abs() < 0.01);
+        assert_eq!(wave_offset(30.0, 0, 15), 0.0);
+    }
+
+    #[test]
+    fn end_fires_on_the_extended_last_frame() -> WeeResult<()> {
+        let ender = SerialiseObject::builder()
+            .name("Ender")
+            .instruction(instruction(vec![Trigger::Time(When::End)], vec![]))
+            .build();
+        let mut game = game_of(vec![ender]);
+        game.extend_length(1.5);
+        game.enable_trace();
+
+        run_frames(&mut game, 239)?;
+        assert!(!game.frames.is_final());
+        run_frames(&mut game, 1000)?;
+
+        let fired_on: Vec<u32> = game.take_trace().iter().map(|trace| trace.frame).collect();
+        assert_eq!(fired_on, vec![359]);
+        assert_eq!(game.frames.ran, 360);
+        Ok(())
+    }
+
+    #[test]
+    fn collision_checks_see_objects_moved_earlier_in_the_frame() -> WeeResult<()> {
+        let wall = SerialiseObject::builder()
+            .name("Wall")
+            .position(Vec2::new(1400.0, 450.0))
+            .build();
+        let player = SerialiseObject::builder()
+            .name("Player")
+            .position(Vec2::new(100.0, 450.0))
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![
+                    Action::SetProperty(PropertySetter::CopyPosition {
+                        name: "Wall".to_string(),
+                        offset: Vec2::zero(),
+                    }),
+                    Action::If {
+                        check: Trigger::Collision(CollisionWith::Object {
+                            name: "Wall".to_string(),
+                        }),
+                        then: vec![Action::Win],
+                        otherwise: vec![],
+                    },
+                ],
+            ))
+            .build();
+        let mut game = game_of(vec![wall, player]);
+
+        assert!(has_won(run_frames(&mut game, 1)?));
+        Ok(())
+    }
+
+    #[test]
+    fn toggled_ping_pong_keeps_repeating() {
+        assert_eq!(!AnimationType::PingPong, AnimationType::Loop);
+        assert_eq!(!AnimationType::Loop, AnimationType::PlayOnce);
+        assert_eq!(!AnimationType::PlayOnce, AnimationType::Loop);
+    }
+
+    #[test]
+    fn damaging_an_object_without_health_is_skipped() -> WeeResult<()> {
+        let object = SerialiseObject::builder()
+            .name("Scorer")
+            .instruction(instruction(
+                vec![Trigger::Time(When::Start)],
+                vec![Action::Damage(1), Action::AdjustScore(1)],
+            ))
+            .build();
+        let mut game = game_of(vec![object]);
+
+        run_frames(&mut game, 1)?;
+
+        assert_eq!(game.score_adjustment, 1);
+        assert_eq!(game.objects["Scorer"].health(), None);
+        Ok(())
+    }
+}